@@ -0,0 +1,208 @@
+// The shaders of `VoxelMaterial`. The fragment shader is Bevy's PBR shader cut down to a base color texture, with the
+// atlas UVs wrapped into their tile so the tile repeats once per voxel, the texture of flowing voxels scrolled, and
+// the glow of emissive voxels added on top. A normal map laid out like the atlas gets the same wrapping. With
+// `VOXEL_TEXTURE_ARRAY` the tile is sampled from its layer of the texture array instead. The AO comes
+// in its own attribute and is applied as `material.ao_shading` says. Grid lines along the voxel edges darken the base
+// color before lighting, and distance fog goes on last, before tonemapping.
+
+#import bevy_pbr::mesh_view_bindings
+#import bevy_pbr::mesh_bindings
+#import bevy_pbr::pbr_types
+#import bevy_pbr::mesh_functions
+
+#import bevy_pbr::utils
+#import bevy_pbr::clustered_forward
+#import bevy_pbr::lighting
+#import bevy_pbr::shadows
+#import bevy_pbr::pbr_functions
+
+struct VoxelMaterial {
+    base_color: vec4<f32>,
+    tiles: vec2<f32>,
+    uv_offset: vec2<f32>,
+    perceptual_roughness: f32,
+    alpha_cutoff: f32,
+    flags: u32,
+    ao_shading: u32,
+    fog_color: vec4<f32>,
+    fog_start: f32,
+    fog_end: f32,
+    grid_line_thickness: f32,
+    grid_line_color: vec4<f32>,
+};
+
+@group(1) @binding(0)
+var<uniform> material: VoxelMaterial;
+@group(1) @binding(1)
+var base_color_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var base_color_sampler: sampler;
+@group(1) @binding(3)
+var normal_map_texture: texture_2d<f32>;
+@group(1) @binding(4)
+var normal_map_sampler: sampler;
+@group(1) @binding(5)
+var texture_array: texture_2d_array<f32>;
+@group(1) @binding(6)
+var texture_array_sampler: sampler;
+
+// Has to match `TILE_UV_STRIDE` in atlas.rs.
+let TILE_UV_STRIDE: f32 = 32.0;
+// The values of `AoShading` in material.rs.
+let AO_SHADING_ALBEDO: u32 = 0u;
+let AO_SHADING_AMBIENT: u32 = 1u;
+
+// The layout `VoxelMaterial::specialize` sets up.
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+    @location(3) tangent: vec4<f32>,
+#ifdef VERTEX_COLORS
+    @location(4) color: vec4<f32>,
+#endif
+    @location(5) emissive: vec3<f32>,
+    @location(6) flow: f32,
+#ifdef VOXEL_AO
+    @location(7) ao: vec4<f32>,
+#endif
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    #import bevy_pbr::mesh_vertex_output
+    @location(5) emissive: vec3<f32>,
+    @location(6) flow: f32,
+#ifdef VOXEL_AO
+    @location(7) ao: vec4<f32>,
+#endif
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    out.world_normal = mesh_normal_local_to_world(vertex.normal);
+    out.world_position = mesh_position_local_to_world(mesh.model, vec4<f32>(vertex.position, 1.0));
+    out.clip_position = mesh_position_world_to_clip(out.world_position);
+    out.uv = vertex.uv;
+    out.world_tangent = mesh_tangent_local_to_world(mesh.model, vertex.tangent);
+#ifdef VERTEX_COLORS
+    out.color = vertex.color;
+#endif
+    out.emissive = vertex.emissive;
+    out.flow = vertex.flow;
+#ifdef VOXEL_AO
+    out.ao = vertex.ao;
+#endif
+    return out;
+}
+
+struct FragmentInput {
+    @builtin(front_facing) is_front: bool,
+    @builtin(position) frag_coord: vec4<f32>,
+    #import bevy_pbr::mesh_vertex_output
+    @location(5) emissive: vec3<f32>,
+    @location(6) flow: f32,
+#ifdef VOXEL_AO
+    @location(7) ao: vec4<f32>,
+#endif
+};
+
+@fragment
+fn fragment(in: FragmentInput) -> @location(0) vec4<f32> {
+    var output_color: vec4<f32> = material.base_color;
+    // `tile` is the column and row of the tile counted from 0, the rest counts voxels across the quad.
+    let tile = floor(in.uv / TILE_UV_STRIDE);
+    let voxel_uv = in.uv - tile * TILE_UV_STRIDE;
+    // Scrolling before the wrap keeps flowing textures inside their own tile.
+    let atlas_uv = (tile + fract(voxel_uv + in.flow * material.uv_offset)) / material.tiles;
+    // The gradients come from the unwrapped UVs, otherwise the jump at every voxel edge picks the smallest mip.
+    let unwrapped = voxel_uv / material.tiles;
+    let uv_dx = dpdx(unwrapped);
+    let uv_dy = dpdy(unwrapped);
+
+    var texture_color = vec4<f32>(1.0);
+    if ((material.flags & STANDARD_MATERIAL_FLAGS_BASE_COLOR_TEXTURE_BIT) != 0u) {
+#ifdef VOXEL_TEXTURE_ARRAY
+        // The layers go through the atlas row by row, like `AtlasConfig::layer`.
+        let layer = i32(tile.y * material.tiles.x + tile.x);
+        let layer_uv = fract(voxel_uv + in.flow * material.uv_offset);
+        texture_color = textureSampleGrad(texture_array, texture_array_sampler, layer_uv, layer, dpdx(voxel_uv), dpdy(voxel_uv));
+#else
+        texture_color = textureSampleGrad(base_color_texture, base_color_sampler, atlas_uv, uv_dx, uv_dy);
+#endif
+    }
+#ifdef VERTEX_COLORS
+    output_color = output_color * in.color;
+#endif
+    output_color = output_color * texture_color;
+
+    var ao = vec4<f32>(1.0);
+#ifdef VOXEL_AO
+    ao = in.ao;
+#endif
+    var occlusion = 1.0;
+    if (material.ao_shading == AO_SHADING_ALBEDO) {
+        output_color = output_color * ao;
+    } else if (material.ao_shading == AO_SHADING_AMBIENT) {
+        // The luminance of the AO color, since Bevy only takes a single occlusion value.
+        occlusion = dot(ao.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+    }
+
+    // Like `GridLines::coverage`, with the edge of the line smoothed over a pixel so it doesn't flicker in the distance.
+    let pixel = fwidth(voxel_uv);
+    if (material.grid_line_thickness > 0.0) {
+        let cell = fract(voxel_uv);
+        let edge = min(cell, 1.0 - cell);
+        let thickness = vec2<f32>(material.grid_line_thickness);
+        let line = 1.0 - smoothstep(thickness, thickness + pixel, edge);
+        let coverage = max(line.x, line.y) * material.grid_line_color.a;
+        output_color = vec4<f32>(mix(output_color.rgb, material.grid_line_color.rgb, coverage), output_color.a);
+    }
+
+    var pbr_input: PbrInput = pbr_input_new();
+    pbr_input.material.base_color = output_color;
+    pbr_input.material.perceptual_roughness = material.perceptual_roughness;
+    pbr_input.material.flags = material.flags;
+    pbr_input.material.alpha_cutoff = material.alpha_cutoff;
+    pbr_input.occlusion = occlusion;
+    pbr_input.material.emissive = vec4<f32>(in.emissive * texture_color.rgb, 1.0);
+
+    pbr_input.frag_coord = in.frag_coord;
+    pbr_input.world_position = in.world_position;
+    pbr_input.world_normal = prepare_world_normal(in.world_normal, false, in.is_front);
+    pbr_input.is_orthographic = view.projection[3].w == 1.0;
+    var N = pbr_input.world_normal;
+#ifdef VOXEL_NORMAL_MAP
+    // The same mikktspace mapping as Bevy's `apply_normal_mapping`, which can't be used since it samples its own
+    // texture without the tile wrapping.
+    let T = in.world_tangent.xyz;
+    let B = in.world_tangent.w * cross(N, T);
+    let Nt = textureSampleGrad(normal_map_texture, normal_map_sampler, atlas_uv, uv_dx, uv_dy).rgb * 2.0 - 1.0;
+    N = Nt.x * T + Nt.y * B + Nt.z * N;
+#endif
+    pbr_input.N = normalize(N);
+    pbr_input.V = calculate_view(in.world_position, pbr_input.is_orthographic);
+    output_color = pbr(pbr_input);
+
+    // Linear between the start and end distance, like `FogSettings::amount`.
+    if (material.fog_end > material.fog_start) {
+        let distance = length(in.world_position.xyz - view.world_position);
+        let fog = clamp((distance - material.fog_start) / (material.fog_end - material.fog_start), 0.0, 1.0);
+        output_color = vec4<f32>(mix(output_color.rgb, material.fog_color.rgb, fog), output_color.a);
+    }
+
+#ifdef TONEMAP_IN_SHADER
+    output_color = tone_mapping(output_color);
+#endif
+#ifdef DEBAND_DITHER
+    var output_rgb = output_color.rgb;
+    output_rgb = pow(output_rgb, vec3<f32>(1.0 / 2.2));
+    output_rgb = output_rgb + screen_space_dither(in.frag_coord.xy);
+    // This conversion back to linear space is required because our output texture format is
+    // SRGB; the GPU will assume our output is linear and will apply an SRGB conversion.
+    output_rgb = pow(output_rgb, vec3<f32>(2.2));
+    output_color = vec4(output_rgb, output_color.a);
+#endif
+    return output_color;
+}
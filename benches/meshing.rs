@@ -0,0 +1,170 @@
+//! Compares the simple and greedy meshers on a chunk of terrain. Run with `cargo bench`; the time criterion reports is
+//! per chunk, and the quad counts are printed before the measurements start. `mesh_grid` meshes a whole world of 27
+//! chunks instead, once sequentially and once in parallel, and `remesh_after_edit` what it takes to mesh a chunk again
+//! once a voxel in it changed.
+
+use block_mesh::RIGHT_HANDED_Y_UP_CONFIG;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use bevy::prelude::{default, UVec3};
+use uv_mapping::ao::{AoQuality, AoSettings};
+use uv_mapping::atlas::AtlasConfig;
+use uv_mapping::chunk::{gather_padding, ChunkGrid};
+use uv_mapping::diagnostics::count_visible_faces;
+use uv_mapping::meshing::ChunkMeshData;
+use uv_mapping::meshing::{generate_quads, mesh_chunk, CoordinateSystem, MeshingMode, RenderMode};
+use uv_mapping::parallel::{mesh_grid, mesh_grid_parallel};
+use uv_mapping::voxel::{Voxel, VoxelRegistry};
+use uv_mapping::worldgen::{
+    generate_terrain, generate_world, RandomSeed, TerrainConfig, WorldGenerator,
+};
+
+/// [`mesh_grid`] and [`mesh_grid_parallel`] take the same arguments.
+type MeshGridFn = fn(
+    &ChunkGrid,
+    &VoxelRegistry,
+    &AtlasConfig,
+    &AoSettings,
+    MeshingMode,
+    RenderMode,
+    CoordinateSystem,
+) -> Vec<(UVec3, ChunkMeshData)>;
+
+const MODES: [MeshingMode; 2] = [MeshingMode::Simple, MeshingMode::Greedy];
+
+fn meshing(c: &mut Criterion) {
+    // The surface of the default terrain runs through the first chunk, so it has air, grass, dirt and stone.
+    let mut grid = ChunkGrid::new(1);
+    generate_terrain(&mut grid, UVec3::ZERO, &TerrainConfig::default());
+    let (_, chunk) = grid.chunks().next().unwrap();
+    let registry = VoxelRegistry::default();
+    let atlas = AtlasConfig::default();
+    let ao_settings = AoSettings::default();
+    let faces = RIGHT_HANDED_Y_UP_CONFIG.faces;
+
+    let visibility = count_visible_faces(&chunk.voxels, &registry);
+    println!(
+        "{} of {} filled voxels are interior",
+        visibility.interior, visibility.filled
+    );
+    for mode in MODES {
+        let quads: usize = generate_quads(&chunk.voxels, &registry, mode, &faces)
+            .iter()
+            .map(Vec::len)
+            .sum();
+        println!("{mode:?}: {quads} quads per chunk");
+    }
+
+    let mut group = c.benchmark_group("quads");
+    for mode in MODES {
+        group.bench_function(BenchmarkId::from_parameter(format!("{mode:?}")), |b| {
+            b.iter(|| generate_quads(&chunk.voxels, &registry, mode, &faces))
+        });
+    }
+    group.finish();
+
+    // The whole mesh, with the AO, UVs and colors the quads turn into.
+    let mut group = c.benchmark_group("mesh_chunk");
+    for mode in MODES {
+        group.bench_function(BenchmarkId::from_parameter(format!("{mode:?}")), |b| {
+            b.iter(|| {
+                mesh_chunk(
+                    &chunk.voxels,
+                    &registry,
+                    &atlas,
+                    &ao_settings,
+                    mode,
+                    RenderMode::Textured,
+                    CoordinateSystem::default(),
+                    1.0,
+                )
+            })
+        });
+    }
+    group.finish();
+
+    // What the AO lookups cost, against greedy meshing since that's the one that's left once the quads are cheap.
+    let mut group = c.benchmark_group("ao_quality");
+    for quality in [AoQuality::Full, AoQuality::EdgesOnly, AoQuality::Off] {
+        let ao_settings = AoSettings {
+            quality,
+            ..default()
+        };
+        group.bench_function(BenchmarkId::from_parameter(format!("{quality:?}")), |b| {
+            b.iter(|| {
+                mesh_chunk(
+                    &chunk.voxels,
+                    &registry,
+                    &atlas,
+                    &ao_settings,
+                    MeshingMode::Greedy,
+                    RenderMode::Textured,
+                    CoordinateSystem::default(),
+                    1.0,
+                )
+            })
+        });
+    }
+    group.finish();
+
+    // A whole world of terrain, one chunk after the other and on every core.
+    let mut world = ChunkGrid::new(3);
+    generate_world(
+        &mut world,
+        WorldGenerator::Terrain,
+        &TerrainConfig::default(),
+        RandomSeed(0),
+    );
+    let mut group = c.benchmark_group("mesh_grid");
+    for (name, mesh_grid) in [
+        ("sequential", mesh_grid as MeshGridFn),
+        ("parallel", mesh_grid_parallel),
+    ] {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                mesh_grid(
+                    &world,
+                    &registry,
+                    &atlas,
+                    &ao_settings,
+                    MeshingMode::Greedy,
+                    RenderMode::Textured,
+                    CoordinateSystem::default(),
+                )
+            })
+        });
+    }
+    group.finish();
+
+    // Editing a voxel remeshes the chunks it touches from a fresh padded copy, which has to fit into a frame.
+    let mut group = c.benchmark_group("remesh_after_edit");
+    for mode in MODES {
+        group.bench_function(BenchmarkId::from_parameter(format!("{mode:?}")), |b| {
+            let mut placed = false;
+            b.iter(|| {
+                placed = !placed;
+                let voxel = if placed {
+                    Voxel::STONE_VOXEL
+                } else {
+                    Voxel::EMPTY_VOXEL
+                };
+                let touched = world.set_voxel(UVec3::new(30, 20, 30), voxel);
+                let voxels = gather_padding(&world, touched[0]);
+                mesh_chunk(
+                    &voxels,
+                    &registry,
+                    &atlas,
+                    &ao_settings,
+                    mode,
+                    RenderMode::Textured,
+                    CoordinateSystem::default(),
+                    1.0,
+                )
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, meshing);
+criterion_main!(benches);
@@ -21,6 +21,8 @@ pub trait MergeVoxel: Voxel {
     /// in the same quad. Often this is some material identifier so that the same texture can be used for a full quad.
     fn merge_value(&self) -> Self::MergeValue;
 
+    /// The value used to determine if this voxel can join a given quad in the mesh, taken from the voxel on the visible
+    /// side of the face. Quads only merge while the voxels they face have equal values.
     fn merge_value_facing_neighbour(&self) -> Self::MergeValueFacingNeighbour;
 }
 
@@ -257,11 +259,13 @@ where
     let adjacent_voxel =
         voxels.get_unchecked(voxel_stride.wrapping_add(visibility_offset) as usize);
 
-    // TODO: If the face lies between two transparent voxels, we choose not to mesh it. We might need to extend the IsOpaque
-    // trait with different levels of transparency to support this.
+    // Faces between two translucent voxels are left to `Voxel::shows_face_to_translucent`.
     match adjacent_voxel.get_visibility() {
         VoxelVisibility::Empty => true,
-        VoxelVisibility::Translucent => voxel.get_visibility() == VoxelVisibility::Opaque,
+        VoxelVisibility::Translucent => {
+            voxel.get_visibility() == VoxelVisibility::Opaque
+                || voxel.shows_face_to_translucent(adjacent_voxel)
+        }
         VoxelVisibility::Opaque => false,
     }
 }
@@ -302,6 +306,42 @@ mod tests {
         );
     }
 
+    #[test]
+    fn only_uniform_ao_keeps_shaded_faces_apart() {
+        // A row of voxels with another row on top of it, one step back, so the tops of the lower row are occluded along
+        // one edge. Every top has the same corner AO, but its corners differ from each other.
+        let mut samples = [EMPTY; SampleShape::SIZE as usize];
+        for x in 1..=4 {
+            samples[<SampleShape as ConstShape<3>>::linearize([x, 1, 1]) as usize] =
+                BoolVoxel(true);
+            samples[<SampleShape as ConstShape<3>>::linearize([x, 2, 2]) as usize] =
+                BoolVoxel(true);
+        }
+        let count = |uniform: bool| {
+            let mut buffer = GreedyQuadsBuffer::new(samples.len());
+            let mesh = if uniform {
+                greedy_quads_with_merge_strategy::<_, _, VoxelMerger<_, true, true>>
+            } else {
+                greedy_quads
+            };
+            mesh(
+                &samples,
+                &SampleShape {},
+                [0; 3],
+                [33; 3],
+                &RIGHT_HANDED_Y_UP_CONFIG.faces,
+                &mut buffer,
+            );
+            buffer.quads.num_quads()
+        };
+        assert!(
+            count(false) < count(true),
+            "{} {}",
+            count(false),
+            count(true)
+        );
+    }
+
     type SampleShape = ConstShape3u32<34, 34, 34>;
 
     /// Basic voxel type with one byte of texture layers
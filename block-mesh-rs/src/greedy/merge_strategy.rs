@@ -54,11 +54,20 @@ pub struct FaceStrides {
     pub face_index: u8,
 }
 
-pub struct VoxelMerger<T> {
+/// Merges faces of voxels with equal merge values.
+///
+/// With `MATCH_AO`, which is the default, faces also need the same corner AO. Without it, the AO of each quad is the AO
+/// of its first voxel, and callers that shade quads should work the corners out themselves.
+///
+/// With `UNIFORM_AO` on top of that, only faces whose four corners are equally occluded grow past a single voxel, so
+/// merging never stretches the shading of one face across a quad. It's off by default, which merges as much as
+/// upstream block-mesh does.
+pub struct VoxelMerger<T, const MATCH_AO: bool = true, const UNIFORM_AO: bool = false> {
     marker: std::marker::PhantomData<T>,
 }
 
-impl<T> MergeStrategy for VoxelMerger<T>
+impl<T, const MATCH_AO: bool, const UNIFORM_AO: bool> MergeStrategy
+    for VoxelMerger<T, MATCH_AO, UNIFORM_AO>
 where
     T: MergeVoxel,
 {
@@ -126,7 +135,7 @@ where
     }
 }
 
-impl<T> VoxelMerger<T> {
+impl<T, const MATCH_AO: bool, const UNIFORM_AO: bool> VoxelMerger<T, MATCH_AO, UNIFORM_AO> {
     fn calculate_ao(
         voxels: &[T],
         visibility_offset: u32,
@@ -300,19 +309,31 @@ impl<T> VoxelMerger<T> {
                 aos.insert((quad_start_stride, current_face), ao);
             }
 
+            let quad_start_ao = aos.get(&(quad_start_stride, current_face)).unwrap();
             if !voxel.merge_value().eq(quad_merge_voxel_value)
                 || !neighbour
                     .merge_value_facing_neighbour()
                     .eq(quad_merge_voxel_value_facing_neighbour)
-                || !(aos
-                    .get(&(row_stride, current_face))
-                    .unwrap()
-                    .eq(aos.get(&(quad_start_stride, current_face)).unwrap()))
+                || (MATCH_AO
+                    && !(aos
+                        .get(&(row_stride, current_face))
+                        .unwrap()
+                        .eq(quad_start_ao)))
             {
                 // Voxel needs to be non-empty and match the quad merge value.
                 break;
             }
 
+            // The corner AO values get interpolated over the whole quad, so a quad can only grow past its first voxel when
+            // every corner has the same occlusion. Otherwise the shading of each unit face would be stretched across the
+            // merged quad.
+            if UNIFORM_AO
+                && row_stride != quad_start_stride
+                && quad_start_ao.iter().any(|ao| *ao != quad_start_ao[0])
+            {
+                break;
+            }
+
             quad_width += 1;
             row_stride += delta_stride;
         }
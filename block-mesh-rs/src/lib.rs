@@ -38,10 +38,15 @@
 //!
 //! impl MergeVoxel for BoolVoxel {
 //!     type MergeValue = Self;
+//!     type MergeValueFacingNeighbour = Self;
 //!
 //!     fn merge_value(&self) -> Self::MergeValue {
 //!         *self
 //!     }
+//!
+//!     fn merge_value_facing_neighbour(&self) -> Self::MergeValueFacingNeighbour {
+//!         *self
+//!     }
 //! }
 //!
 //! // A 16^3 chunk with 1-voxel boundary padding.
@@ -102,6 +107,13 @@ pub enum VoxelVisibility {
 /// how to generate geometry for this voxel.
 pub trait Voxel {
     fn get_visibility(&self) -> VoxelVisibility;
+
+    /// Whether this translucent voxel gets a face towards the translucent `neighbour`. By default it doesn't, so
+    /// touching translucent voxels look like one volume.
+    #[inline]
+    fn shows_face_to_translucent(&self, _neighbour: &Self) -> bool {
+        false
+    }
 }
 
 /// Used as a dummy for functions that must wrap a voxel
@@ -113,6 +125,11 @@ impl<'a, T: Voxel> Voxel for IdentityVoxel<'a, T> {
     fn get_visibility(&self) -> VoxelVisibility {
         self.0.get_visibility()
     }
+
+    #[inline]
+    fn shows_face_to_translucent(&self, neighbour: &Self) -> bool {
+        self.0.shows_face_to_translucent(neighbour.0)
+    }
 }
 
 impl<'a, T: Voxel> From<&'a T> for IdentityVoxel<'a, T> {
@@ -71,11 +71,13 @@ pub fn visible_block_faces_with_voxel_view<'a, T, V, S>(
             let neighbor_index = p_index.wrapping_add(face_stride);
             let neighbor_voxel = V::from(unsafe { voxels.get_unchecked(neighbor_index as usize) });
 
-            // TODO: If the face lies between two transparent voxels, we choose not to mesh it. We might need to extend the
-            // IsOpaque trait with different levels of transparency to support this.
+            // Faces between two translucent voxels are left to `Voxel::shows_face_to_translucent`.
             let face_needs_mesh = match neighbor_voxel.get_visibility() {
                 VoxelVisibility::Empty => true,
-                VoxelVisibility::Translucent => p_voxel.get_visibility() == VoxelVisibility::Opaque,
+                VoxelVisibility::Translucent => {
+                    p_voxel.get_visibility() == VoxelVisibility::Opaque
+                        || p_voxel.shows_face_to_translucent(&neighbor_voxel)
+                }
                 VoxelVisibility::Opaque => false,
             };
 
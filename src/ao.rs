@@ -0,0 +1,623 @@
+use bevy::prelude::*;
+use block_mesh::ndshape::ConstShape;
+use block_mesh::{OrientedBlockFace, UnorientedQuad, VoxelVisibility};
+
+use crate::input::{Action, KeyBindings};
+use crate::volume::VoxelVolume;
+use crate::voxel::VoxelRegistry;
+
+/// The most open AO level block-mesh produces. 0 is the most occluded corner. Longer ramps of
+/// [`AoSettings::stops`] raise it, see [`AoSettings::levels`].
+pub const MAX_AO: u8 = 3;
+
+/// How AO levels pick a color from [`AoSettings::stops`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AoInterpolation {
+    /// Each level uses the stop it lands on, rounding down.
+    #[default]
+    Step,
+    /// Levels that land between two stops blend them linearly.
+    Smooth,
+}
+
+/// How many neighbours each vertex looks at for its AO. Press Q to cycle through them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AoQuality {
+    /// The two voxels beside the corner and the one diagonal to it.
+    #[default]
+    Full,
+    /// Only the two voxels beside the corner, which saves a lookup per vertex. Inside corners still get dark, but the
+    /// corners a single voxel casts onto the floor next to it disappear.
+    EdgesOnly,
+    /// No AO at all. Meshes without voxel colors also leave out the color attribute, see
+    /// [`crate::meshing::MeshData::into_mesh`].
+    Off,
+    /// Casts [`AO_RAYS`] short rays from every vertex and counts how many of them hit something, see
+    /// [`raymarched_ao`]. That also darkens the floors of pits and the inside of deep corners, not just the voxels
+    /// right next to a vertex, but it's much slower to mesh, so it's meant for stills.
+    Raymarched,
+}
+
+/// How many rays [`AoQuality::Raymarched`] casts from each vertex.
+pub const AO_RAYS: usize = 16;
+/// How far those rays go, in voxels.
+pub const AO_RAY_LENGTH: f32 = 4.0;
+/// The distance between the points each ray checks for voxels, in voxels.
+const AO_RAY_STEP: f32 = 0.25;
+
+/// Controls how the AO levels are turned into vertex colors.
+///
+/// The stops are sRGB, like colors picked in an image editor: a stop of 0.5 makes a white face show up as 50% gray on
+/// screen. Vertex colors are linear, so [`AoSettings::color`] converts them, after interpolating and applying the
+/// strength in sRGB so those steps look even too.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct AoSettings {
+    /// sRGB colors from the most occluded to the most open level, spread evenly over the [`AoSettings::levels`].
+    /// There can be any number of stops, but there has to be at least one.
+    pub stops: Vec<[f32; 4]>,
+    /// How much of the occlusion is applied: 1 uses the stops as they are and 0 turns AO off.
+    pub strength: f32,
+    pub interpolation: AoInterpolation,
+    /// Splits each quad along the diagonal that keeps the AO gradient symmetric, instead of always along the same one.
+    pub flip_quads: bool,
+    pub quality: AoQuality,
+    /// Only lets greedy meshing merge faces whose AO matches, so the occlusion stays where it is. Ramps of more than
+    /// four stops compare the finer levels of [`corner_ao`] too. Turning it off merges by voxel id alone, which saves
+    /// quads but stretches the occlusion of a corner across the merged quad.
+    pub merge_respecting_ao: bool,
+}
+
+impl Default for AoSettings {
+    fn default() -> Self {
+        Self {
+            stops: vec![
+                [0.1, 0.1, 0.1, 1.0],
+                [0.3, 0.3, 0.3, 1.0],
+                [0.5, 0.5, 0.5, 1.0],
+                [0.75, 0.75, 0.75, 1.0],
+            ],
+            strength: 1.0,
+            interpolation: AoInterpolation::Step,
+            flip_quads: true,
+            quality: AoQuality::Full,
+            merge_respecting_ao: true,
+        }
+    }
+}
+
+impl AoSettings {
+    /// How many AO levels the meshes tell apart: one per stop, but never fewer than block-mesh's four. Ramps of more
+    /// than four stops get their levels from [`corner_ao`], which looks further than block-mesh does so the extra
+    /// levels band less on large flat surfaces.
+    pub fn levels(&self) -> u8 {
+        (self.stops.len().min(u8::MAX.into()) as u8).max(MAX_AO + 1)
+    }
+
+    /// The most open level, [`MAX_AO`] unless the ramp is longer than four stops.
+    pub fn max_level(&self) -> u8 {
+        self.levels() - 1
+    }
+
+    /// `levels` stops spread evenly over the current ramp, blending the stops like [`AoInterpolation::Smooth`], for
+    /// comparing the same ramp at a different number of levels.
+    pub fn resampled(&self, levels: usize) -> Vec<[f32; 4]> {
+        let last = (self.stops.len() - 1) as f32;
+        (0..levels)
+            .map(|level| {
+                let position = level as f32 / (levels - 1).max(1) as f32 * last;
+                let lower = position.floor() as usize;
+                let upper = (lower + 1).min(self.stops.len() - 1);
+                let t = position - lower as f32;
+                let (a, b) = (self.stops[lower], self.stops[upper]);
+                [0, 1, 2, 3].map(|i| a[i] + (b[i] - a[i]) * t)
+            })
+            .collect()
+    }
+
+    /// The linear vertex color for one AO level. Levels above [`AoSettings::max_level`] carry no occlusion and stay
+    /// at full brightness.
+    pub fn color(&self, value: u8) -> [f32; 4] {
+        let max_level = self.max_level();
+        if value > max_level {
+            return [1.0; 4];
+        }
+        let position = value as f32 / max_level as f32 * (self.stops.len() - 1) as f32;
+        let lower = position.floor() as usize;
+        let occluded = match self.interpolation {
+            AoInterpolation::Step => self.stops[lower],
+            AoInterpolation::Smooth => {
+                let upper = (lower + 1).min(self.stops.len() - 1);
+                let t = position - lower as f32;
+                let (a, b) = (self.stops[lower], self.stops[upper]);
+                [0, 1, 2, 3].map(|i| a[i] + (b[i] - a[i]) * t)
+            }
+        };
+        // Fade towards white as the strength goes down.
+        let [r, g, b, a] = occluded.map(|c| 1.0 - (1.0 - c) * self.strength);
+        Color::rgba(r, g, b, a).as_linear_rgba_f32()
+    }
+}
+
+/// The AO level of each corner of a quad, in the order of [`OrientedBlockFace::quad_corners`].
+///
+/// Each corner looks at the layer of voxels in front of the face: the two voxels beside the quad's corner voxel and the
+/// one diagonal to it. The corners are taken from the edges of the whole quad, so this also works for merged greedy
+/// quads, whose `ao` from block-mesh only describes the voxel at their minimum. `quality` decides which of those voxels
+/// actually get looked at.
+pub fn quad_corner_ao<S: ConstShape<3, Coord = u32>>(
+    voxels: &VoxelVolume<S>,
+    registry: &VoxelRegistry,
+    face: &OrientedBlockFace,
+    quad: &UnorientedQuad,
+    quality: AoQuality,
+) -> [u8; 4] {
+    match quality {
+        AoQuality::Off => return [MAX_AO; 4],
+        AoQuality::Raymarched => return raymarched_ao(voxels, registry, face, quad),
+        AoQuality::Full | AoQuality::EdgesOnly => {}
+    }
+    let [n, u, v] = face.permutation().axes().map(|axis| axis.index());
+    // Quads on the padding would look past the edge of the volume, which counts as open.
+    let opaque = |[x, y, z]: [i32; 3]| {
+        voxels
+            .get(x as u32, y as u32, z as u32)
+            .is_some_and(|voxel| registry.visibility(voxel) == VoxelVisibility::Opaque)
+    };
+
+    let mut front = quad.minimum.map(|c| c as i32);
+    front[n] += face.n_sign();
+    [(-1, -1), (1, -1), (-1, 1), (1, 1)].map(|(du, dv)| {
+        let mut corner = front;
+        if du > 0 {
+            corner[u] += quad.width as i32 - 1;
+        }
+        if dv > 0 {
+            corner[v] += quad.height as i32 - 1;
+        }
+        let mut side_u = corner;
+        side_u[u] += du;
+        let mut side_v = corner;
+        side_v[v] += dv;
+        let mut diagonal = side_u;
+        diagonal[v] += dv;
+        let diagonal = quality == AoQuality::Full && opaque(diagonal);
+        vertex_ao(opaque(side_u), opaque(side_v), diagonal)
+    })
+}
+
+/// The AO level of each corner of a quad out of the [`AoSettings::levels`] of `settings`.
+///
+/// Four levels are block-mesh's, from [`quad_corner_ao`]. More levels than that need more to tell apart than the three
+/// neighbours of a corner give, so [`AoQuality::Full`] also counts the ring of voxels one further out, which fades
+/// the occlusion out over two voxels instead of one, and [`AoQuality::Raymarched`] keeps the share of open rays
+/// unrounded. [`AoQuality::EdgesOnly`] stays the same as with four levels, just spread over more of them.
+pub fn corner_ao<S: ConstShape<3, Coord = u32>>(
+    voxels: &VoxelVolume<S>,
+    registry: &VoxelRegistry,
+    face: &OrientedBlockFace,
+    quad: &UnorientedQuad,
+    settings: &AoSettings,
+) -> [u8; 4] {
+    let max_level = settings.max_level();
+    if max_level == MAX_AO {
+        return quad_corner_ao(voxels, registry, face, quad, settings.quality);
+    }
+    let openness = match settings.quality {
+        AoQuality::Off => [1.0; 4],
+        AoQuality::Raymarched => raymarched_openness(voxels, registry, face, quad),
+        AoQuality::Full | AoQuality::EdgesOnly => {
+            fine_corner_openness(voxels, registry, face, quad, settings.quality)
+        }
+    };
+    openness.map(|open| (open * max_level as f32).round() as u8)
+}
+
+/// How open each corner of a quad is, from 0 to 1, for [`corner_ao`]: two thirds from the usual [`vertex_ao`] and
+/// one third from the five voxels around the corner's neighbours.
+fn fine_corner_openness<S: ConstShape<3, Coord = u32>>(
+    voxels: &VoxelVolume<S>,
+    registry: &VoxelRegistry,
+    face: &OrientedBlockFace,
+    quad: &UnorientedQuad,
+    quality: AoQuality,
+) -> [f32; 4] {
+    let [n, u, v] = face.permutation().axes().map(|axis| axis.index());
+    let opaque = |[x, y, z]: [i32; 3]| {
+        x >= 0
+            && y >= 0
+            && z >= 0
+            && voxels
+                .get(x as u32, y as u32, z as u32)
+                .is_some_and(|voxel| registry.visibility(voxel) == VoxelVisibility::Opaque)
+    };
+
+    let mut front = quad.minimum.map(|c| c as i32);
+    front[n] += face.n_sign();
+    [(-1, -1), (1, -1), (-1, 1), (1, 1)].map(|(du, dv)| {
+        let mut corner = front;
+        if du > 0 {
+            corner[u] += quad.width as i32 - 1;
+        }
+        if dv > 0 {
+            corner[v] += quad.height as i32 - 1;
+        }
+        let at = |steps_u: i32, steps_v: i32| {
+            let mut voxel = corner;
+            voxel[u] += steps_u * du;
+            voxel[v] += steps_v * dv;
+            opaque(voxel)
+        };
+        let diagonal = quality == AoQuality::Full && at(1, 1);
+        let near = vertex_ao(at(1, 0), at(0, 1), diagonal) as f32 / MAX_AO as f32;
+        if quality == AoQuality::EdgesOnly {
+            return near;
+        }
+        let ring = [(2, 0), (2, 1), (2, 2), (1, 2), (0, 2)];
+        let blocked = ring.iter().filter(|&&(su, sv)| at(su, sv)).count();
+        let far = 1.0 - blocked as f32 / ring.len() as f32;
+        (2.0 * near + far) / 3.0
+    })
+}
+
+/// The AO level of each corner of a quad from the rays of [`AoQuality::Raymarched`]: the share of rays that get
+/// [`AO_RAY_LENGTH`] away from the corner without running into an opaque voxel, rounded to the nearest level.
+///
+/// The rays are spread over the half sphere in front of the face, more of them towards the normal than along the face
+/// so they count like light falling onto it would. Rays that leave the volume count as open, like the padding in
+/// [`quad_corner_ao`]. Merged greedy quads only sample their corners, so their insides get blended from those.
+pub fn raymarched_ao<S: ConstShape<3, Coord = u32>>(
+    voxels: &VoxelVolume<S>,
+    registry: &VoxelRegistry,
+    face: &OrientedBlockFace,
+    quad: &UnorientedQuad,
+) -> [u8; 4] {
+    raymarched_openness(voxels, registry, face, quad)
+        .map(|open| (open * MAX_AO as f32).round() as u8)
+}
+
+/// The share of rays of [`raymarched_ao`] that get away from each corner.
+fn raymarched_openness<S: ConstShape<3, Coord = u32>>(
+    voxels: &VoxelVolume<S>,
+    registry: &VoxelRegistry,
+    face: &OrientedBlockFace,
+    quad: &UnorientedQuad,
+) -> [f32; 4] {
+    let [n, u, v] = face.permutation().axes().map(|axis| axis.index());
+    let mut normal = Vec3::ZERO;
+    normal[n] = face.n_sign() as f32;
+    let (mut axis_u, mut axis_v) = (Vec3::ZERO, Vec3::ZERO);
+    axis_u[u] = 1.0;
+    axis_v[v] = 1.0;
+    // Evenly spread points on a disc, lifted up onto the half sphere, which gives cosine weighted directions.
+    let golden_angle = std::f32::consts::PI * (3.0 - 5f32.sqrt());
+    let directions: [Vec3; AO_RAYS] = std::array::from_fn(|i| {
+        let r = ((i as f32 + 0.5) / AO_RAYS as f32).sqrt();
+        let (sin, cos) = (i as f32 * golden_angle).sin_cos();
+        axis_u * r * cos + axis_v * r * sin + normal * (1.0 - r * r).sqrt()
+    });
+
+    let blocked = |start: Vec3, direction: Vec3| {
+        let steps = (AO_RAY_LENGTH / AO_RAY_STEP) as u32;
+        for step in 1..=steps {
+            let point = (start + direction * (step as f32 * AO_RAY_STEP)).floor();
+            if point.cmplt(Vec3::ZERO).any() {
+                return false;
+            }
+            let [x, y, z] = point.as_uvec3().to_array();
+            match voxels.get(x, y, z) {
+                Some(voxel) if registry.visibility(voxel) == VoxelVisibility::Opaque => {
+                    return true
+                }
+                Some(_) => {}
+                None => return false,
+            }
+        }
+        false
+    };
+
+    face.quad_mesh_positions(quad, 1.0).map(|corner| {
+        // Start just in front of the face, so the rays don't hit the voxel the face belongs to.
+        let start = Vec3::from(corner) + normal * 1e-3;
+        let open = directions
+            .iter()
+            .filter(|&&direction| !blocked(start, direction))
+            .count();
+        open as f32 / AO_RAYS as f32
+    })
+}
+
+/// The usual vertex AO: two sides fully occlude a corner whatever the diagonal is, otherwise every opaque neighbour
+/// takes away one level.
+pub fn vertex_ao(side_u: bool, side_v: bool, diagonal: bool) -> u8 {
+    if side_u && side_v {
+        0
+    } else {
+        MAX_AO - (side_u as u8 + side_v as u8 + diagonal as u8)
+    }
+}
+
+/// The 6 indices of a quad whose 4 vertices start at `start`, like [`OrientedBlockFace::quad_mesh_indices`].
+///
+/// With `flip` the quad is split along the diagonal between the corners with the least light. Splitting it along the
+/// other diagonal leaves the occlusion of a single dark corner in one triangle, which shows up as a visible crease.
+/// `left_handed` reverses the winding, see [`crate::meshing::CoordinateSystem::is_left_handed`].
+pub fn ao_quad_indices(
+    face: &OrientedBlockFace,
+    start: u32,
+    ao: [u8; 4],
+    flip: bool,
+    left_handed: bool,
+) -> [u32; 6] {
+    let mut indices = if !flip || ao[0] + ao[3] >= ao[1] + ao[2] {
+        face.quad_mesh_indices(start)
+    } else if face.n_sign() * face.permutation().sign() > 0 {
+        // Corner 0 to corner 3 instead of 1 to 2, with the same winding.
+        [start, start + 1, start + 3, start, start + 3, start + 2]
+    } else {
+        [start, start + 3, start + 1, start, start + 2, start + 3]
+    };
+    if left_handed {
+        indices.swap(1, 2);
+        indices.swap(4, 5);
+    }
+    indices
+}
+
+pub fn ao_convert(ao: Vec<u8>, settings: &AoSettings) -> Vec<[f32; 4]> {
+    ao.into_iter().map(|value| settings.color(value)).collect()
+}
+
+/// I switches the interpolation, O turns quad flipping on and off, Q cycles the [`AoQuality`], `[` and `]` lower and
+/// raise the strength.
+pub fn ao_controls_system(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut settings: ResMut<AoSettings>,
+) {
+    if bindings.just_pressed(&keys, Action::CycleAoQuality) {
+        settings.quality = match settings.quality {
+            AoQuality::Full => AoQuality::EdgesOnly,
+            AoQuality::EdgesOnly => AoQuality::Off,
+            AoQuality::Off => AoQuality::Raymarched,
+            AoQuality::Raymarched => AoQuality::Full,
+        };
+        info!("AO quality: {:?}", settings.quality);
+    }
+    if bindings.just_pressed(&keys, Action::ToggleQuadFlipping) {
+        settings.flip_quads = !settings.flip_quads;
+        info!("AO quad flipping: {}", settings.flip_quads);
+    }
+    if bindings.just_pressed(&keys, Action::ToggleAoInterpolation) {
+        settings.interpolation = match settings.interpolation {
+            AoInterpolation::Step => AoInterpolation::Smooth,
+            AoInterpolation::Smooth => AoInterpolation::Step,
+        };
+        info!("AO interpolation: {:?}", settings.interpolation);
+    }
+    let change = if bindings.just_pressed(&keys, Action::StrongerAo) {
+        0.25
+    } else if bindings.just_pressed(&keys, Action::WeakerAo) {
+        -0.25
+    } else {
+        return;
+    };
+    settings.strength = (settings.strength + change).clamp(0.0, 1.0);
+    info!("AO strength: {}", settings.strength);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::brush::fill_box;
+    use crate::chunk::ChunkGrid;
+    use crate::meshing::{generate_quads, MeshingMode};
+    use crate::voxel::{Voxel, VoxelFace};
+    use crate::worldgen::{generate_world, RandomSeed, TerrainConfig, WorldGenerator};
+    use block_mesh::RIGHT_HANDED_Y_UP_CONFIG;
+
+    #[test]
+    fn quads_split_through_the_darkest_corners() {
+        let face = RIGHT_HANDED_Y_UP_CONFIG.faces[4];
+        let unflipped = face.quad_mesh_indices(0);
+        assert_eq!(
+            ao_quad_indices(&face, 0, [0, 3, 3, 3], false, false),
+            unflipped
+        );
+        assert_eq!(
+            ao_quad_indices(&face, 0, [3, 0, 3, 3], true, false),
+            unflipped
+        );
+
+        let flipped = ao_quad_indices(&face, 0, [0, 3, 3, 3], true, false);
+        assert_ne!(flipped, unflipped);
+        // Both triangles share the dark corner and the one opposite it.
+        for triangle in flipped.chunks_exact(3) {
+            assert!(triangle.contains(&0) && triangle.contains(&3));
+        }
+    }
+
+    #[test]
+    fn corner_ao_matches_block_mesh() {
+        let mut grid = ChunkGrid::new(1);
+        generate_world(
+            &mut grid,
+            WorldGenerator::Terrain,
+            &TerrainConfig::default(),
+            RandomSeed(0),
+        );
+        let registry = VoxelRegistry::default();
+        let (_, chunk) = grid.chunks().next().unwrap();
+        let faces = RIGHT_HANDED_Y_UP_CONFIG.faces;
+
+        // Both meshers only hand out quads whose corners all come from the same unit face AO, so they have to agree.
+        for mode in [MeshingMode::Simple, MeshingMode::Greedy] {
+            let groups = generate_quads(&chunk.voxels, &registry, mode, &faces);
+            for (group, face) in groups.iter().zip(&faces) {
+                for quad in group {
+                    assert_eq!(
+                        quad_corner_ao(&chunk.voxels, &registry, face, quad, AoQuality::Full),
+                        quad.ao,
+                        "{mode:?} quad {quad:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cheaper_qualities_skip_neighbours() {
+        let registry = VoxelRegistry::default();
+        let mut voxels = VoxelVolume::default();
+        // A floor voxel with one voxel diagonally above it, which only the diagonal samples of its top face see.
+        voxels.set(5, 5, 5, Voxel::STONE_VOXEL);
+        voxels.set(6, 6, 6, Voxel::STONE_VOXEL);
+        let faces = RIGHT_HANDED_Y_UP_CONFIG.faces;
+        let top = VoxelFace::Up.index();
+        let groups = generate_quads(&voxels, &registry, MeshingMode::Simple, &faces);
+        let quad = groups[top]
+            .iter()
+            .find(|quad| quad.minimum == [5, 5, 5])
+            .unwrap();
+
+        let ao = |quality| quad_corner_ao(&voxels, &registry, &faces[top], quad, quality);
+        assert_eq!(
+            ao(AoQuality::Full)
+                .iter()
+                .filter(|&&ao| ao < MAX_AO)
+                .count(),
+            1
+        );
+        assert_eq!(ao(AoQuality::EdgesOnly), [MAX_AO; 4]);
+        assert_eq!(ao(AoQuality::Off), [MAX_AO; 4]);
+    }
+
+    #[test]
+    fn ao_stops_are_srgb() {
+        let settings = AoSettings::default();
+        for (level, stop) in (0..=MAX_AO).zip(&settings.stops) {
+            // A white face shaded by the vertex color ends up on screen as the stop, once the output encodes it to sRGB.
+            let [r, g, b, a] = settings.color(level);
+            let shaded = Color::rgba_linear(r, g, b, a).as_rgba_f32();
+            for (shaded, stop) in shaded.iter().zip(stop) {
+                assert!((shaded - stop).abs() < 1e-5, "level {level}");
+            }
+            assert!(r <= stop[0]);
+        }
+        // 50% gray on screen is only about 21% of the light.
+        let half = AoSettings {
+            stops: vec![[0.5, 0.5, 0.5, 1.0]],
+            ..default()
+        };
+        assert!((half.color(0)[0] - 0.214).abs() < 1e-3);
+        assert_eq!(settings.color(MAX_AO + 1), [1.0; 4]);
+    }
+
+    #[test]
+    fn raymarched_ao_darkens_pits() {
+        let registry = VoxelRegistry::default();
+        let mut voxels = VoxelVolume::default();
+        // A floor voxel out in the open, and one at the bottom of a pit three voxels wide and deep.
+        voxels.set(3, 5, 3, Voxel::STONE_VOXEL);
+        fill_box(
+            &mut voxels,
+            IVec3::new(8, 5, 8),
+            IVec3::new(14, 8, 14),
+            Voxel::STONE_VOXEL,
+        );
+        fill_box(
+            &mut voxels,
+            IVec3::new(10, 6, 10),
+            IVec3::new(12, 8, 12),
+            Voxel::EMPTY_VOXEL,
+        );
+
+        let faces = RIGHT_HANDED_Y_UP_CONFIG.faces;
+        let top = VoxelFace::Up.index();
+        let groups = generate_quads(&voxels, &registry, MeshingMode::Simple, &faces);
+        let ao = |minimum: [u32; 3], quality| {
+            let quad = groups[top]
+                .iter()
+                .find(|quad| quad.minimum == minimum)
+                .unwrap();
+            quad_corner_ao(&voxels, &registry, &faces[top], quad, quality)
+        };
+
+        assert_eq!(ao([3, 5, 3], AoQuality::Raymarched), [MAX_AO; 4]);
+        // Nothing touches the middle of the pit floor, so the usual AO leaves it open, but the walls catch a lot of
+        // the rays.
+        assert_eq!(ao([11, 5, 11], AoQuality::Full), [MAX_AO; 4]);
+        let pit = ao([11, 5, 11], AoQuality::Raymarched);
+        assert!(pit.iter().all(|&ao| ao < MAX_AO), "{pit:?}");
+    }
+
+    #[test]
+    fn more_levels_fade_further_from_walls() {
+        let registry = VoxelRegistry::default();
+        let mut voxels = VoxelVolume::default();
+        // A floor with a wall along one edge.
+        fill_box(
+            &mut voxels,
+            IVec3::new(2, 5, 2),
+            IVec3::new(13, 5, 13),
+            Voxel::STONE_VOXEL,
+        );
+        fill_box(
+            &mut voxels,
+            IVec3::new(2, 6, 2),
+            IVec3::new(13, 6, 2),
+            Voxel::STONE_VOXEL,
+        );
+        let faces = RIGHT_HANDED_Y_UP_CONFIG.faces;
+        let top = VoxelFace::Up.index();
+        let groups = generate_quads(&voxels, &registry, MeshingMode::Simple, &faces);
+        let floor: Vec<_> = groups[top]
+            .iter()
+            .filter(|quad| quad.minimum[1] == 5)
+            .collect();
+        let levels = |settings: &AoSettings| -> Vec<[u8; 4]> {
+            floor
+                .iter()
+                .map(|quad| corner_ao(&voxels, &registry, &faces[top], quad, settings))
+                .collect()
+        };
+
+        let four = AoSettings::default();
+        assert_eq!(four.levels(), 4);
+        for (quad, ao) in floor.iter().zip(levels(&four)) {
+            assert_eq!(
+                ao,
+                quad_corner_ao(&voxels, &registry, &faces[top], quad, AoQuality::Full)
+            );
+        }
+        let eight = AoSettings {
+            stops: four.resampled(8),
+            ..default()
+        };
+        assert_eq!(eight.levels(), 8);
+        assert_eq!(eight.stops[0], four.stops[0]);
+        assert_eq!(eight.stops[7], four.stops[3]);
+
+        // The same floor ends up with more shades, and the shadow of the wall reaches a voxel further.
+        let shades = |levels: Vec<[u8; 4]>| {
+            let mut shades: Vec<u8> = levels.into_iter().flatten().collect();
+            shades.sort_unstable();
+            shades.dedup();
+            shades
+        };
+        assert!(shades(levels(&eight)).len() > shades(levels(&four)).len());
+        assert_eq!(*shades(levels(&eight)).last().unwrap(), 7);
+        let shaded_rows = |settings: &AoSettings| {
+            floor
+                .iter()
+                .zip(levels(settings))
+                .filter(|(_, ao)| ao.iter().any(|&ao| ao < settings.max_level()))
+                .map(|(quad, _)| quad.minimum[2])
+                .max()
+                .unwrap()
+        };
+        assert_eq!(shaded_rows(&four), 3);
+        assert_eq!(shaded_rows(&eight), 4);
+        // Open corners stay fully open, however many levels there are.
+        assert_eq!(eight.color(eight.max_level()), four.color(MAX_AO));
+    }
+}
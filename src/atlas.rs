@@ -0,0 +1,257 @@
+use bevy::prelude::*;
+use bevy::render::render_resource::{
+    AddressMode, Extent3d, FilterMode, SamplerDescriptor, TextureDimension, TextureFormat,
+    TextureViewDescriptor, TextureViewDimension,
+};
+use bevy::render::texture::ImageSampler;
+
+use crate::chunk::CHUNK_SIZE;
+use crate::input::{Action, KeyBindings};
+
+/// Layout of the texture atlas, in pixels.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct AtlasConfig {
+    pub tile_size: f32,
+    pub atlas_width: f32,
+    pub atlas_height: f32,
+}
+
+impl Default for AtlasConfig {
+    fn default() -> Self {
+        Self {
+            tile_size: 64.0,
+            atlas_width: 1024.0,
+            atlas_height: 1024.0,
+        }
+    }
+}
+
+impl AtlasConfig {
+    /// How many tiles fit in a row and in a column of the atlas.
+    pub fn tiles(&self) -> Vec2 {
+        Vec2::new(
+            self.atlas_width / self.tile_size,
+            self.atlas_height / self.tile_size,
+        )
+    }
+
+    /// Turns the `tex_coords` of a quad, which run from 0 up to its width and height in voxels, into UVs that repeat a
+    /// tile once per voxel. The tile's column and row are counted from 1 and are stored as multiples of
+    /// [`TILE_UV_STRIDE`], which the voxel shader splits off again before wrapping the rest into the tile. The sampler
+    /// can't do the wrapping itself, `AddressMode::Repeat` would repeat the whole atlas rather than one tile.
+    pub fn repeat_tile(&self, column: f32, row: f32, tex_coords: [[f32; 2]; 4]) -> [[f32; 2]; 4] {
+        let origin = Vec2::new(column - 1.0, row - 1.0) * TILE_UV_STRIDE;
+        tex_coords.map(|uv| (origin + Vec2::from(uv)).to_array())
+    }
+
+    /// The layer of [`atlas_to_texture_array`] that holds the tile at `column` and `row`, counted from 1 like in
+    /// [`AtlasConfig::repeat_tile`]. The layers go through the atlas row by row.
+    pub fn layer(&self, column: f32, row: f32) -> u32 {
+        ((row - 1.0) * self.tiles().x + column - 1.0) as u32
+    }
+}
+
+/// Which texture the chunk materials sample. Press Z to switch.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextureLayout {
+    /// The tiles of one big atlas image.
+    #[default]
+    Atlas,
+    /// A texture array with a layer per tile, see [`atlas_to_texture_array`]. Filtering never reaches into the
+    /// neighbouring tiles, so there's no bleeding at the edges of the voxels, and the number of layers isn't bound to
+    /// the size of a single image.
+    Array,
+}
+
+pub fn toggle_texture_layout(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut layout: ResMut<TextureLayout>,
+) {
+    if bindings.just_pressed(&keys, Action::ToggleTextureLayout) {
+        *layout = match *layout {
+            TextureLayout::Atlas => TextureLayout::Array,
+            TextureLayout::Array => TextureLayout::Atlas,
+        };
+        info!("texture layout: {:?}", *layout);
+    }
+}
+
+/// Cuts `image` into its tiles and stacks them into a texture array, with the tile of layer `n` at
+/// [`AtlasConfig::layer`] `n`. The tile size in pixels comes from the image itself, so an atlas with more pixels than
+/// `atlas` says still works as long as it has the same number of tiles. The sampler is kept.
+pub fn atlas_to_texture_array(image: &Image, atlas: &AtlasConfig) -> Image {
+    let tiles = atlas.tiles().as_uvec2();
+    let size = image.texture_descriptor.size;
+    let (tile_width, tile_height) = (size.width / tiles.x, size.height / tiles.y);
+    let pixel_size = image.texture_descriptor.format.describe().block_size as usize;
+    let row_bytes = size.width as usize * pixel_size;
+    let tile_row_bytes = tile_width as usize * pixel_size;
+
+    let mut data = Vec::with_capacity(image.data.len());
+    for row in 0..tiles.y {
+        for column in 0..tiles.x {
+            for y in 0..tile_height {
+                let start = (row * tile_height + y) as usize * row_bytes
+                    + (column * tile_width) as usize * pixel_size;
+                data.extend_from_slice(&image.data[start..start + tile_row_bytes]);
+            }
+        }
+    }
+    let mut array = Image::new(
+        Extent3d {
+            width: tile_width,
+            height: tile_height,
+            depth_or_array_layers: tiles.x * tiles.y,
+        },
+        TextureDimension::D2,
+        data,
+        image.texture_descriptor.format,
+    );
+    // A single layer would get a plain 2D view otherwise.
+    array.texture_view_descriptor = Some(TextureViewDescriptor {
+        dimension: Some(TextureViewDimension::D2Array),
+        ..default()
+    });
+    array.sampler_descriptor = image.sampler_descriptor.clone();
+    array
+}
+
+/// The distance between two neighbouring tiles in the UVs of a chunk mesh, see [`AtlasConfig::repeat_tile`]. Has to
+/// match `TILE_UV_STRIDE` in `voxel.wgsl`.
+pub const TILE_UV_STRIDE: f32 = 32.0;
+
+// A quad can be as long as a chunk, and its UVs mustn't run into the next tile.
+const _: () = assert!(
+    TILE_UV_STRIDE > CHUNK_SIZE.x as f32
+        && TILE_UV_STRIDE > CHUNK_SIZE.y as f32
+        && TILE_UV_STRIDE > CHUNK_SIZE.z as f32
+);
+
+/// How the atlas is sampled. Press P to switch the filtering.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SamplerSettings {
+    /// `Nearest` keeps the texels crisp, `Linear` blends between them.
+    pub filter: FilterMode,
+}
+
+impl Default for SamplerSettings {
+    fn default() -> Self {
+        Self {
+            filter: FilterMode::Nearest,
+        }
+    }
+}
+
+impl SamplerSettings {
+    /// Clamps to the edge of the atlas so nothing wraps around, and uses the same filter for magnification,
+    /// minification and mipmaps.
+    pub fn descriptor(&self) -> SamplerDescriptor<'static> {
+        SamplerDescriptor {
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: self.filter,
+            min_filter: self.filter,
+            mipmap_filter: self.filter,
+            ..default()
+        }
+    }
+}
+
+pub fn toggle_sampler_filter(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut settings: ResMut<SamplerSettings>,
+) {
+    if bindings.just_pressed(&keys, Action::ToggleSamplerFilter) {
+        settings.filter = match settings.filter {
+            FilterMode::Nearest => FilterMode::Linear,
+            FilterMode::Linear => FilterMode::Nearest,
+        };
+        info!("atlas filtering: {:?}", settings.filter);
+    }
+}
+
+/// Puts the [`SamplerSettings`] on an atlas image. Changing the image gets it uploaded again with the new sampler.
+pub fn apply_sampler_settings(image: &mut Image, settings: &SamplerSettings) {
+    image.sampler_descriptor = ImageSampler::Descriptor(settings.descriptor());
+}
+
+/// Whether `image` already uses the [`SamplerSettings`].
+pub fn has_sampler_settings(image: &Image, settings: &SamplerSettings) -> bool {
+    match &image.sampler_descriptor {
+        ImageSampler::Descriptor(descriptor) => *descriptor == settings.descriptor(),
+        ImageSampler::Default => false,
+    }
+}
+
+/// A checkerboard with the atlas' size and tile layout, used when the real atlas can't be loaded. Every tile is split
+/// into four squares, so the tile borders stay visible.
+pub fn placeholder_atlas(atlas: &AtlasConfig) -> Image {
+    let (width, height) = (atlas.atlas_width as u32, atlas.atlas_height as u32);
+    let half_tile = (atlas.tile_size as u32 / 2).max(1);
+    let mut data = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let color = if (x / half_tile + y / half_tile).is_multiple_of(2) {
+                [255, 0, 255, 255]
+            } else {
+                [32, 32, 32, 255]
+            };
+            data.extend_from_slice(&color);
+        }
+    }
+    Image::new(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_tile_gets_its_own_layer() {
+        let atlas = AtlasConfig {
+            tile_size: 2.0,
+            atlas_width: 6.0,
+            atlas_height: 4.0,
+        };
+        // Every pixel's red channel is the layer its tile should end up in.
+        let mut data = Vec::new();
+        for y in 0..4 {
+            for x in 0..6 {
+                data.extend_from_slice(&[(y / 2 * 3 + x / 2) as u8, 0, 0, 255]);
+            }
+        }
+        let image = Image::new(
+            Extent3d {
+                width: 6,
+                height: 4,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+        );
+
+        let array = atlas_to_texture_array(&image, &atlas);
+        let size = array.texture_descriptor.size;
+        assert_eq!(
+            (size.width, size.height, size.depth_or_array_layers),
+            (2, 2, 6)
+        );
+        for (layer, pixels) in array.data.chunks_exact(2 * 2 * 4).enumerate() {
+            assert!(pixels.chunks_exact(4).all(|pixel| pixel[0] == layer as u8));
+        }
+        assert_eq!(atlas.layer(3.0, 2.0), 5);
+    }
+}
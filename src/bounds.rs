@@ -0,0 +1,168 @@
+use bevy::pbr::NotShadowCaster;
+use bevy::prelude::*;
+use bevy::render::mesh::PrimitiveTopology;
+
+use crate::chunk::{ChunkGrid, CHUNK_SIZE};
+use crate::input::{Action, KeyBindings};
+
+/// Draws a box around every chunk and the X, Y and Z axes at the world origin in red, green and blue, for checking
+/// that the chunks line up. Press F3 to show and hide them.
+pub struct ChunkBoundsPlugin;
+
+impl Plugin for ChunkBoundsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChunkBoundsSettings>()
+            .init_resource::<KeyBindings>()
+            .add_system(toggle_chunk_bounds)
+            .add_system(update_chunk_bounds);
+    }
+}
+
+/// How the chunk bounds look. Changes show up on the next frame.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct ChunkBoundsSettings {
+    pub visible: bool,
+    /// The color of the boxes, which should stand out from the axes.
+    pub color: Color,
+}
+
+impl Default for ChunkBoundsSettings {
+    fn default() -> Self {
+        Self {
+            visible: false,
+            color: Color::YELLOW,
+        }
+    }
+}
+
+/// The entity the lines are drawn with.
+#[derive(Component)]
+struct ChunkBounds;
+
+/// The corners of the box from `min` to `max`, in the order of the bits of their index: bit 0 picks X, bit 1 Y and
+/// bit 2 Z from `max`.
+fn box_corners(min: Vec3, max: Vec3) -> [Vec3; 8] {
+    [0, 1, 2, 3, 4, 5, 6, 7].map(|i: u32| {
+        Vec3::new(
+            if i & 1 == 0 { min.x } else { max.x },
+            if i & 2 == 0 { min.y } else { max.y },
+            if i & 4 == 0 { min.z } else { max.z },
+        )
+    })
+}
+
+/// A line list mesh with the 12 edges of every chunk's box, in world space, followed by the three axes. The boxes
+/// cover the voxels the chunk owns, its padding left out, so neighbouring boxes share their faces. Each axis is a chunk
+/// long.
+pub fn chunk_bounds_mesh(grid: &ChunkGrid, color: Color) -> Mesh {
+    let size = CHUNK_SIZE.as_vec3() * grid.voxel_scale();
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut colors: Vec<[f32; 4]> = Vec::new();
+    let mut line = |from: Vec3, to: Vec3, color: Color| {
+        positions.extend([from.to_array(), to.to_array()]);
+        colors.extend([color.as_linear_rgba_f32(); 2]);
+    };
+
+    for (coord, _) in grid.chunks() {
+        let min = grid.voxel_origin() + coord.as_vec3() * size;
+        let corners = box_corners(min, min + size);
+        // Every pair of corners whose indices differ in a single bit is an edge.
+        for a in 0..8 {
+            for bit in [1, 2, 4] {
+                if a & bit == 0 {
+                    line(corners[a], corners[a | bit], color);
+                }
+            }
+        }
+    }
+    for (axis, color) in [
+        (Vec3::X, Color::RED),
+        (Vec3::Y, Color::GREEN),
+        (Vec3::Z, Color::BLUE),
+    ] {
+        line(Vec3::ZERO, axis * size, color);
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::LineList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    mesh
+}
+
+fn toggle_chunk_bounds(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut settings: ResMut<ChunkBoundsSettings>,
+) {
+    if bindings.just_pressed(&keys, Action::ToggleChunkBounds) {
+        settings.visible = !settings.visible;
+    }
+}
+
+/// Spawns the lines once the grid exists and draws them again whenever the grid or the settings change, which also
+/// picks up a new voxel scale.
+fn update_chunk_bounds(
+    mut commands: Commands,
+    settings: Res<ChunkBoundsSettings>,
+    grid: Option<Res<ChunkGrid>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut bounds: Query<(&mut Handle<Mesh>, &mut Visibility), With<ChunkBounds>>,
+) {
+    let Some(grid) = grid else {
+        return;
+    };
+    if !(settings.is_changed() || grid.is_changed()) {
+        return;
+    }
+    let mesh = meshes.add(chunk_bounds_mesh(&grid, settings.color));
+    if let Ok((mut handle, mut visibility)) = bounds.get_single_mut() {
+        *handle = mesh;
+        visibility.is_visible = settings.visible;
+        return;
+    }
+    commands.spawn((
+        PbrBundle {
+            mesh,
+            material: materials.add(StandardMaterial {
+                base_color: Color::WHITE,
+                unlit: true,
+                ..default()
+            }),
+            visibility: Visibility {
+                is_visible: settings.visible,
+            },
+            ..default()
+        },
+        NotShadowCaster,
+        ChunkBounds,
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boxes_line_up_with_the_voxels() {
+        let grid = ChunkGrid::new(2).with_voxel_scale(crate::chunk::VoxelScale(0.5));
+        let mesh = chunk_bounds_mesh(&grid, Color::YELLOW);
+        let positions = mesh
+            .attribute(Mesh::ATTRIBUTE_POSITION)
+            .unwrap()
+            .as_float3()
+            .unwrap();
+        // 12 edges for each of the 8 chunks and the three axes, two points each.
+        assert_eq!(positions.len(), (8 * 12 + 3) * 2);
+
+        // The boxes end where the voxels of the grid end.
+        let boxes = &positions[..8 * 12 * 2];
+        let (min, max) = boxes.iter().fold(
+            (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+            |(min, max), &p| (min.min(Vec3::from(p)), max.max(Vec3::from(p))),
+        );
+        assert_eq!(min, grid.voxel_origin());
+        assert_eq!(max - min, grid.world_size().as_vec3() * 0.5);
+        assert_eq!(positions[8 * 12 * 2], [0.0; 3]);
+    }
+}
@@ -0,0 +1,96 @@
+use bevy::prelude::*;
+use block_mesh::ndshape::ConstShape;
+
+use crate::chunk::ChunkGrid;
+use crate::volume::VoxelVolume;
+use crate::voxel::Voxel;
+
+/// Something the brushes can paint into. Positions outside of it are skipped, so a brush can hang over the edge.
+pub trait BrushTarget {
+    fn paint(&mut self, position: IVec3, voxel: Voxel);
+}
+
+/// Paints the voxels of the volume itself, padding included.
+impl<S: ConstShape<3, Coord = u32>> BrushTarget for VoxelVolume<S> {
+    fn paint(&mut self, position: IVec3, voxel: Voxel) {
+        if position.cmpge(IVec3::ZERO).all() {
+            let [x, y, z] = position.as_uvec3().to_array();
+            if self.contains(x, y, z) {
+                self.set(x, y, z, voxel);
+            }
+        }
+    }
+}
+
+/// Paints in grid coordinates, keeping the chunk padding up to date like [`ChunkGrid::set_voxel`].
+impl BrushTarget for ChunkGrid {
+    fn paint(&mut self, position: IVec3, voxel: Voxel) {
+        if self.contains(position) {
+            self.set_voxel(position.as_uvec3(), voxel);
+        }
+    }
+}
+
+/// Sets every voxel whose center is at most `radius` voxels away from the center of `center`.
+pub fn fill_sphere(target: &mut impl BrushTarget, center: IVec3, radius: u32, voxel: Voxel) {
+    let r = radius as i32;
+    let offsets = (-r..=r)
+        .flat_map(|z| (-r..=r).flat_map(move |y| (-r..=r).map(move |x| IVec3::new(x, y, z))));
+    for offset in offsets.filter(|offset| offset.dot(*offset) <= r * r) {
+        target.paint(center + offset, voxel);
+    }
+}
+
+/// Sets every voxel from `min` to `max`, both included.
+pub fn fill_box(target: &mut impl BrushTarget, min: IVec3, max: IVec3, voxel: Voxel) {
+    let (min, max) = (min.min(max), min.max(max));
+    for z in min.z..=max.z {
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                target.paint(IVec3::new(x, y, z), voxel);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sphere_is_round_and_clipped() {
+        let mut volume = VoxelVolume::default();
+        fill_sphere(&mut volume, IVec3::splat(5), 1, Voxel::STONE_VOXEL);
+        // The center and its six neighbours.
+        assert_eq!(volume.iter_filled().count(), 7);
+
+        let mut volume = VoxelVolume::default();
+        fill_sphere(&mut volume, IVec3::splat(5), 2, Voxel::STONE_VOXEL);
+        assert_eq!(volume.iter_filled().count(), 33);
+        assert_eq!(volume.get(7, 5, 5), Some(Voxel::STONE_VOXEL));
+        assert_eq!(volume.get(7, 6, 5), Some(Voxel::EMPTY_VOXEL));
+
+        // Only the octant inside the volume is left of a sphere around the corner.
+        let mut volume = VoxelVolume::default();
+        fill_sphere(&mut volume, IVec3::ZERO, 2, Voxel::STONE_VOXEL);
+        assert_eq!(volume.iter_filled().count(), 11);
+    }
+
+    #[test]
+    fn box_includes_both_corners() {
+        let mut volume = VoxelVolume::default();
+        fill_box(
+            &mut volume,
+            IVec3::new(3, 4, 5),
+            IVec3::new(1, 1, 1),
+            Voxel::DIRT_VOXEL,
+        );
+        assert_eq!(volume.iter_filled().count(), 3 * 4 * 5);
+        assert_eq!(volume.get(1, 1, 1), Some(Voxel::DIRT_VOXEL));
+        assert_eq!(volume.get(3, 4, 5), Some(Voxel::DIRT_VOXEL));
+
+        let mut volume = VoxelVolume::default();
+        fill_box(&mut volume, IVec3::splat(-3), IVec3::ONE, Voxel::DIRT_VOXEL);
+        assert_eq!(volume.iter_filled().count(), 8);
+    }
+}
@@ -0,0 +1,318 @@
+use bevy::input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+use bevy::render::camera::ScalingMode;
+use bevy::window::CursorGrabMode;
+
+use crate::chunk::ChunkGrid;
+use crate::input::{Action, KeyBindings};
+
+const FLY_SPEED: f32 = 20.0;
+const MOUSE_SENSITIVITY: f32 = 0.002;
+/// How far the orbit camera can zoom in and out.
+const ORBIT_RADIUS_RANGE: (f32, f32) = (10.0, 200.0);
+/// The fastest the orbit camera can go, in radians per second.
+const MAX_ORBIT_SPEED: f32 = 2.0;
+
+/// How the camera is driven. Press C to switch; the cursor is captured while flying.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CameraMode {
+    /// Automatically circles the origin, see [`OrbitSettings`].
+    #[default]
+    Orbit,
+    /// WASD to move, Space and E to go up and down, mouse to look around.
+    FlyCam,
+}
+
+/// How the camera projects the world. Press M to look straight down at the whole grid and again to go back.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CameraProjection {
+    /// The orbit or fly camera, see [`CameraMode`].
+    #[default]
+    Perspective,
+    /// An orthographic view from above that fits the whole grid, with -Z at the top of the screen. The camera doesn't
+    /// move in this view.
+    OrthoTop,
+}
+
+/// The camera entity and where the orbit camera is.
+#[derive(Resource)]
+pub struct CameraRotationState {
+    pub camera: Entity,
+    /// How far around the origin the orbit camera has gone, in radians.
+    pub orbit_angle: f32,
+}
+
+impl CameraRotationState {
+    pub fn new(camera: Entity) -> Self {
+        Self {
+            camera,
+            orbit_angle: 0.0,
+        }
+    }
+}
+
+/// The orbit camera's path. Scroll to zoom, and press + and - to change the speed.
+#[derive(Resource, Clone, Debug)]
+pub struct OrbitSettings {
+    /// Distance from the origin, the camera also bobs up and down by a bit more than half of it.
+    pub radius: f32,
+    /// In radians per second.
+    pub speed: f32,
+}
+
+impl Default for OrbitSettings {
+    fn default() -> Self {
+        Self {
+            radius: 50.0,
+            speed: 0.3,
+        }
+    }
+}
+
+pub fn orbit_controls_system(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+    mut settings: ResMut<OrbitSettings>,
+) {
+    let scroll: f32 = mouse_wheel
+        .iter()
+        .map(|wheel| match wheel.unit {
+            MouseScrollUnit::Line => wheel.y,
+            // Roughly one line per notch on most platforms.
+            MouseScrollUnit::Pixel => wheel.y / 100.0,
+        })
+        .sum();
+    if scroll != 0.0 {
+        let (min_radius, max_radius) = ORBIT_RADIUS_RANGE;
+        settings.radius = (settings.radius * 0.9f32.powf(scroll)).clamp(min_radius, max_radius);
+    }
+
+    let change = if bindings.just_pressed(&keys, Action::OrbitFaster) {
+        0.1
+    } else if bindings.just_pressed(&keys, Action::OrbitSlower) {
+        -0.1
+    } else {
+        return;
+    };
+    settings.speed = (settings.speed + change).clamp(0.0, MAX_ORBIT_SPEED);
+    info!("orbit speed: {:.1}", settings.speed);
+}
+
+/// The transform of the camera in `state`, or `None` if that entity is gone, like after the camera was despawned or
+/// swapped for another one without updating [`CameraRotationState::camera`]. The systems that move the camera skip the
+/// frame then instead of panicking, with a warning the first time, which `warned` keeps track of until the camera is
+/// back.
+fn camera_transform<'a>(
+    transforms: &'a mut Query<&mut Transform>,
+    state: &CameraRotationState,
+    warned: &mut bool,
+) -> Option<Mut<'a, Transform>> {
+    match transforms.get_mut(state.camera) {
+        Ok(transform) => {
+            *warned = false;
+            Some(transform)
+        }
+        Err(err) => {
+            if !*warned {
+                warn!("the camera {:?} has no transform: {err}", state.camera);
+                *warned = true;
+            }
+            None
+        }
+    }
+}
+
+pub fn camera_rotation_system(
+    mode: Res<CameraMode>,
+    projection: Res<CameraProjection>,
+    settings: Res<OrbitSettings>,
+    mut state: ResMut<CameraRotationState>,
+    time: Res<Time>,
+    mut transforms: Query<&mut Transform>,
+    mut warned: Local<bool>,
+) {
+    if *mode != CameraMode::Orbit || *projection != CameraProjection::Perspective {
+        return;
+    }
+
+    // Accumulated rather than derived from the elapsed time, so changing the speed doesn't make the camera jump.
+    state.orbit_angle += settings.speed * time.delta_seconds();
+    let t = state.orbit_angle;
+
+    let target = Vec3::new(0.0, 0.0, 0.0);
+    let radius = settings.radius;
+    let height = 0.6 * radius * (2.0 * t).sin();
+    let x = radius * t.cos();
+    let z = radius * t.sin();
+    let mut eye = Transform::from_translation(Vec3::new(x, height, z));
+    eye.look_at(target, Vec3::Y);
+
+    let Some(mut cam_tfm) = camera_transform(&mut transforms, &state, &mut warned) else {
+        return;
+    };
+    *cam_tfm = eye;
+}
+
+pub fn toggle_camera_mode(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut mode: ResMut<CameraMode>,
+    mut windows: ResMut<Windows>,
+) {
+    if !bindings.just_pressed(&keys, Action::ToggleCameraMode) {
+        return;
+    }
+    *mode = match *mode {
+        CameraMode::Orbit => CameraMode::FlyCam,
+        CameraMode::FlyCam => CameraMode::Orbit,
+    };
+    info!("camera mode: {:?}", *mode);
+
+    if let Some(window) = windows.get_primary_mut() {
+        let flying = *mode == CameraMode::FlyCam;
+        window.set_cursor_grab_mode(if flying {
+            CursorGrabMode::Locked
+        } else {
+            CursorGrabMode::None
+        });
+        window.set_cursor_visibility(!flying);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn fly_camera_system(
+    mode: Res<CameraMode>,
+    projection: Res<CameraProjection>,
+    state: Res<CameraRotationState>,
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut transforms: Query<&mut Transform>,
+    mut warned: Local<bool>,
+) {
+    // Always drain the events, so motion from before switching modes doesn't make the camera jump.
+    let mouse_delta: Vec2 = mouse_motion.iter().map(|motion| motion.delta).sum();
+    if *mode != CameraMode::FlyCam || *projection != CameraProjection::Perspective {
+        return;
+    }
+    let Some(mut cam_tfm) = camera_transform(&mut transforms, &state, &mut warned) else {
+        return;
+    };
+
+    let (yaw, pitch, _) = cam_tfm.rotation.to_euler(EulerRot::YXZ);
+    let yaw = yaw - mouse_delta.x * MOUSE_SENSITIVITY;
+    // Stop just short of straight up or down, where yaw becomes ambiguous.
+    let pitch = (pitch - mouse_delta.y * MOUSE_SENSITIVITY).clamp(-1.54, 1.54);
+    cam_tfm.rotation = Quat::from_euler(EulerRot::YXZ, yaw, pitch, 0.0);
+
+    let forward = cam_tfm.forward();
+    let right = cam_tfm.right();
+    let mut direction = Vec3::ZERO;
+    if bindings.pressed(&keys, Action::FlyForward) {
+        direction += forward;
+    }
+    if bindings.pressed(&keys, Action::FlyBack) {
+        direction -= forward;
+    }
+    if bindings.pressed(&keys, Action::FlyRight) {
+        direction += right;
+    }
+    if bindings.pressed(&keys, Action::FlyLeft) {
+        direction -= right;
+    }
+    if bindings.pressed(&keys, Action::FlyUp) {
+        direction += Vec3::Y;
+    }
+    if bindings.pressed(&keys, Action::FlyDown) {
+        direction -= Vec3::Y;
+    }
+    cam_tfm.translation += direction.normalize_or_zero() * FLY_SPEED * time.delta_seconds();
+}
+
+/// Switches the [`CameraProjection`]. The perspective camera gets back the transform it had, so the fly camera carries
+/// on from where it was.
+pub fn toggle_camera_projection(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    state: Res<CameraRotationState>,
+    grid: Res<ChunkGrid>,
+    mut mode: ResMut<CameraProjection>,
+    mut cameras: Query<(&mut Transform, &mut Projection)>,
+    mut perspective_transform: Local<Option<Transform>>,
+) {
+    if !bindings.just_pressed(&keys, Action::ToggleProjection) {
+        return;
+    }
+    let Ok((mut transform, mut projection)) = cameras.get_mut(state.camera) else {
+        return;
+    };
+    *mode = match *mode {
+        CameraProjection::Perspective => CameraProjection::OrthoTop,
+        CameraProjection::OrthoTop => CameraProjection::Perspective,
+    };
+    info!("camera projection: {:?}", *mode);
+
+    match *mode {
+        CameraProjection::OrthoTop => {
+            *perspective_transform = Some(*transform);
+            let size = grid.world_size().as_vec3() * grid.voxel_scale();
+            let center = grid.voxel_origin() + size / 2.0;
+            // High enough above the top of the grid that nothing gets clipped by the near plane.
+            *transform = Transform::from_translation(center + Vec3::Y * size.y)
+                .looking_at(center, Vec3::NEG_Z);
+            *projection = Projection::Orthographic(OrthographicProjection {
+                far: 4.0 * size.y,
+                // A little margin so the edges of the grid aren't right at the border of the window.
+                scaling_mode: ScalingMode::FixedVertical(1.1 * size.x.max(size.z)),
+                scale: 1.0,
+                ..default()
+            });
+        }
+        CameraProjection::Perspective => {
+            if let Some(saved) = perspective_transform.take() {
+                *transform = saved;
+            }
+            *projection = Projection::Perspective(default());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn camera_systems_survive_a_missing_camera() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<Input<KeyCode>>()
+            .init_resource::<KeyBindings>()
+            .init_resource::<OrbitSettings>()
+            .init_resource::<CameraProjection>()
+            .add_event::<MouseMotion>()
+            .add_system(camera_rotation_system)
+            .add_system(fly_camera_system);
+        let mut camera = app.world.spawn(Transform::default()).id();
+        app.insert_resource(CameraRotationState::new(camera));
+
+        for mode in [CameraMode::Orbit, CameraMode::FlyCam] {
+            app.insert_resource(mode);
+            app.update();
+            app.world.despawn(camera);
+            app.update();
+            app.update();
+            // Back to normal once there's a camera again.
+            camera = app.world.spawn(Transform::default()).id();
+            app.insert_resource(CameraRotationState::new(camera));
+            app.update();
+            if mode == CameraMode::Orbit {
+                assert_ne!(
+                    app.world.get::<Transform>(camera),
+                    Some(&Transform::default())
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,341 @@
+use bevy::prelude::*;
+use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+
+use crate::volume::VoxelVolume;
+use crate::voxel::Voxel;
+
+/// Number of voxels along each axis of a chunk, not counting the padding. The axes don't need to be the same length.
+pub const CHUNK_SIZE: UVec3 = UVec3::new(20, 20, 20);
+
+/// A chunk plus one voxel of padding on every side, which block-mesh needs to decide whether boundary faces are visible.
+pub type ChunkShape =
+    ConstShape3u32<{ CHUNK_SIZE.x + 2 }, { CHUNK_SIZE.y + 2 }, { CHUNK_SIZE.z + 2 }>;
+
+// `set_voxel` and the meshing bounds rely on exactly one voxel of padding on every side.
+const _: () = {
+    let [x, y, z] = ChunkShape::ARRAY;
+    assert!(x == CHUNK_SIZE.x + 2 && y == CHUNK_SIZE.y + 2 && z == CHUNK_SIZE.z + 2);
+};
+
+/// One chunk of the [`ChunkGrid`]: its voxels and the meshes built from them.
+pub struct Chunk {
+    /// The padding holds copies of the neighbouring chunks' boundary voxels.
+    pub voxels: VoxelVolume,
+    /// Faces of opaque voxels, drawn with alpha masking.
+    pub opaque_mesh: Handle<Mesh>,
+    /// Faces of translucent voxels, drawn with blending and sorted back to front.
+    pub translucent_mesh: Handle<Mesh>,
+    /// The entity that places the chunk in the world, with the two mesh entities as its children. `None` until it's
+    /// spawned.
+    pub entity: Option<Entity>,
+}
+
+/// The edge length of a voxel in world units. The voxel grid itself stays integer, this only sizes the meshes and
+/// spaces the chunks out to match. It's read when the world is built, and changing it later rescales the grid in place,
+/// see [`ChunkGrid::set_voxel_scale`].
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct VoxelScale(pub f32);
+
+impl Default for VoxelScale {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+/// Marks the entities that draw chunk meshes.
+#[derive(Component)]
+pub struct ChunkMesh;
+
+/// Marks a [`Chunk::entity`] whose voxels were edited since it was last meshed, so only that chunk gets remeshed. It's
+/// removed once the new mesh is queued.
+#[derive(Component)]
+pub struct Dirty;
+
+/// A cube of `chunks_per_axis`³ chunks that are meshed independently but form one continuous world.
+#[derive(Resource)]
+pub struct ChunkGrid {
+    chunks_per_axis: u32,
+    voxel_scale: f32,
+    chunks: Vec<Chunk>,
+}
+
+impl ChunkGrid {
+    /// An empty grid. Chunk meshes start out as default handles until they're meshed.
+    pub fn new(chunks_per_axis: u32) -> Self {
+        let chunks = (0..chunks_per_axis.pow(3))
+            .map(|_| Chunk {
+                voxels: VoxelVolume::default(),
+                opaque_mesh: Handle::default(),
+                translucent_mesh: Handle::default(),
+                entity: None,
+            })
+            .collect();
+        Self {
+            chunks_per_axis,
+            voxel_scale: 1.0,
+            chunks,
+        }
+    }
+
+    /// The `chunks_per_axis` of the smallest grid that holds a structure of `size` voxels, for
+    /// [`crate::vox::place_model`].
+    pub fn chunks_to_fit(size: UVec3) -> u32 {
+        let chunks = (size + CHUNK_SIZE - 1) / CHUNK_SIZE;
+        chunks.max_element().max(1)
+    }
+
+    /// The same grid with voxels `scale` world units across.
+    pub fn with_voxel_scale(mut self, scale: VoxelScale) -> Self {
+        self.voxel_scale = scale.0;
+        self
+    }
+
+    /// Changes the edge length of the voxels. The chunk meshes and entities have to follow, which
+    /// [`crate::plugin::VoxelMeshPlugin`] does whenever the [`VoxelScale`] resource changes.
+    pub fn set_voxel_scale(&mut self, scale: VoxelScale) {
+        self.voxel_scale = scale.0;
+    }
+
+    /// The edge length of a voxel in world units.
+    pub fn voxel_scale(&self) -> f32 {
+        self.voxel_scale
+    }
+
+    /// Number of chunks along each axis of the grid.
+    pub fn chunks_per_axis(&self) -> u32 {
+        self.chunks_per_axis
+    }
+
+    /// Number of voxels along each axis of the whole world.
+    pub fn world_size(&self) -> UVec3 {
+        self.chunks_per_axis * CHUNK_SIZE
+    }
+
+    fn chunk_index(&self, coord: UVec3) -> usize {
+        let n = self.chunks_per_axis;
+        (coord.x + n * (coord.y + n * coord.z)) as usize
+    }
+
+    fn chunk_coord(&self, index: usize) -> UVec3 {
+        let n = self.chunks_per_axis as usize;
+        UVec3::new(
+            (index % n) as u32,
+            (index / n % n) as u32,
+            (index / (n * n)) as u32,
+        )
+    }
+
+    /// Where the chunk's mesh is placed. Padded voxel 1 is the first voxel of a chunk, so this lines chunks up edge to edge.
+    pub fn chunk_translation(&self, coord: UVec3) -> Vec3 {
+        self.voxel_origin() + ((coord * CHUNK_SIZE).as_vec3() - 1.0) * self.voxel_scale
+    }
+
+    /// The world space position of the minimum corner of voxel `[0, 0, 0]`. The grid is centered on the origin, whatever
+    /// its size and voxel scale, which is where the orbit camera looks.
+    pub fn voxel_origin(&self) -> Vec3 {
+        -self.world_size().as_vec3() * self.voxel_scale / 2.0
+    }
+
+    /// Whether the grid coordinates `world` are inside the grid.
+    pub fn contains(&self, world: IVec3) -> bool {
+        world.cmpge(IVec3::ZERO).all() && world.cmplt(self.world_size().as_ivec3()).all()
+    }
+
+    /// The chunk at `coord`, counted in chunks. Panics outside the grid.
+    pub fn chunk(&self, coord: UVec3) -> &Chunk {
+        &self.chunks[self.chunk_index(coord)]
+    }
+
+    /// The chunk at `coord`, for changes that don't have to reach the neighbours' padding. Use
+    /// [`ChunkGrid::set_voxel`] to edit voxels.
+    pub fn chunk_mut(&mut self, coord: UVec3) -> &mut Chunk {
+        let index = self.chunk_index(coord);
+        &mut self.chunks[index]
+    }
+
+    /// Every chunk with its coordinates, X changing fastest.
+    pub fn chunks(&self) -> impl Iterator<Item = (UVec3, &Chunk)> {
+        self.chunks
+            .iter()
+            .enumerate()
+            .map(|(index, chunk)| (self.chunk_coord(index), chunk))
+    }
+
+    /// The voxel at the grid coordinates `world`, which must be inside the grid.
+    pub fn voxel(&self, world: UVec3) -> Voxel {
+        let chunk = &self.chunks[self.chunk_index(world / CHUNK_SIZE)];
+        let local = world % CHUNK_SIZE + UVec3::ONE;
+        chunk.voxels[local.to_array()]
+    }
+
+    /// Sets the voxel at `world`, which must be inside the grid. The voxel is written to the chunk that owns it and to the
+    /// padding of every neighbouring chunk that can see it, which keeps faces on chunk boundaries correct. Returns the
+    /// coordinates of all of those chunks, the owner first, since they're the ones whose meshes can change.
+    pub fn set_voxel(&mut self, world: UVec3, voxel: Voxel) -> Vec<UVec3> {
+        // For each axis, every (chunk, padded local coordinate) pair whose padded extent contains the voxel.
+        let per_axis = [0, 1, 2].map(|axis| {
+            let (w, size) = (world[axis], CHUNK_SIZE[axis]);
+            let chunk = w / size;
+            let local = w % size + 1;
+            let mut options = vec![(chunk, local)];
+            if local == 1 && chunk > 0 {
+                options.push((chunk - 1, size + 1));
+            }
+            if local == size && chunk + 1 < self.chunks_per_axis {
+                options.push((chunk + 1, 0));
+            }
+            options
+        });
+
+        let mut touched = Vec::new();
+        for &(cx, lx) in &per_axis[0] {
+            for &(cy, ly) in &per_axis[1] {
+                for &(cz, lz) in &per_axis[2] {
+                    let coord = UVec3::new(cx, cy, cz);
+                    self.chunk_mut(coord).voxels.set(lx, ly, lz, voxel);
+                    touched.push(coord);
+                }
+            }
+        }
+        touched
+    }
+}
+
+/// The voxels of the chunk at `coord` with its padding read straight from the neighbouring chunks, and left empty at
+/// the edge of the grid. [`ChunkGrid::set_voxel`] keeps every chunk's own padding the same as this, so the mesher can
+/// use either, but this doesn't depend on every edit having gone through it.
+pub fn gather_padding(grid: &ChunkGrid, coord: UVec3) -> VoxelVolume {
+    let mut volume = VoxelVolume::default();
+    let [dx, dy, dz] = volume.dims();
+    // Padded voxel 1 is the first voxel of the chunk, so padded voxel 0 is the last one of the chunk before it.
+    let origin = (coord * CHUNK_SIZE).as_ivec3() - IVec3::ONE;
+    for z in 0..dz {
+        for y in 0..dy {
+            for x in 0..dx {
+                let world = origin + UVec3::new(x, y, z).as_ivec3();
+                if grid.contains(world) {
+                    volume.set(x, y, z, grid.voxel(world.as_uvec3()));
+                }
+            }
+        }
+    }
+    volume
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boundary_edits_touch_the_neighbours() {
+        let mut grid = ChunkGrid::new(2);
+        assert_eq!(
+            grid.set_voxel(UVec3::splat(5), Voxel::STONE_VOXEL),
+            [UVec3::ZERO]
+        );
+
+        // The last voxel of a chunk along X is in the padding of the next chunk along X.
+        let edge = UVec3::new(CHUNK_SIZE.x - 1, 5, 5);
+        assert_eq!(
+            grid.set_voxel(edge, Voxel::STONE_VOXEL),
+            [UVec3::ZERO, UVec3::X]
+        );
+        assert_eq!(grid.chunk(UVec3::X).voxels[[0, 6, 6]], Voxel::STONE_VOXEL);
+
+        // A corner voxel is seen by all eight chunks around it.
+        let corner = CHUNK_SIZE - UVec3::ONE;
+        assert_eq!(grid.set_voxel(corner, Voxel::STONE_VOXEL).len(), 8);
+    }
+
+    #[test]
+    fn gathered_padding_matches_the_kept_padding() {
+        let mut grid = ChunkGrid::new(2);
+        crate::worldgen::random_fill(&mut grid, crate::worldgen::RandomSeed(3));
+        for (coord, chunk) in grid.chunks() {
+            assert_eq!(gather_padding(&grid, coord), chunk.voxels, "chunk {coord}");
+        }
+    }
+
+    #[test]
+    fn grids_are_centered_on_the_origin() {
+        for (chunks, scale) in [(1, 1.0), (2, 0.5), (3, 2.0)] {
+            let grid = ChunkGrid::new(chunks).with_voxel_scale(VoxelScale(scale));
+            let size = grid.world_size().as_vec3() * scale;
+            assert_eq!(grid.voxel_origin(), -size / 2.0);
+            // The padding voxel in front of the first voxel is at the chunk's translation.
+            let last = UVec3::splat(chunks - 1);
+            assert_eq!(
+                grid.chunk_translation(UVec3::ZERO) + scale,
+                grid.voxel_origin()
+            );
+            assert_eq!(
+                grid.chunk_translation(last) + (CHUNK_SIZE.as_vec3() + 1.0) * scale,
+                size / 2.0
+            );
+        }
+    }
+
+    #[test]
+    fn single_voxel_edits_remesh_quickly() {
+        use crate::ao::AoSettings;
+        use crate::atlas::AtlasConfig;
+        use crate::meshing::{mesh_chunk, CoordinateSystem, MeshingMode, RenderMode};
+        use crate::voxel::VoxelRegistry;
+        use crate::worldgen::{generate_world, RandomSeed, TerrainConfig, WorldGenerator};
+        use std::time::{Duration, Instant};
+
+        let mut grid = ChunkGrid::new(2);
+        generate_world(
+            &mut grid,
+            WorldGenerator::Terrain,
+            &TerrainConfig::default(),
+            RandomSeed(0),
+        );
+        let registry = VoxelRegistry::default();
+        // What the remesh of a dirty chunk does before the mesh goes to the GPU, see `remesh_system`.
+        let remesh = |grid: &ChunkGrid, coord, mode| {
+            let voxels = gather_padding(grid, coord);
+            mesh_chunk(
+                &voxels,
+                &registry,
+                &AtlasConfig::default(),
+                &AoSettings::default(),
+                mode,
+                RenderMode::Textured,
+                CoordinateSystem::default(),
+                grid.voxel_scale(),
+            )
+        };
+
+        // A voxel in the corner of the chunks, near the height of the terrain's surface, touches all eight of them.
+        let edit = CHUNK_SIZE - UVec3::ONE;
+        for voxel in [Voxel::STONE_VOXEL, Voxel::EMPTY_VOXEL] {
+            let touched = grid.set_voxel(edit, voxel);
+            assert_eq!(touched.len(), 8);
+            for mode in [MeshingMode::Simple, MeshingMode::Greedy] {
+                // The best of a few runs for each chunk, so a busy machine doesn't fail the test.
+                let slowest = touched
+                    .iter()
+                    .map(|&coord| {
+                        (0..5)
+                            .map(|_| {
+                                let start = Instant::now();
+                                remesh(&grid, coord, mode);
+                                start.elapsed()
+                            })
+                            .min()
+                            .unwrap()
+                    })
+                    .max()
+                    .unwrap();
+                // A remesh takes around a millisecond even in a debug build. The bound is far above that so slow CI
+                // machines pass, and only a regression of an order of magnitude or more fails; the `remesh_after_edit`
+                // bench tracks the actual time.
+                assert!(
+                    slowest < Duration::from_millis(250),
+                    "placing {voxel:?}, {mode:?}: {slowest:?} for the slowest chunk"
+                );
+            }
+        }
+    }
+}
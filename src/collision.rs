@@ -0,0 +1,111 @@
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+use block_mesh::ndshape::ConstShape;
+use block_mesh::VoxelVisibility;
+
+use crate::volume::VoxelVolume;
+use crate::voxel::{Voxel, VoxelRegistry};
+
+/// Whether something should bump into `voxel`. Flowing voxels like water can be walked through.
+fn is_solid(registry: &VoxelRegistry, voxel: Voxel) -> bool {
+    registry.visibility(voxel) != VoxelVisibility::Empty
+        && !registry
+            .get(voxel)
+            .is_some_and(|voxel_type| voxel_type.flowing)
+}
+
+/// Covers the solid voxels of a chunk with as few boxes as a greedy sweep finds, ready to be turned into colliders.
+///
+/// Each box starts at the first voxel nobody has covered yet and grows as far as it can along X, then Y, then Z,
+/// taking whole rows and slabs only. The padding belongs to the neighbouring chunks and is left out. The boxes are in
+/// the same space as the chunk's mesh before [`crate::chunk::VoxelScale`] is applied, so voxel `[x, y, z]` covers
+/// `[x, x + 1]` on every axis.
+pub fn collider_boxes<S: ConstShape<3, Coord = u32>>(
+    volume: &VoxelVolume<S>,
+    registry: &VoxelRegistry,
+) -> Vec<Aabb> {
+    let [dx, dy, dz] = volume.dims();
+    let mut covered = vec![false; S::USIZE];
+    let free = |covered: &[bool], [x, y, z]: [u32; 3]| {
+        !covered[S::linearize([x, y, z]) as usize] && is_solid(registry, volume[[x, y, z]])
+    };
+
+    let mut boxes = Vec::new();
+    for z in 1..dz - 1 {
+        for y in 1..dy - 1 {
+            for x in 1..dx - 1 {
+                if !free(&covered, [x, y, z]) {
+                    continue;
+                }
+                let mut max = [x, y, z];
+                while max[0] + 1 < dx - 1 && free(&covered, [max[0] + 1, y, z]) {
+                    max[0] += 1;
+                }
+                while max[1] + 1 < dy - 1
+                    && (x..=max[0]).all(|x| free(&covered, [x, max[1] + 1, z]))
+                {
+                    max[1] += 1;
+                }
+                while max[2] + 1 < dz - 1
+                    && (y..=max[1])
+                        .all(|y| (x..=max[0]).all(|x| free(&covered, [x, y, max[2] + 1])))
+                {
+                    max[2] += 1;
+                }
+
+                for cz in z..=max[2] {
+                    for cy in y..=max[1] {
+                        for cx in x..=max[0] {
+                            covered[S::linearize([cx, cy, cz]) as usize] = true;
+                        }
+                    }
+                }
+                let min = UVec3::new(x, y, z).as_vec3();
+                let max = UVec3::from(max).as_vec3() + 1.0;
+                boxes.push(Aabb::from_min_max(min, max));
+            }
+        }
+    }
+    boxes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn volume_of(boxes: &[Aabb]) -> f32 {
+        boxes
+            .iter()
+            .map(|aabb| {
+                (Vec3::from(aabb.half_extents) * 2.0)
+                    .to_array()
+                    .iter()
+                    .product::<f32>()
+            })
+            .sum()
+    }
+
+    #[test]
+    fn boxes_cover_exactly_the_solid_voxels() {
+        let registry = VoxelRegistry::default();
+        let mut volume = VoxelVolume::default();
+        for z in 1..4 {
+            for x in 1..3 {
+                volume.set(x, 1, z, Voxel::STONE_VOXEL);
+            }
+        }
+        let boxes = collider_boxes(&volume, &registry);
+        assert_eq!(boxes.len(), 1);
+        assert_eq!(Vec3::from(boxes[0].min()), Vec3::ONE);
+        assert_eq!(Vec3::from(boxes[0].max()), Vec3::new(3.0, 2.0, 4.0));
+
+        // A post on one end makes an L, which needs a second box. Water and the padding don't collide.
+        volume.set(1, 2, 1, Voxel::DIRT_VOXEL);
+        volume.set(1, 3, 1, Voxel::DIRT_VOXEL);
+        volume.set(5, 1, 1, Voxel::WATER_VOXEL);
+        volume.set(0, 1, 1, Voxel::STONE_VOXEL);
+        let boxes = collider_boxes(&volume, &registry);
+        assert_eq!(boxes.len(), 2);
+        assert_eq!(volume_of(&boxes), 8.0);
+    }
+}
@@ -0,0 +1,94 @@
+use bevy::prelude::*;
+use block_mesh::ndshape::ConstShape;
+
+use crate::chunk::{ChunkGrid, CHUNK_SIZE};
+use crate::input::{Action, KeyBindings};
+use crate::volume::VoxelVolume;
+use crate::voxel::Voxel;
+
+/// A horizontal cut through the world. Every voxel at or above `height` is left out of the meshes, so the voxels
+/// right below the cut get faces and the inside of solid ground and its AO can be seen. The voxels themselves stay in
+/// the grid, so editing still hits the ones that are hidden.
+///
+/// Down arrow lowers the cut, starting from the top of the world, and up arrow raises it until it's gone again.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CrossSection {
+    /// The world Y of the first hidden layer of voxels, or `None` to show everything.
+    pub height: Option<u32>,
+}
+
+impl CrossSection {
+    /// Empties the voxels of the chunk at `coord` that are cut off, padding included, so the faces along the cut are
+    /// kept.
+    pub fn apply<S: ConstShape<3, Coord = u32>>(&self, volume: &mut VoxelVolume<S>, coord: UVec3) {
+        let Some(height) = self.height else {
+            return;
+        };
+        let [dx, dy, dz] = volume.dims();
+        // Padded voxel 1 is the first layer of the chunk, see `gather_padding`.
+        let bottom = (coord.y * CHUNK_SIZE.y) as i64 - 1;
+        let first_hidden = (height as i64 - bottom).clamp(0, dy as i64) as u32;
+        for z in 0..dz {
+            for y in first_hidden..dy {
+                for x in 0..dx {
+                    volume.set(x, y, z, Voxel::EMPTY_VOXEL);
+                }
+            }
+        }
+    }
+}
+
+pub fn cross_section_controls_system(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    grid: Res<ChunkGrid>,
+    mut cross_section: ResMut<CrossSection>,
+) {
+    let top = grid.world_size().y;
+    let height = if bindings.just_pressed(&keys, Action::LowerCrossSection) {
+        Some(cross_section.height.unwrap_or(top).saturating_sub(1))
+    } else if bindings.just_pressed(&keys, Action::RaiseCrossSection) {
+        cross_section
+            .height
+            .map(|height| height + 1)
+            .filter(|&height| height < top)
+    } else {
+        return;
+    };
+    cross_section.height = height;
+    match height {
+        Some(height) => info!("cross section at y = {height}"),
+        None => info!("cross section off"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::gather_padding;
+
+    #[test]
+    fn cut_hides_everything_above_it() {
+        let mut grid = ChunkGrid::new(2);
+        for y in 0..grid.world_size().y {
+            grid.set_voxel(UVec3::new(3, y, 3), Voxel::STONE_VOXEL);
+        }
+        let cross_section = CrossSection { height: Some(25) };
+
+        // The lower chunk is below the cut altogether, padding included.
+        let mut lower = gather_padding(&grid, UVec3::ZERO);
+        cross_section.apply(&mut lower, UVec3::ZERO);
+        assert_eq!(lower.iter_filled().count(), 21);
+
+        // The upper chunk starts at world Y 20, so five of its layers and the padding below them are left.
+        let mut upper = gather_padding(&grid, UVec3::Y);
+        cross_section.apply(&mut upper, UVec3::Y);
+        assert_eq!(upper.iter_filled().count(), 6);
+        assert_eq!(upper.get(4, 5, 4), Some(Voxel::STONE_VOXEL));
+        assert_eq!(upper.get(4, 6, 4), Some(Voxel::EMPTY_VOXEL));
+
+        let mut uncut = gather_padding(&grid, UVec3::Y);
+        CrossSection::default().apply(&mut uncut, UVec3::Y);
+        assert_eq!(uncut, gather_padding(&grid, UVec3::Y));
+    }
+}
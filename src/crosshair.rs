@@ -0,0 +1,140 @@
+use bevy::prelude::*;
+
+use crate::input::{Action, KeyBindings};
+
+/// Draws a crosshair in the middle of the screen, where the editing ray goes. Press X to hide and show it.
+pub struct CrosshairPlugin;
+
+impl Plugin for CrosshairPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CrosshairSettings>()
+            .init_resource::<KeyBindings>()
+            .add_startup_system(spawn_crosshair)
+            .add_system(toggle_crosshair)
+            .add_system(update_crosshair);
+    }
+}
+
+/// How the crosshair looks. Changes show up on the next frame.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct CrosshairSettings {
+    pub visible: bool,
+    pub color: Color,
+    /// The length of each line, in logical pixels.
+    pub size: f32,
+    /// How thick the lines are, in logical pixels.
+    pub thickness: f32,
+    /// Draws a dot in the middle, twice as wide as the lines are thick. With a thickness of 0 only the dot is left.
+    pub center_dot: bool,
+}
+
+impl Default for CrosshairSettings {
+    fn default() -> Self {
+        Self {
+            visible: true,
+            color: Color::rgba(1.0, 1.0, 1.0, 0.8),
+            size: 16.0,
+            thickness: 2.0,
+            center_dot: false,
+        }
+    }
+}
+
+/// The node the parts are laid out in, a square of [`CrosshairSettings::size`] in the middle of the screen.
+#[derive(Component)]
+struct CrosshairFrame;
+
+#[derive(Component, Clone, Copy)]
+enum CrosshairPart {
+    Horizontal,
+    Vertical,
+    Dot,
+}
+
+fn spawn_crosshair(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            ..default()
+        })
+        .with_children(|root| {
+            let frame = NodeBundle {
+                background_color: Color::NONE.into(),
+                ..default()
+            };
+            root.spawn((frame, CrosshairFrame)).with_children(|frame| {
+                for part in [
+                    CrosshairPart::Horizontal,
+                    CrosshairPart::Vertical,
+                    CrosshairPart::Dot,
+                ] {
+                    frame.spawn((NodeBundle::default(), part));
+                }
+            });
+        });
+}
+
+fn toggle_crosshair(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut settings: ResMut<CrosshairSettings>,
+) {
+    if bindings.just_pressed(&keys, Action::ToggleCrosshair) {
+        settings.visible = !settings.visible;
+    }
+}
+
+/// Lays the parts out again when the settings change. They're positioned absolutely inside the frame, since flexbox
+/// can't stack nodes on top of each other.
+#[allow(clippy::type_complexity)]
+fn update_crosshair(
+    settings: Res<CrosshairSettings>,
+    mut frames: Query<
+        (&mut Style, &mut Visibility),
+        (With<CrosshairFrame>, Without<CrosshairPart>),
+    >,
+    mut parts: Query<(
+        &CrosshairPart,
+        &mut Style,
+        &mut BackgroundColor,
+        &mut Visibility,
+    )>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    let size = settings.size;
+    for (mut style, mut visibility) in &mut frames {
+        style.size = Size::new(Val::Px(size), Val::Px(size));
+        visibility.is_visible = settings.visible;
+    }
+    for (part, mut style, mut color, mut visibility) in &mut parts {
+        let (width, height, visible) = match part {
+            CrosshairPart::Horizontal => (size, settings.thickness, settings.thickness > 0.0),
+            CrosshairPart::Vertical => (settings.thickness, size, settings.thickness > 0.0),
+            CrosshairPart::Dot => {
+                let dot = 2.0 * settings.thickness.max(1.0);
+                (dot, dot, settings.center_dot)
+            }
+        };
+        *style = Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                left: Val::Px((size - width) / 2.0),
+                top: Val::Px((size - height) / 2.0),
+                ..default()
+            },
+            size: Size::new(Val::Px(width), Val::Px(height)),
+            ..default()
+        };
+        *color = settings.color.into();
+        visibility.is_visible = visible;
+    }
+}
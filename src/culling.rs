@@ -0,0 +1,34 @@
+use bevy::prelude::*;
+use bevy::render::primitives::{Aabb, Frustum};
+
+use crate::camera::CameraRotationState;
+use crate::chunk::{ChunkGrid, ChunkMesh, CHUNK_SIZE};
+
+/// The space a chunk's voxels can take up, relative to its transform. Padded voxel 1 is the first voxel of the chunk.
+fn chunk_bounds(voxel_scale: f32) -> Aabb {
+    Aabb::from_min_max(
+        Vec3::splat(voxel_scale),
+        (CHUNK_SIZE.as_vec3() + 1.0) * voxel_scale,
+    )
+}
+
+/// Hides the chunk entities whose bounds are outside the camera's frustum, so they're skipped by the rest of the
+/// visibility checks and never drawn. Bevy updates the frustum after this runs, so it lags the camera by a frame.
+pub fn chunk_frustum_culling_system(
+    camera: Res<CameraRotationState>,
+    frusta: Query<&Frustum>,
+    grid: Res<ChunkGrid>,
+    mut chunks: Query<(&GlobalTransform, &mut Visibility), With<ChunkMesh>>,
+) {
+    let Ok(frustum) = frusta.get(camera.camera) else {
+        return;
+    };
+    let bounds = chunk_bounds(grid.voxel_scale());
+    for (transform, mut visibility) in &mut chunks {
+        let visible = frustum.intersects_obb(&bounds, &transform.compute_matrix(), true);
+        // Only write when it changes, to keep change detection quiet.
+        if visibility.is_visible != visible {
+            visibility.is_visible = visible;
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+/// The opaque chunk meshes currently in use, by their [`mesh_hash`]. Chunks whose meshes come out byte for byte the
+/// same, like the chunks of a flat world, share one asset instead of each uploading their own copy.
+///
+/// The cache only keeps weak handles, so a mesh goes away as soon as no chunk uses it any more. Shared assets are never
+/// changed after they're added; a chunk that's remeshed moves to another handle instead.
+#[derive(Resource, Default)]
+pub struct MeshDedupCache {
+    meshes: HashMap<u64, Handle<Mesh>>,
+}
+
+impl MeshDedupCache {
+    /// A strong handle to a mesh identical to `mesh`, which is only added as a new asset if there isn't one yet.
+    pub fn share(&mut self, meshes: &mut Assets<Mesh>, mesh: Mesh) -> Handle<Mesh> {
+        let hash = mesh_hash(&mesh);
+        if let Some(handle) = self.meshes.get(&hash) {
+            if meshes.contains(handle) {
+                return meshes.get_handle(handle);
+            }
+        }
+        self.meshes.retain(|_, handle| meshes.contains(handle));
+        let handle = meshes.add(mesh);
+        self.meshes.insert(hash, handle.clone_weak());
+        handle
+    }
+
+    /// How many different meshes were handed out and are still around.
+    pub fn len(&self) -> usize {
+        self.meshes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.meshes.is_empty()
+    }
+}
+
+/// A hash of everything that ends up on the GPU for `mesh`: which attributes it has, their interleaved vertex data
+/// and its indices.
+pub fn mesh_hash(mesh: &Mesh) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for (id, _) in mesh.attributes() {
+        id.hash(&mut hasher);
+    }
+    mesh.get_vertex_buffer_data().hash(&mut hasher);
+    mesh.get_index_buffer_bytes().hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ao::AoSettings;
+    use crate::atlas::AtlasConfig;
+    use crate::meshing::{mesh_chunk, CoordinateSystem, MeshingMode, RenderMode};
+    use crate::normals::NormalMode;
+    use crate::volume::VoxelVolume;
+    use crate::voxel::{Voxel, VoxelRegistry};
+
+    fn mesh(voxels: &VoxelVolume) -> Mesh {
+        mesh_chunk(
+            voxels,
+            &VoxelRegistry::default(),
+            &AtlasConfig::default(),
+            &AoSettings::default(),
+            MeshingMode::Greedy,
+            RenderMode::Textured,
+            CoordinateSystem::default(),
+            1.0,
+        )
+        .opaque
+        .into_mesh(&AoSettings::default(), NormalMode::Flat)
+    }
+
+    #[test]
+    fn identical_chunks_hash_the_same() {
+        let mut floor = VoxelVolume::default();
+        for z in 1..=20 {
+            for x in 1..=20 {
+                floor.set(x, 1, z, Voxel::STONE_VOXEL);
+            }
+        }
+        assert_eq!(mesh_hash(&mesh(&floor)), mesh_hash(&mesh(&floor.clone())));
+
+        // A single voxel of another type changes the texture coordinates of its faces.
+        let mut other = floor.clone();
+        other.set(5, 1, 5, Voxel::DIRT_VOXEL);
+        assert_ne!(mesh_hash(&mesh(&floor)), mesh_hash(&mesh(&other)));
+    }
+}
@@ -0,0 +1,225 @@
+use std::ops::Add;
+
+use bevy::diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_inspector_egui::bevy_egui::EguiContext;
+use bevy_inspector_egui::egui;
+use block_mesh::ndshape::ConstShape;
+use block_mesh::VoxelVisibility;
+
+use crate::cross_section::CrossSection;
+use crate::volume::VoxelVolume;
+use crate::voxel::VoxelRegistry;
+use crate::worldgen::RandomSeed;
+
+/// How much geometry a mesh has.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GeometryCount {
+    pub quads: usize,
+    pub vertices: usize,
+    pub indices: usize,
+}
+
+impl GeometryCount {
+    /// Counts a chunk mesh, which has 4 vertices for every quad.
+    pub fn of(mesh: &Mesh) -> Self {
+        let vertices = mesh.count_vertices();
+        Self {
+            quads: vertices / 4,
+            vertices,
+            indices: mesh.indices().map_or(0, |indices| indices.len()),
+        }
+    }
+}
+
+impl Add for GeometryCount {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            quads: self.quads + other.quads,
+            vertices: self.vertices + other.vertices,
+            indices: self.indices + other.indices,
+        }
+    }
+}
+
+/// How many of a chunk's voxels are hidden inside it, see [`count_visible_faces`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FaceVisibility {
+    /// Voxels that aren't empty.
+    pub filled: usize,
+    /// Filled voxels with all six faces hidden, which the mesher checks without ever drawing anything.
+    pub interior: usize,
+    /// Faces that need a quad, which is what simple meshing produces.
+    pub visible_faces: usize,
+}
+
+impl FaceVisibility {
+    /// The share of the filled voxels that is interior, from 0 to 1.
+    pub fn interior_fraction(&self) -> f32 {
+        if self.filled == 0 {
+            0.0
+        } else {
+            self.interior as f32 / self.filled as f32
+        }
+    }
+}
+
+impl Add for FaceVisibility {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            filled: self.filled + other.filled,
+            interior: self.interior + other.interior,
+            visible_faces: self.visible_faces + other.visible_faces,
+        }
+    }
+}
+
+/// Counts the visible faces and interior voxels of a chunk, with the same visibility rules as block-mesh. The padding
+/// only hides faces, it isn't counted itself.
+pub fn count_visible_faces<S: ConstShape<3, Coord = u32>>(
+    volume: &VoxelVolume<S>,
+    registry: &VoxelRegistry,
+) -> FaceVisibility {
+    let [dx, dy, dz] = volume.dims();
+    let mut counts = FaceVisibility::default();
+    for [x, y, z] in volume.iter_filled() {
+        if x == 0 || y == 0 || z == 0 || x == dx - 1 || y == dy - 1 || z == dz - 1 {
+            continue;
+        }
+        let visibility = registry.visibility(volume[[x, y, z]]);
+        if visibility == VoxelVisibility::Empty {
+            continue;
+        }
+        let neighbours = [
+            [x - 1, y, z],
+            [x + 1, y, z],
+            [x, y - 1, z],
+            [x, y + 1, z],
+            [x, y, z - 1],
+            [x, y, z + 1],
+        ];
+        let visible = neighbours
+            .into_iter()
+            .filter(|&neighbour| registry.face_visible(volume[[x, y, z]], volume[neighbour]))
+            .count();
+        counts.filled += 1;
+        counts.visible_faces += visible;
+        if visible == 0 {
+            counts.interior += 1;
+        }
+    }
+    counts
+}
+
+/// What one chunk looked like at its last remesh.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChunkStats {
+    pub geometry: GeometryCount,
+    pub visibility: FaceVisibility,
+}
+
+/// The stats of every chunk as of its last remesh.
+#[derive(Resource, Default)]
+pub struct MeshStats(pub HashMap<UVec3, ChunkStats>);
+
+impl MeshStats {
+    pub fn total(&self) -> GeometryCount {
+        self.0
+            .values()
+            .map(|stats| stats.geometry)
+            .fold(GeometryCount::default(), Add::add)
+    }
+
+    pub fn visibility(&self) -> FaceVisibility {
+        self.0
+            .values()
+            .map(|stats| stats.visibility)
+            .fold(FaceVisibility::default(), Add::add)
+    }
+}
+
+/// Shows the frame rate, the size of the world's meshes, the seed it was generated from and the
+/// [`CrossSection`] in the top left corner.
+pub fn diagnostics_overlay_system(
+    mut egui_context: ResMut<EguiContext>,
+    diagnostics: Res<Diagnostics>,
+    stats: Res<MeshStats>,
+    seed: Res<RandomSeed>,
+    cross_section: Res<CrossSection>,
+) {
+    let fps = diagnostics
+        .get(FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.smoothed());
+    let total = stats.total();
+    let visibility = stats.visibility();
+    egui::Area::new("diagnostics")
+        .anchor(egui::Align2::LEFT_TOP, [8.0, 8.0])
+        .show(egui_context.ctx_mut(), |ui| {
+            match fps {
+                Some(fps) => ui.label(format!("FPS: {fps:.0}")),
+                None => ui.label("FPS: -"),
+            };
+            ui.label(format!("Quads: {}", total.quads));
+            ui.label(format!("Vertices: {}", total.vertices));
+            ui.label(format!("Indices: {}", total.indices));
+            ui.label(format!("Visible faces: {}", visibility.visible_faces));
+            ui.label(format!(
+                "Interior voxels: {} of {} ({:.0}%)",
+                visibility.interior,
+                visibility.filled,
+                visibility.interior_fraction() * 100.0
+            ));
+            ui.label(format!("Seed: {}", seed.0));
+            match cross_section.height {
+                Some(height) => ui.label(format!("Cross section: y < {height}")),
+                None => ui.label("Cross section: off"),
+            };
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meshing::{generate_quads, MeshingMode};
+    use crate::voxel::Voxel;
+    use block_mesh::RIGHT_HANDED_Y_UP_CONFIG;
+
+    #[test]
+    fn only_the_shell_of_a_solid_cube_is_visible() {
+        let registry = VoxelRegistry::default();
+        let mut volume = VoxelVolume::default();
+        for x in 1..5 {
+            for y in 1..5 {
+                for z in 1..5 {
+                    volume.set(x, y, z, Voxel::STONE_VOXEL);
+                }
+            }
+        }
+        let counts = count_visible_faces(&volume, &registry);
+        assert_eq!(
+            counts,
+            FaceVisibility {
+                filled: 64,
+                interior: 8,
+                visible_faces: 6 * 16,
+            }
+        );
+        assert_eq!(counts.interior_fraction(), 0.125);
+
+        let quads: usize = generate_quads(
+            &volume,
+            &registry,
+            MeshingMode::Simple,
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+        )
+        .iter()
+        .map(Vec::len)
+        .sum();
+        assert_eq!(quads, counts.visible_faces);
+    }
+}
@@ -0,0 +1,284 @@
+use bevy::pbr::NotShadowCaster;
+use bevy::prelude::*;
+use bevy::render::mesh::PrimitiveTopology;
+use bevy::utils::HashSet;
+use bevy_inspector_egui::bevy_egui::EguiContext;
+use bevy_inspector_egui::egui;
+
+use crate::brush::{fill_box, fill_sphere, BrushTarget};
+use crate::camera::CameraRotationState;
+use crate::chunk::{ChunkGrid, Dirty};
+use crate::input::{Action, KeyBindings};
+use crate::raycast::{raycast, RayHit};
+use crate::voxel::{Voxel, VoxelFace, VoxelRegistry};
+
+/// How far away from the camera voxels can be edited, in voxels.
+const EDIT_DISTANCE: f32 = 100.0;
+
+/// The voxel placed with the right mouse button. The number keys pick a registered id.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SelectedVoxel(pub Voxel);
+
+impl Default for SelectedVoxel {
+    fn default() -> Self {
+        Self(Voxel::A2_VOXEL)
+    }
+}
+
+pub fn select_voxel_system(
+    keys: Res<Input<KeyCode>>,
+    registry: Res<VoxelRegistry>,
+    mut selected: ResMut<SelectedVoxel>,
+) {
+    const NUMBER_KEYS: [KeyCode; 9] = [
+        KeyCode::Key1,
+        KeyCode::Key2,
+        KeyCode::Key3,
+        KeyCode::Key4,
+        KeyCode::Key5,
+        KeyCode::Key6,
+        KeyCode::Key7,
+        KeyCode::Key8,
+        KeyCode::Key9,
+    ];
+    for (i, key) in NUMBER_KEYS.into_iter().enumerate() {
+        let voxel = Voxel(i as u8 + 1);
+        if keys.just_pressed(key) {
+            if let Some(voxel_type) = registry.get(voxel) {
+                info!("selected voxel: {}", voxel_type.name);
+                selected.0 = voxel;
+            }
+        }
+    }
+}
+
+/// Edits the grid without flagging all of it as changed, which would remesh every chunk, and marks only the chunks
+/// whose meshes the edits can change [`Dirty`].
+struct ChunkEdit<'a> {
+    grid: &'a mut ChunkGrid,
+    touched: HashSet<UVec3>,
+}
+
+impl<'a> ChunkEdit<'a> {
+    fn new(grid: &'a mut ResMut<ChunkGrid>) -> Self {
+        Self {
+            grid: grid.bypass_change_detection(),
+            touched: HashSet::default(),
+        }
+    }
+
+    fn set_voxel(&mut self, world: UVec3, voxel: Voxel) {
+        self.touched.extend(self.grid.set_voxel(world, voxel));
+    }
+
+    fn finish(self, commands: &mut Commands) {
+        for coord in self.touched {
+            if let Some(entity) = self.grid.chunk(coord).entity {
+                commands.entity(entity).insert(Dirty);
+            }
+        }
+    }
+}
+
+impl BrushTarget for ChunkEdit<'_> {
+    fn paint(&mut self, position: IVec3, voxel: Voxel) {
+        if self.grid.contains(position) {
+            self.set_voxel(position.as_uvec3(), voxel);
+        }
+    }
+}
+
+/// The voxel in the middle of the screen, which is the one the mouse buttons edit.
+fn pointed_voxel(
+    camera: &CameraRotationState,
+    transforms: &Query<&GlobalTransform>,
+    grid: &ChunkGrid,
+    registry: &VoxelRegistry,
+) -> Option<RayHit> {
+    let camera_transform = transforms.get(camera.camera).ok()?;
+    let (origin, direction) = (camera_transform.translation(), camera_transform.forward());
+    raycast(grid, registry, origin, direction, EDIT_DISTANCE)
+}
+
+/// Removes the voxel in the middle of the screen on left click, and places the [`SelectedVoxel`] against it on right
+/// click. Only the chunks around the edited voxel get remeshed.
+pub fn edit_voxel_system(
+    mut commands: Commands,
+    buttons: Res<Input<MouseButton>>,
+    camera: Res<CameraRotationState>,
+    registry: Res<VoxelRegistry>,
+    selected: Res<SelectedVoxel>,
+    transforms: Query<&GlobalTransform>,
+    mut grid: ResMut<ChunkGrid>,
+) {
+    let remove = buttons.just_pressed(MouseButton::Left);
+    let place = buttons.just_pressed(MouseButton::Right);
+    if !(remove || place) {
+        return;
+    }
+    let Some(hit) = pointed_voxel(&camera, &transforms, &grid, &registry) else {
+        return;
+    };
+
+    let mut edit = ChunkEdit::new(&mut grid);
+    if remove {
+        edit.set_voxel(hit.voxel, Voxel::EMPTY_VOXEL);
+    } else if hit.normal != IVec3::ZERO {
+        edit.paint(hit.voxel.as_ivec3() + hit.normal, selected.0);
+    }
+    edit.finish(&mut commands);
+}
+
+/// The size of the brushes, in voxels from the center. Comma and period make it smaller and bigger.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BrushSettings {
+    pub radius: u32,
+}
+
+impl Default for BrushSettings {
+    fn default() -> Self {
+        Self { radius: 2 }
+    }
+}
+
+impl BrushSettings {
+    pub const MAX_RADIUS: u32 = 8;
+}
+
+/// B fills a sphere and H a box of the [`SelectedVoxel`] around the voxel a right click would place. Holding shift
+/// carves the shape out around the pointed voxel instead.
+#[allow(clippy::too_many_arguments)]
+pub fn brush_system(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    camera: Res<CameraRotationState>,
+    registry: Res<VoxelRegistry>,
+    selected: Res<SelectedVoxel>,
+    transforms: Query<&GlobalTransform>,
+    mut settings: ResMut<BrushSettings>,
+    mut grid: ResMut<ChunkGrid>,
+) {
+    if bindings.just_pressed(&keys, Action::ShrinkBrush) && settings.radius > 1 {
+        settings.radius -= 1;
+        info!("brush radius: {}", settings.radius);
+    }
+    if bindings.just_pressed(&keys, Action::GrowBrush)
+        && settings.radius < BrushSettings::MAX_RADIUS
+    {
+        settings.radius += 1;
+        info!("brush radius: {}", settings.radius);
+    }
+
+    let carve_sphere = bindings.just_pressed(&keys, Action::CarveSphere);
+    let carve = carve_sphere || bindings.just_pressed(&keys, Action::CarveBox);
+    let sphere = carve_sphere || bindings.just_pressed(&keys, Action::SphereBrush);
+    if !(carve || sphere || bindings.just_pressed(&keys, Action::BoxBrush)) {
+        return;
+    }
+    let Some(hit) = pointed_voxel(&camera, &transforms, &grid, &registry) else {
+        return;
+    };
+    let (center, voxel) = if carve {
+        (hit.voxel.as_ivec3(), Voxel::EMPTY_VOXEL)
+    } else {
+        (hit.voxel.as_ivec3() + hit.normal, selected.0)
+    };
+    let radius = settings.radius;
+    let mut edit = ChunkEdit::new(&mut grid);
+    if sphere {
+        fill_sphere(&mut edit, center, radius, voxel);
+    } else {
+        let extent = IVec3::splat(radius as i32);
+        fill_box(&mut edit, center - extent, center + extent, voxel);
+    }
+    edit.finish(&mut commands);
+}
+
+/// The outline drawn around the face the editing ray hits.
+#[derive(Component)]
+pub struct HoveredFaceOutline;
+
+/// How far the outline floats in front of the face, in voxels, so it isn't hidden by the face itself.
+const OUTLINE_OFFSET: f32 = 0.01;
+
+/// Outlines the face of the voxel in the middle of the screen that a right click would attach the new voxel to, like
+/// the selection box of most block games. The outline is a line strip that's only rebuilt when another face is hit.
+#[allow(clippy::too_many_arguments)]
+pub fn hovered_face_outline_system(
+    mut commands: Commands,
+    camera: Res<CameraRotationState>,
+    registry: Res<VoxelRegistry>,
+    transforms: Query<&GlobalTransform>,
+    grid: Res<ChunkGrid>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut outline: Query<(&Handle<Mesh>, &mut Visibility), With<HoveredFaceOutline>>,
+    mut last_hit: Local<Option<RayHit>>,
+) {
+    let hit = pointed_voxel(&camera, &transforms, &grid, &registry);
+    let corners = hit.and_then(|hit| hit.face_corners(&grid));
+    let Ok((mesh, mut visibility)) = outline.get_single_mut() else {
+        commands.spawn((
+            PbrBundle {
+                mesh: meshes.add(Mesh::new(PrimitiveTopology::LineStrip)),
+                material: materials.add(StandardMaterial {
+                    base_color: Color::BLACK,
+                    unlit: true,
+                    ..default()
+                }),
+                visibility: Visibility { is_visible: false },
+                ..default()
+            },
+            NotShadowCaster,
+            HoveredFaceOutline,
+        ));
+        return;
+    };
+    if visibility.is_visible != corners.is_some() {
+        visibility.is_visible = corners.is_some();
+    }
+    if hit == *last_hit && !grid.is_changed() {
+        return;
+    }
+    *last_hit = hit;
+    let (Some(corners), Some(mesh)) = (corners, meshes.get_mut(mesh)) else {
+        return;
+    };
+    let offset =
+        hit.map_or(Vec3::ZERO, |hit| hit.normal.as_vec3()) * OUTLINE_OFFSET * grid.voxel_scale();
+    // Back to the first corner to close the loop.
+    let positions: Vec<[f32; 3]> = [0, 1, 2, 3, 0]
+        .map(|i| (corners[i] + offset).to_array())
+        .to_vec();
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+}
+
+/// Shows the grid coordinates and type of the voxel that would be edited, and which of its faces is pointed at, in the
+/// bottom left corner.
+pub fn hovered_voxel_readout_system(
+    mut egui_context: ResMut<EguiContext>,
+    camera: Res<CameraRotationState>,
+    registry: Res<VoxelRegistry>,
+    transforms: Query<&GlobalTransform>,
+    grid: Res<ChunkGrid>,
+) {
+    let text = match pointed_voxel(&camera, &transforms, &grid, &registry) {
+        Some(hit) => {
+            let name = registry
+                .get(grid.voxel(hit.voxel))
+                .map_or("unknown", |voxel_type| &voxel_type.name);
+            let [x, y, z] = hit.voxel.to_array();
+            match VoxelFace::from_normal(hit.normal) {
+                Some(face) => format!("Voxel: [{x}, {y}, {z}] {name}, {face:?} face"),
+                None => format!("Voxel: [{x}, {y}, {z}] {name}"),
+            }
+        }
+        None => "Voxel: no hit".to_string(),
+    };
+    egui::Area::new("hovered_voxel")
+        .anchor(egui::Align2::LEFT_BOTTOM, [8.0, -8.0])
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.label(text);
+        });
+}
@@ -0,0 +1,208 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use bevy::prelude::*;
+
+use crate::ao::AoSettings;
+use crate::atlas::{AtlasConfig, TILE_UV_STRIDE};
+use crate::chunk::ChunkGrid;
+use crate::input::{Action, KeyBindings};
+use crate::meshing::{CoordinateSystem, MeshData, MeshingMode, RenderMode};
+use crate::parallel::mesh_grid_parallel;
+use crate::voxel::VoxelRegistry;
+
+/// Where Ctrl+E exports the world's mesh to.
+const EXPORT_PATH: &str = "world.obj";
+
+/// Writes `data` as a Wavefront OBJ file, which Blender and most other modelling tools can import. See [`write_obj`].
+pub fn export_obj(path: impl AsRef<Path>, data: &MeshData, atlas: &AtlasConfig) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    write_obj(&mut file, data, atlas)?;
+    file.flush()
+}
+
+/// The UVs of one quad in the atlas, from the ones [`AtlasConfig::repeat_tile`] encodes for the voxel shader. A quad's
+/// tile is where its smallest UV is, and the rest runs from 0 to 1 across a quad of a single voxel. OBJ has no way to
+/// repeat a tile across a bigger quad, so the mesh has to come from [`MeshingMode::Simple`].
+fn atlas_uvs<'a>(atlas: &AtlasConfig, uvs: &'a [[f32; 2]]) -> impl Iterator<Item = Vec2> + 'a {
+    let min = uvs
+        .iter()
+        .copied()
+        .map(Vec2::from)
+        .fold(Vec2::splat(f32::MAX), Vec2::min);
+    let tile = (min / TILE_UV_STRIDE).floor();
+    let tiles = atlas.tiles();
+    uvs.iter()
+        .map(move |&uv| (tile + (Vec2::from(uv) - tile * TILE_UV_STRIDE)) / tiles)
+}
+
+/// Writes the positions, UVs, normals and triangles of `data` in the OBJ text format. Every vertex gets its own
+/// position, UV and normal, so a face refers to all three by the same index. The UVs are turned from the
+/// voxel shader's tiles into UVs into `atlas`, which only works for [`MeshingMode::Simple`] meshes, and since OBJ puts
+/// their origin in the bottom left corner, V is flipped.
+pub fn write_obj(writer: &mut impl Write, data: &MeshData, atlas: &AtlasConfig) -> io::Result<()> {
+    writeln!(writer, "# exported by HackyBlockMeshAo")?;
+    for [x, y, z] in &data.positions {
+        writeln!(writer, "v {x} {y} {z}")?;
+    }
+    for quad in data.uvs.chunks(4) {
+        for uv in atlas_uvs(atlas, quad) {
+            writeln!(writer, "vt {} {}", uv.x, 1.0 - uv.y)?;
+        }
+    }
+    for [x, y, z] in &data.normals {
+        writeln!(writer, "vn {x} {y} {z}")?;
+    }
+    for triangle in data.indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| triangle[i] + 1);
+        writeln!(writer, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}")?;
+    }
+    Ok(())
+}
+
+/// Appends the vertices and indices of `other` to `data`, moved by `offset`.
+fn append_translated(data: &mut MeshData, other: MeshData, offset: Vec3) {
+    let start = data.positions.len() as u32;
+    data.positions.extend(
+        other
+            .positions
+            .into_iter()
+            .map(|position| (Vec3::from(position) + offset).to_array()),
+    );
+    data.indices
+        .extend(other.indices.into_iter().map(|index| index + start));
+    data.normals.extend(other.normals);
+    data.uvs.extend(other.uvs);
+    data.tangents.extend(other.tangents);
+    data.ao.extend(other.ao);
+    data.colors.extend(other.colors);
+    data.emissive.extend(other.emissive);
+    data.light.extend(other.light);
+    data.flow.extend(other.flow);
+}
+
+/// Meshes the whole grid again, spread over all cores, and puts every chunk's opaque and translucent faces into one
+/// mesh, in world space.
+pub fn world_mesh_data(
+    grid: &ChunkGrid,
+    registry: &VoxelRegistry,
+    atlas: &AtlasConfig,
+    ao_settings: &AoSettings,
+    mode: MeshingMode,
+    coordinates: CoordinateSystem,
+) -> MeshData {
+    let chunks = mesh_grid_parallel(
+        grid,
+        registry,
+        atlas,
+        ao_settings,
+        mode,
+        RenderMode::Textured,
+        coordinates,
+    );
+    let mut world = MeshData::default();
+    for (coord, chunk) in chunks {
+        let offset = grid.chunk_translation(coord);
+        append_translated(&mut world, chunk.opaque, offset);
+        append_translated(&mut world, chunk.translucent, offset);
+    }
+    world
+}
+
+/// Ctrl+E exports the world's mesh to an OBJ file, meshed with the current settings except that it's always
+/// [`MeshingMode::Simple`], see [`write_obj`].
+pub fn export_obj_system(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    grid: Res<ChunkGrid>,
+    registry: Res<VoxelRegistry>,
+    atlas: Res<AtlasConfig>,
+    ao_settings: Res<AoSettings>,
+    coordinates: Res<CoordinateSystem>,
+) {
+    if !bindings.just_pressed(&keys, Action::ExportObj) {
+        return;
+    }
+    let data = world_mesh_data(
+        &grid,
+        &registry,
+        &atlas,
+        &ao_settings,
+        MeshingMode::Simple,
+        *coordinates,
+    );
+    match export_obj(EXPORT_PATH, &data, &atlas) {
+        Ok(()) => info!(
+            "exported {} triangles to {EXPORT_PATH}",
+            data.indices.len() / 3
+        ),
+        Err(err) => error!("failed to export {EXPORT_PATH}: {err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::Voxel;
+
+    #[test]
+    fn obj_has_a_line_per_vertex_and_triangle() {
+        let mut grid = ChunkGrid::new(2);
+        grid.set_voxel(UVec3::splat(19), Voxel::STONE_VOXEL);
+        grid.set_voxel(UVec3::splat(20), Voxel::STONE_VOXEL);
+        let data = world_mesh_data(
+            &grid,
+            &VoxelRegistry::default(),
+            &AtlasConfig::default(),
+            &AoSettings::default(),
+            MeshingMode::Simple,
+            CoordinateSystem::default(),
+        );
+        // Two voxels in different chunks that only touch at a corner.
+        assert_eq!(data.positions.len(), 2 * 6 * 4);
+
+        let atlas = AtlasConfig::default();
+        let mut obj = Vec::new();
+        write_obj(&mut obj, &data, &atlas).unwrap();
+        let obj = String::from_utf8(obj).unwrap();
+        let count = |prefix: &str| obj.lines().filter(|line| line.starts_with(prefix)).count();
+        assert_eq!(count("v "), 48);
+        assert_eq!(count("vt "), 48);
+        assert_eq!(count("vn "), 48);
+        assert_eq!(count("f "), 24);
+        // Indices are 1 based, and the second chunk's refer to its own vertices.
+        assert!(obj.contains("f 1/1/1 "));
+        assert!(obj.contains(" 48/48/48"));
+        // The voxels end up where the chunks are, a voxel apart on every axis.
+        let (min, max) = data.positions.iter().fold(
+            (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+            |(min, max), &p| (min.min(Vec3::from(p)), max.max(Vec3::from(p))),
+        );
+        assert_eq!(max - min, Vec3::splat(2.0));
+
+        // Every UV lands in the atlas, and each quad covers exactly one tile of it.
+        let uvs: Vec<Vec2> = obj
+            .lines()
+            .filter_map(|line| line.strip_prefix("vt "))
+            .map(|uv| {
+                let [u, v] = [0, 1].map(|i| uv.split(' ').nth(i).unwrap().parse().unwrap());
+                Vec2::new(u, v)
+            })
+            .collect();
+        let tile = Vec2::ONE / atlas.tiles();
+        for quad in uvs.chunks(4) {
+            let min = quad.iter().copied().fold(Vec2::splat(f32::MAX), Vec2::min);
+            let max = quad.iter().copied().fold(Vec2::splat(f32::MIN), Vec2::max);
+            assert!(
+                min.cmpge(Vec2::ZERO).all() && max.cmple(Vec2::ONE).all(),
+                "{quad:?}"
+            );
+            assert!((max - min - tile).abs().max_element() < 1e-5, "{quad:?}");
+            assert!(
+                ((min / tile).round() * tile - min).abs().max_element() < 1e-5,
+                "{quad:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use bevy::prelude::*;
+
+/// The color behind the world, a light blue sky. [`crate::plugin::VoxelMeshPlugin`] sets it as the [`ClearColor`]
+/// unless the app already has a color of its own.
+pub const SKY_COLOR: Color = Color::rgb(0.53, 0.72, 0.9);
+
+/// Distance fog, which fades the chunks into `color` so far away geometry is easier to tell from near geometry. It's
+/// applied by the chunk material, so other meshes aren't fogged.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct FogSettings {
+    pub enabled: bool,
+    /// Usually the [`ClearColor`], so fully fogged chunks blend into the sky.
+    pub color: Color,
+    /// The distance from the camera where the fog starts, in world units.
+    pub start: f32,
+    /// The distance where nothing but the fog color is left.
+    pub end: f32,
+}
+
+impl Default for FogSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            color: SKY_COLOR,
+            start: 40.0,
+            end: 120.0,
+        }
+    }
+}
+
+impl FogSettings {
+    /// How much of the fog color covers a fragment `distance` away from the camera, from 0 to 1. The fog thickens
+    /// linearly between [`FogSettings::start`] and [`FogSettings::end`], like `voxel.wgsl` does it.
+    pub fn amount(&self, distance: f32) -> f32 {
+        if !self.enabled || self.end <= self.start {
+            return 0.0;
+        }
+        ((distance - self.start) / (self.end - self.start)).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fog_thickens_between_start_and_end() {
+        let fog = FogSettings {
+            start: 10.0,
+            end: 30.0,
+            ..default()
+        };
+        assert_eq!(fog.amount(5.0), 0.0);
+        assert_eq!(fog.amount(20.0), 0.5);
+        assert_eq!(fog.amount(50.0), 1.0);
+
+        let off = FogSettings {
+            enabled: false,
+            ..fog
+        };
+        assert_eq!(off.amount(50.0), 0.0);
+    }
+}
@@ -0,0 +1,303 @@
+use bevy::prelude::*;
+use block_mesh::{Axis, OrientedBlockFace, UnorientedQuad, RIGHT_HANDED_Y_UP_CONFIG};
+
+use crate::ao::{ao_quad_indices, vertex_ao, MAX_AO};
+use crate::atlas::{AtlasConfig, TILE_UV_STRIDE};
+use crate::meshing::{quad_tangent, CoordinateSystem, MeshData};
+use crate::voxel::{Voxel, VoxelFace, VoxelRegistry};
+
+/// One column of a [`Heightmap`]: `height` voxels of `voxel`, stacked up from the ground. A height of 0 is a hole.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Column {
+    pub height: u32,
+    pub voxel: Voxel,
+}
+
+/// A world where every column is solid from the ground up, like plain terrain. It only needs a height per column
+/// instead of a voxel per cell, so it can be much larger than a [`crate::ChunkGrid`], see [`mesh_heightmap`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Heightmap {
+    size: UVec2,
+    columns: Vec<Column>,
+}
+
+impl Heightmap {
+    /// A heightmap of `size.x` by `size.y` columns along X and Z, filled by `column(x, z)`.
+    pub fn from_fn(size: UVec2, mut column: impl FnMut(u32, u32) -> Column) -> Self {
+        let columns = (0..size.y)
+            .flat_map(|z| (0..size.x).map(move |x| (x, z)))
+            .map(|(x, z)| column(x, z))
+            .collect();
+        Self { size, columns }
+    }
+
+    /// The number of columns along X and Z.
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// The column at `x` and `z`, or an empty one outside the map, so the map is surrounded by holes.
+    pub fn get(&self, x: i32, z: i32) -> Column {
+        if x < 0 || z < 0 || x >= self.size.x as i32 || z >= self.size.y as i32 {
+            return Column::default();
+        }
+        self.columns[(z as u32 * self.size.x + x as u32) as usize]
+    }
+
+    fn height(&self, x: i32, z: i32) -> u32 {
+        self.get(x, z).height
+    }
+}
+
+/// Meshes a [`Heightmap`] without ever looking at the voxels below the surface: every column gets its top and a wall
+/// towards each lower neighbour, one quad as high as the step, or a stack of them for a step too high for
+/// [`TILE_UV_STRIDE`]. That's the same surface the general mesher finds for the voxels, minus the bottom faces nobody
+/// sees, but in a single pass over the columns.
+///
+/// Tops get the usual AO from the neighbouring columns, in block-mesh's four levels, while the walls are left
+/// unoccluded. The mesh has column `[0, 0]` at the origin, Y up, and voxels `voxel_scale` units across.
+pub fn mesh_heightmap(
+    heights: &Heightmap,
+    registry: &VoxelRegistry,
+    atlas: &AtlasConfig,
+    voxel_scale: f32,
+) -> MeshData {
+    let faces = RIGHT_HANDED_Y_UP_CONFIG.faces;
+    let mut data = MeshData::default();
+    for z in 0..heights.size.y as i32 {
+        for x in 0..heights.size.x as i32 {
+            let Column { height, voxel } = heights.get(x, z);
+            if height == 0 {
+                continue;
+            }
+            for (face, voxel_face) in faces.iter().zip(VoxelFace::ALL) {
+                let normal = voxel_face.normal();
+                if normal.y == 1 {
+                    let quad = UnorientedQuad {
+                        minimum: [x as u32, height - 1, z as u32],
+                        width: 1,
+                        height: 1,
+                        ao: [MAX_AO; 4],
+                    };
+                    let corners = face.quad_mesh_positions(&quad, 1.0);
+                    let ao = corners.map(|[cx, _, cz]| {
+                        let du = if cx as i32 > x { 1 } else { -1 };
+                        let dv = if cz as i32 > z { 1 } else { -1 };
+                        let above = |dx, dz| heights.height(x + dx, z + dz) > height;
+                        vertex_ao(above(du, 0), above(0, dv), above(du, dv))
+                    });
+                    push_quad(
+                        &mut data,
+                        registry,
+                        atlas,
+                        face,
+                        voxel_face,
+                        &quad,
+                        voxel,
+                        ao,
+                        voxel_scale,
+                    );
+                } else if normal.y == 0 {
+                    let neighbour = heights.height(x + normal.x, z + normal.z);
+                    if neighbour >= height {
+                        continue;
+                    }
+                    // One of the two axes along the face is Y, which the wall spans from the neighbour's top up. The
+                    // UVs of a quad run up to its size, so no piece may reach the next tile.
+                    let mut bottom = neighbour;
+                    while bottom < height {
+                        let step = (height - bottom).min(TILE_UV_STRIDE as u32 - 1);
+                        let (width, quad_height) = match face.permutation().axes()[1] {
+                            Axis::Y => (step, 1),
+                            _ => (1, step),
+                        };
+                        let quad = UnorientedQuad {
+                            minimum: [x as u32, bottom, z as u32],
+                            width,
+                            height: quad_height,
+                            ao: [MAX_AO; 4],
+                        };
+                        push_quad(
+                            &mut data,
+                            registry,
+                            atlas,
+                            face,
+                            voxel_face,
+                            &quad,
+                            voxel,
+                            [MAX_AO; 4],
+                            voxel_scale,
+                        );
+                        bottom += step;
+                    }
+                }
+            }
+        }
+    }
+    data
+}
+
+/// Appends one textured quad of `voxel` to `data`, like [`crate::meshing::mesh_chunk`] does in
+/// [`crate::meshing::RenderMode::Textured`].
+#[allow(clippy::too_many_arguments)]
+fn push_quad(
+    data: &mut MeshData,
+    registry: &VoxelRegistry,
+    atlas: &AtlasConfig,
+    face: &OrientedBlockFace,
+    voxel_face: VoxelFace,
+    quad: &UnorientedQuad,
+    voxel: Voxel,
+    ao: [u8; 4],
+    voxel_scale: f32,
+) {
+    let voxel_type = registry.get_or_missing(voxel);
+    data.indices.extend_from_slice(&ao_quad_indices(
+        face,
+        data.positions.len() as u32,
+        ao,
+        false,
+        false,
+    ));
+    let positions = face.quad_mesh_positions(quad, voxel_scale);
+    let normals = face.quad_mesh_normals();
+    let [column, row] = voxel_type.textures.for_face(voxel_face);
+    let uvs = atlas.repeat_tile(
+        column,
+        row,
+        CoordinateSystem::RightHandedYUp.tex_coords(face, quad),
+    );
+    data.positions.extend_from_slice(&positions);
+    data.normals.extend_from_slice(&normals);
+    data.uvs.extend_from_slice(&uvs);
+    data.tangents
+        .extend_from_slice(&[quad_tangent(&positions, &uvs, normals[0]); 4]);
+    data.ao.extend_from_slice(&ao);
+    data.colors.extend_from_slice(&[[1.0; 4]; 4]);
+    data.emissive
+        .extend_from_slice(&[voxel_type.emissive_strength; 4]);
+    data.light.extend_from_slice(&[0; 4]);
+    let flow = if voxel_type.flowing { 1.0 } else { 0.0 };
+    data.flow.extend_from_slice(&[flow; 4]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ao::AoSettings;
+    use crate::meshing::{mesh_chunk, MeshingMode, RenderMode};
+    use crate::volume::VoxelVolume;
+
+    /// The area of every quad, with the normal it faces.
+    fn quad_areas(data: &MeshData) -> Vec<(IVec3, f32)> {
+        data.positions
+            .chunks_exact(4)
+            .zip(data.normals.chunks_exact(4))
+            .map(|(corners, normals)| {
+                let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(corners[i]));
+                (
+                    Vec3::from(normals[0]).as_ivec3(),
+                    (b - a).cross(c - a).length(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn heightmap_covers_the_same_surface_with_fewer_quads() {
+        let heights = Heightmap::from_fn(UVec2::new(12, 9), |x, z| Column {
+            height: 1 + (x * 7 + z * 3) % 11,
+            voxel: Voxel::STONE_VOXEL,
+        });
+        let data = mesh_heightmap(
+            &heights,
+            &VoxelRegistry::default(),
+            &AtlasConfig::default(),
+            1.0,
+        );
+
+        let mut voxels = VoxelVolume::default();
+        for z in 0..9 {
+            for x in 0..12 {
+                for y in 0..heights.height(x, z) {
+                    // Padded voxel 1 is the first voxel of the chunk.
+                    voxels.set(x as u32 + 1, y + 1, z as u32 + 1, Voxel::STONE_VOXEL);
+                }
+            }
+        }
+        let general = mesh_chunk(
+            &voxels,
+            &VoxelRegistry::default(),
+            &AtlasConfig::default(),
+            &AoSettings::default(),
+            MeshingMode::Simple,
+            RenderMode::Textured,
+            CoordinateSystem::default(),
+            1.0,
+        )
+        .opaque;
+
+        // Apart from the bottom faces, both meshes cover the same area facing every way.
+        let area = |data: &MeshData, normal: IVec3| -> f32 {
+            quad_areas(data)
+                .into_iter()
+                .filter(|&(n, _)| n == normal)
+                .map(|(_, area)| area)
+                .sum()
+        };
+        for normal in [IVec3::X, -IVec3::X, IVec3::Y, IVec3::Z, -IVec3::Z] {
+            assert_eq!(area(&data, normal), area(&general, normal), "{normal}");
+        }
+        assert_eq!(area(&data, -IVec3::Y), 0.0);
+
+        let triangles = data.indices.len() / 3;
+        let general_triangles = general.indices.len() / 3;
+        assert!(
+            triangles * 2 < general_triangles,
+            "{triangles} against {general_triangles}"
+        );
+        // One top per column, and the AO of the tops darkens the feet of the walls around them.
+        assert_eq!(area(&data, IVec3::Y), 12.0 * 9.0);
+        assert!(data.ao.iter().any(|&ao| ao < MAX_AO));
+    }
+
+    #[test]
+    fn tall_walls_stay_within_their_tile() {
+        let heights = Heightmap::from_fn(UVec2::new(1, 1), |_, _| Column {
+            height: 100,
+            voxel: Voxel::STONE_VOXEL,
+        });
+        let data = mesh_heightmap(
+            &heights,
+            &VoxelRegistry::default(),
+            &AtlasConfig::default(),
+            1.0,
+        );
+        for uvs in data.uvs.chunks_exact(4) {
+            let min = uvs
+                .iter()
+                .copied()
+                .map(Vec2::from)
+                .fold(Vec2::splat(f32::MAX), Vec2::min);
+            let max = uvs
+                .iter()
+                .copied()
+                .map(Vec2::from)
+                .fold(Vec2::splat(f32::MIN), Vec2::max);
+            let tile = (min / TILE_UV_STRIDE).floor();
+            assert!(
+                (max - tile * TILE_UV_STRIDE)
+                    .cmplt(Vec2::splat(TILE_UV_STRIDE))
+                    .all(),
+                "{uvs:?}"
+            );
+        }
+        // The pieces still add up to the whole wall on each side.
+        let walls: f32 = quad_areas(&data)
+            .into_iter()
+            .filter(|&(normal, _)| normal.y == 0)
+            .map(|(_, area)| area)
+            .sum();
+        assert_eq!(walls, 4.0 * 100.0);
+    }
+}
@@ -0,0 +1,371 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+/// Everything the keyboard does, see [`KeyBindings`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    ToggleMeshingMode,
+    ToggleRenderMode,
+    ToggleNormalMode,
+    /// Pushes the quads further apart.
+    Explode,
+    /// Pulls the quads closer together again.
+    Implode,
+    CycleAoQuality,
+    ToggleAoInterpolation,
+    ToggleQuadFlipping,
+    StrongerAo,
+    WeakerAo,
+    ToggleAoShading,
+    ToggleTextureLayout,
+    ToggleSamplerFilter,
+    ToggleWireframe,
+    ToggleChunkBounds,
+    ToggleCrosshair,
+    ToggleDayCycle,
+    ToggleCameraMode,
+    ToggleProjection,
+    OrbitFaster,
+    OrbitSlower,
+    FlyForward,
+    FlyBack,
+    FlyLeft,
+    FlyRight,
+    FlyUp,
+    FlyDown,
+    LowerCrossSection,
+    RaiseCrossSection,
+    SphereBrush,
+    BoxBrush,
+    /// Empties a sphere of the brush's size instead of filling it.
+    CarveSphere,
+    /// Empties a box of the brush's size instead of filling it.
+    CarveBox,
+    ShrinkBrush,
+    GrowBrush,
+    SwitchGenerator,
+    NewSeed,
+    /// Turns the loaded model a quarter turn around Y.
+    RotateModel,
+    /// Mirrors the loaded model along X.
+    MirrorModel,
+    Save,
+    Load,
+    ExportObj,
+    Screenshot,
+}
+
+impl Action {
+    /// Every action, in the order the settings panel lists them.
+    pub const ALL: [Action; 43] = [
+        Action::ToggleMeshingMode,
+        Action::ToggleRenderMode,
+        Action::ToggleNormalMode,
+        Action::Explode,
+        Action::Implode,
+        Action::CycleAoQuality,
+        Action::ToggleAoInterpolation,
+        Action::ToggleQuadFlipping,
+        Action::StrongerAo,
+        Action::WeakerAo,
+        Action::ToggleAoShading,
+        Action::ToggleTextureLayout,
+        Action::ToggleSamplerFilter,
+        Action::ToggleWireframe,
+        Action::ToggleChunkBounds,
+        Action::ToggleCrosshair,
+        Action::ToggleDayCycle,
+        Action::ToggleCameraMode,
+        Action::ToggleProjection,
+        Action::OrbitFaster,
+        Action::OrbitSlower,
+        Action::FlyForward,
+        Action::FlyBack,
+        Action::FlyLeft,
+        Action::FlyRight,
+        Action::FlyUp,
+        Action::FlyDown,
+        Action::LowerCrossSection,
+        Action::RaiseCrossSection,
+        Action::SphereBrush,
+        Action::BoxBrush,
+        Action::CarveSphere,
+        Action::CarveBox,
+        Action::ShrinkBrush,
+        Action::GrowBrush,
+        Action::SwitchGenerator,
+        Action::NewSeed,
+        Action::RotateModel,
+        Action::MirrorModel,
+        Action::Save,
+        Action::Load,
+        Action::ExportObj,
+        Action::Screenshot,
+    ];
+
+    /// The keys the action is bound to unless [`KeyBindings`] says otherwise. No two actions share a binding, and
+    /// none of them is a modifier on its own, since those are what tell bindings of the same key apart.
+    fn default_keys(self) -> Vec<KeyBinding> {
+        use KeyCode::*;
+        let keys: &[KeyCode] = match self {
+            Action::Implode => return vec![KeyBinding::shift(Y)],
+            Action::CarveSphere => return vec![KeyBinding::shift(B)],
+            Action::CarveBox => return vec![KeyBinding::shift(H)],
+            Action::MirrorModel => return vec![KeyBinding::shift(U)],
+            Action::Save => return vec![KeyBinding::ctrl(S)],
+            Action::Load => return vec![KeyBinding::ctrl(L)],
+            Action::ExportObj => return vec![KeyBinding::ctrl(E)],
+            Action::ToggleMeshingMode => &[G],
+            Action::ToggleRenderMode => &[V],
+            Action::ToggleNormalMode => &[N],
+            Action::Explode => &[Y],
+            Action::CycleAoQuality => &[Q],
+            Action::ToggleAoInterpolation => &[I],
+            Action::ToggleQuadFlipping => &[O],
+            Action::StrongerAo => &[RBracket],
+            Action::WeakerAo => &[LBracket],
+            Action::ToggleAoShading => &[J],
+            Action::ToggleTextureLayout => &[Z],
+            Action::ToggleSamplerFilter => &[P],
+            Action::ToggleWireframe => &[F],
+            Action::ToggleChunkBounds => &[F3],
+            Action::ToggleCrosshair => &[X],
+            Action::ToggleDayCycle => &[K],
+            Action::ToggleCameraMode => &[C],
+            Action::ToggleProjection => &[M],
+            Action::OrbitFaster => &[Equals, NumpadAdd],
+            Action::OrbitSlower => &[Minus, NumpadSubtract],
+            Action::FlyForward => &[W],
+            Action::FlyBack => &[S],
+            Action::FlyLeft => &[A],
+            Action::FlyRight => &[D],
+            Action::FlyUp => &[Space],
+            Action::FlyDown => &[E],
+            Action::LowerCrossSection => &[Down],
+            Action::RaiseCrossSection => &[Up],
+            Action::SphereBrush => &[B],
+            Action::BoxBrush => &[H],
+            Action::ShrinkBrush => &[Comma],
+            Action::GrowBrush => &[Period],
+            Action::SwitchGenerator => &[T],
+            Action::NewSeed => &[R],
+            Action::RotateModel => &[U],
+            Action::Screenshot => &[F12],
+        };
+        keys.iter().copied().map(KeyBinding::from).collect()
+    }
+}
+
+/// A key that has to be held together with a [`KeyBinding`]'s own, on either side of the keyboard.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Modifier {
+    Shift,
+    Ctrl,
+}
+
+impl Modifier {
+    pub const ALL: [Modifier; 2] = [Modifier::Shift, Modifier::Ctrl];
+
+    /// The left and right key.
+    pub fn keys(self) -> [KeyCode; 2] {
+        match self {
+            Modifier::Shift => [KeyCode::LShift, KeyCode::RShift],
+            Modifier::Ctrl => [KeyCode::LControl, KeyCode::RControl],
+        }
+    }
+}
+
+/// A key, and the [`Modifier`] that has to be held with it. Where the same key is also bound with another modifier,
+/// that one must not be held, so Ctrl+S and plain S are different bindings that never trigger together. Any other
+/// modifier can be held or not, so flying on with shift held still works.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyBinding {
+    pub key: KeyCode,
+    pub modifier: Option<Modifier>,
+}
+
+impl From<KeyCode> for KeyBinding {
+    fn from(key: KeyCode) -> Self {
+        Self {
+            key,
+            modifier: None,
+        }
+    }
+}
+
+impl KeyBinding {
+    /// `key` with shift held.
+    pub fn shift(key: KeyCode) -> Self {
+        Self {
+            key,
+            modifier: Some(Modifier::Shift),
+        }
+    }
+
+    /// `key` with ctrl held.
+    pub fn ctrl(key: KeyCode) -> Self {
+        Self {
+            key,
+            modifier: Some(Modifier::Ctrl),
+        }
+    }
+}
+
+impl std::fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if let Some(modifier) = self.modifier {
+            write!(f, "{modifier:?}+")?;
+        }
+        write!(f, "{:?}", self.key)
+    }
+}
+
+/// Which keys trigger each [`Action`]. The systems only ask this resource, so a binding can be changed by inserting
+/// an edited copy before `App::run`, or at any time after:
+///
+/// ```no_run
+/// # use bevy::prelude::*;
+/// # use uv_mapping::input::{Action, KeyBindings};
+/// App::new().insert_resource(KeyBindings::default().with(Action::ToggleWireframe, KeyCode::Tab));
+/// ```
+///
+/// The plugins only add the default bindings if there are none yet. A binding can need shift or ctrl held, see
+/// [`KeyBinding`]. The number keys of the voxel palette aren't actions, since they're a row of ids rather than one
+/// thing each.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct KeyBindings {
+    keys: HashMap<Action, Vec<KeyBinding>>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            keys: Action::ALL
+                .into_iter()
+                .map(|action| (action, action.default_keys()))
+                .collect(),
+        }
+    }
+}
+
+impl KeyBindings {
+    /// Binds `action` to `key` alone.
+    pub fn with(mut self, action: Action, key: impl Into<KeyBinding>) -> Self {
+        self.bind(action, [key.into()]);
+        self
+    }
+
+    /// Binds `action` to any of `keys`, replacing its previous keys. No keys at all unbind it.
+    pub fn bind(&mut self, action: Action, keys: impl IntoIterator<Item = KeyBinding>) {
+        self.keys.insert(action, keys.into_iter().collect());
+    }
+
+    /// The keys bound to `action`.
+    pub fn keys(&self, action: Action) -> &[KeyBinding] {
+        self.keys.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether any key of `action` is held down, along with its modifier.
+    pub fn pressed(&self, input: &Input<KeyCode>, action: Action) -> bool {
+        self.keys(action)
+            .iter()
+            .any(|key| input.pressed(key.key) && self.modifiers_match(key, input))
+    }
+
+    /// Whether any key of `action` went down this frame while its modifier was held.
+    pub fn just_pressed(&self, input: &Input<KeyCode>, action: Action) -> bool {
+        self.keys(action)
+            .iter()
+            .any(|key| input.just_pressed(key.key) && self.modifiers_match(key, input))
+    }
+
+    /// Whether the modifier of `binding` is held, and none that another binding of the same key needs instead.
+    fn modifiers_match(&self, binding: &KeyBinding, input: &Input<KeyCode>) -> bool {
+        Modifier::ALL.into_iter().all(|modifier| {
+            let held = input.any_pressed(modifier.keys());
+            if binding.modifier == Some(modifier) {
+                held
+            } else {
+                !held || !self.is_bound(binding.key, Some(modifier))
+            }
+        })
+    }
+
+    /// Whether any action is bound to `key` with `modifier`.
+    fn is_bound(&self, key: KeyCode, modifier: Option<Modifier>) -> bool {
+        self.keys
+            .values()
+            .flatten()
+            .any(|binding| binding.key == key && binding.modifier == modifier)
+    }
+
+    /// The keys of `action` for the settings panel, like "Equals / NumpadAdd" or "Ctrl+S".
+    pub fn describe(&self, action: Action) -> String {
+        let keys = self.keys(action);
+        if keys.is_empty() {
+            return "unbound".to_string();
+        }
+        keys.iter()
+            .map(KeyBinding::to_string)
+            .collect::<Vec<_>>()
+            .join(" / ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebinding_replaces_the_default_keys() {
+        let bindings = KeyBindings::default().with(Action::ToggleWireframe, KeyCode::Tab);
+        let mut input = Input::<KeyCode>::default();
+        input.press(KeyCode::F);
+        assert!(!bindings.just_pressed(&input, Action::ToggleWireframe));
+        input.press(KeyCode::Tab);
+        assert!(bindings.just_pressed(&input, Action::ToggleWireframe));
+
+        // Either of several keys triggers an action, and every action starts out bound.
+        input.press(KeyCode::NumpadAdd);
+        assert!(bindings.just_pressed(&input, Action::OrbitFaster));
+        assert_eq!(bindings.describe(Action::OrbitFaster), "Equals / NumpadAdd");
+        assert!(Action::ALL
+            .iter()
+            .all(|&action| !KeyBindings::default().keys(action).is_empty()));
+    }
+
+    #[test]
+    fn modifiers_tell_bindings_of_the_same_key_apart() {
+        let bindings = KeyBindings::default();
+        let mut input = Input::<KeyCode>::default();
+        input.press(KeyCode::LControl);
+        input.press(KeyCode::S);
+        assert!(bindings.just_pressed(&input, Action::Save));
+        assert!(!bindings.pressed(&input, Action::FlyBack));
+        assert_eq!(bindings.describe(Action::Save), "Ctrl+S");
+
+        input.release_all();
+        input.clear();
+        input.press(KeyCode::S);
+        assert!(!bindings.just_pressed(&input, Action::Save));
+        assert!(bindings.pressed(&input, Action::FlyBack));
+
+        // W has no binding with shift, so holding shift doesn't stop flying forward, but Y with shift is another action.
+        input.press(KeyCode::LShift);
+        input.press(KeyCode::W);
+        input.press(KeyCode::Y);
+        assert!(bindings.pressed(&input, Action::FlyForward));
+        assert!(bindings.just_pressed(&input, Action::Implode));
+        assert!(!bindings.just_pressed(&input, Action::Explode));
+
+        // No two actions share a default binding.
+        let mut all: Vec<KeyBinding> = Action::ALL
+            .iter()
+            .flat_map(|&action| bindings.keys(action).to_vec())
+            .collect();
+        let count = all.len();
+        all.sort_by_key(|key| format!("{key}"));
+        all.dedup();
+        assert_eq!(all.len(), count);
+    }
+}
@@ -0,0 +1,91 @@
+//! The voxel meshing behind the demo binary, usable without it.
+//!
+//! The core of it needs no window or renderer: fill a [`VoxelVolume`] with [`Voxel`] ids, describe them in a
+//! [`VoxelRegistry`] and hand both to [`mesh_chunk`], which returns plain [`MeshData`] that
+//! [`MeshData::into_mesh`] turns into a Bevy [`Mesh`](bevy::prelude::Mesh). [`VoxelMeshPlugin`] puts everything together
+//! into the demo world: a [`ChunkGrid`] that is generated, meshed in the background, lit, edited and drawn with
+//! [`VoxelMaterial`].
+
+/// Ambient occlusion levels and how they turn into vertex colors.
+pub mod ao;
+/// The layout of the texture atlas and its sampler.
+pub mod atlas;
+/// Boxes around the chunks and the axes at the origin, for debugging.
+pub mod bounds;
+/// Sphere and box brushes for editing many voxels at once.
+pub mod brush;
+/// The orbit and fly cameras and the top-down view.
+pub mod camera;
+/// Chunks and the grid of them that forms the world.
+pub mod chunk;
+/// Boxes for physics colliders.
+pub mod collision;
+/// Cutting the world open at a height to look inside.
+pub mod cross_section;
+/// The crosshair in the middle of the screen.
+pub mod crosshair;
+/// Hiding chunks outside the view.
+pub mod culling;
+/// Sharing one mesh between chunks that look the same.
+pub mod dedup;
+/// Mesh statistics and the overlay that shows them.
+pub mod diagnostics;
+/// Placing and removing voxels with the mouse.
+pub mod editing;
+/// Exporting the world's mesh for other tools.
+pub mod export;
+/// The sky color and distance fog.
+pub mod fog;
+/// Meshing worlds that are nothing but a height per column.
+pub mod heightmap;
+/// Which keys trigger what.
+pub mod input;
+/// Block light that spreads from glowing voxels.
+pub mod light;
+/// The material the chunk meshes are drawn with.
+pub mod material;
+/// Turning voxels into quads and quads into meshes.
+pub mod meshing;
+/// Flat and smooth vertex normals.
+pub mod normals;
+/// Meshing every chunk of a grid at once on all cores.
+pub mod parallel;
+/// The Bevy plugin that runs the demo world.
+pub mod plugin;
+/// Finding the voxel along a ray.
+pub mod raycast;
+/// Run-length encoded chunk storage.
+pub mod rle;
+/// Saving the world to a file and loading it back.
+pub mod save;
+/// The scene picked on the command line.
+pub mod scene;
+/// Loading MCEdit schematics.
+pub mod schematic;
+/// Saving screenshots.
+pub mod screenshot;
+/// The egui window with the settings worth tweaking at runtime.
+pub mod settings;
+/// Keeping translucent faces drawn back to front.
+pub mod sorting;
+/// The day and night cycle.
+pub mod sun;
+/// The voxel storage of one chunk.
+pub mod volume;
+/// Loading MagicaVoxel models.
+pub mod vox;
+/// Voxel ids and the registry that describes them.
+pub mod voxel;
+/// Turning triangle meshes into voxels.
+pub mod voxelize;
+/// Scrolling textures for flowing voxels.
+pub mod water;
+/// Filling the world with random voxels or terrain.
+pub mod worldgen;
+
+pub use chunk::{ChunkGrid, ChunkShape, CHUNK_SIZE};
+pub use material::VoxelMaterial;
+pub use meshing::{mesh_chunk, ChunkMeshData, MeshData, MeshingMode, RenderMode};
+pub use plugin::VoxelMeshPlugin;
+pub use volume::VoxelVolume;
+pub use voxel::{FaceTextures, Voxel, VoxelFace, VoxelRegistry, VoxelType};
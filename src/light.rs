@@ -0,0 +1,138 @@
+use std::collections::VecDeque;
+
+use block_mesh::ndshape::ConstShape;
+use block_mesh::{OrientedBlockFace, UnorientedQuad, VoxelVisibility};
+
+use crate::volume::VoxelVolume;
+use crate::voxel::{Voxel, VoxelRegistry};
+
+/// The light level right at a light source. Every step away from it takes one level off.
+pub const MAX_LIGHT: u8 = 15;
+
+/// The linear color block light adds at full brightness, a warm torch light.
+pub const LIGHT_COLOR: [f32; 3] = [1.0, 0.75, 0.45];
+
+/// The block light `voxel` gives off: [`MAX_LIGHT`] for a [`crate::voxel::VoxelType::emissive_strength`] of 1 or
+/// more, and proportionally less for dimmer voxels.
+pub fn emitted_light(registry: &VoxelRegistry, voxel: Voxel) -> u8 {
+    registry.get(voxel).map_or(0, |voxel_type| {
+        (voxel_type.emissive_strength.clamp(0.0, 1.0) * MAX_LIGHT as f32).round() as u8
+    })
+}
+
+/// How bright a light level looks, from 0 for darkness to 1 at [`MAX_LIGHT`]. Each level is a fixed fraction of the
+/// one above, which gives the usual torch falloff.
+pub fn light_factor(level: u8) -> f32 {
+    const FALLOFF: f32 = 0.8;
+    if level == 0 {
+        0.0
+    } else {
+        FALLOFF.powi((MAX_LIGHT - level.min(MAX_LIGHT)) as i32)
+    }
+}
+
+/// Flood fills the block light of every emitting voxel through the empty and translucent voxels around it, with a
+/// breadth first search. The levels are laid out like the voxels of the volume. Opaque voxels stay dark unless they
+/// give off light themselves.
+///
+/// Only the volume itself is lit, so the light of a chunk's neighbours only gets in through the voxels in its padding.
+pub fn propagate_light<S: ConstShape<3, Coord = u32>>(
+    volume: &VoxelVolume<S>,
+    registry: &VoxelRegistry,
+) -> Vec<u8> {
+    let mut light = vec![0; S::USIZE];
+    let mut queue = VecDeque::new();
+    for position in volume.iter_filled() {
+        let level = emitted_light(registry, volume[position]);
+        if level > 0 {
+            light[S::linearize(position) as usize] = level;
+            queue.push_back(position);
+        }
+    }
+
+    let dims = volume.dims();
+    while let Some(position) = queue.pop_front() {
+        let level = light[S::linearize(position) as usize];
+        if level <= 1 {
+            continue;
+        }
+        for axis in 0..3 {
+            for step in [-1, 1] {
+                let coord = position[axis] as i32 + step;
+                if coord < 0 || coord >= dims[axis] as i32 {
+                    continue;
+                }
+                let mut neighbour = position;
+                neighbour[axis] = coord as u32;
+                if registry.visibility(volume[neighbour]) == VoxelVisibility::Opaque {
+                    continue;
+                }
+                let index = S::linearize(neighbour) as usize;
+                // A brighter source may have got here first, in which case there's nothing to add.
+                if light[index] < level - 1 {
+                    light[index] = level - 1;
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+    }
+    light
+}
+
+/// The light level at the four corners of a quad, in the same order as its vertices. Each corner takes the light of
+/// the voxel in front of the face at that corner of the quad, like [`crate::ao::quad_corner_ao`], so merged greedy
+/// quads blend between the light at their ends.
+pub fn quad_corner_light<S: ConstShape<3, Coord = u32>>(
+    volume: &VoxelVolume<S>,
+    light: &[u8],
+    face: &OrientedBlockFace,
+    quad: &UnorientedQuad,
+) -> [u8; 4] {
+    let [n, u, v] = face.permutation().axes().map(|axis| axis.index());
+    let mut front = quad.minimum.map(|c| c as i32);
+    front[n] += face.n_sign();
+    [(false, false), (true, false), (false, true), (true, true)].map(|(far_u, far_v)| {
+        let mut corner = front;
+        if far_u {
+            corner[u] += quad.width as i32 - 1;
+        }
+        if far_v {
+            corner[v] += quad.height as i32 - 1;
+        }
+        let [x, y, z] = corner.map(|c| c as u32);
+        // Quads on the padding would look past the edge of the volume, which is dark.
+        if volume.contains(x, y, z) {
+            light[S::linearize([x, y, z]) as usize]
+        } else {
+            0
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::chunk::ChunkShape;
+
+    #[test]
+    fn light_falls_off_around_walls() {
+        let registry = VoxelRegistry::default();
+        let mut volume = VoxelVolume::default();
+        volume.set(5, 5, 5, Voxel::GLOWSTONE_VOXEL);
+        // A wall right next to the light, which has to be walked around.
+        volume.set(7, 5, 5, Voxel::STONE_VOXEL);
+        let light = propagate_light(&volume, &registry);
+        let at = |position: [u32; 3]| light[ChunkShape::linearize(position) as usize];
+
+        assert_eq!(at([5, 5, 5]), MAX_LIGHT);
+        assert_eq!(at([6, 5, 5]), MAX_LIGHT - 1);
+        assert_eq!(at([5, 8, 5]), MAX_LIGHT - 3);
+        assert_eq!(at([7, 5, 5]), 0);
+        // Three steps in a straight line, but five around the wall.
+        assert_eq!(at([8, 5, 5]), MAX_LIGHT - 5);
+        // Far enough away that the light has run out.
+        assert_eq!(at([5 + MAX_LIGHT as u32, 5, 5]), 0);
+        assert!(light_factor(MAX_LIGHT - 1) < light_factor(MAX_LIGHT));
+    }
+}
@@ -0,0 +1,356 @@
+use bevy::pbr::{MaterialPipeline, MaterialPipelineKey, StandardMaterialFlags};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::render::mesh::{MeshVertexAttribute, MeshVertexBufferLayout};
+use bevy::render::render_asset::RenderAssets;
+use bevy::render::render_resource::{
+    AsBindGroup, AsBindGroupShaderType, RenderPipelineDescriptor, ShaderRef,
+    SpecializedMeshPipelineError, VertexFormat,
+};
+use bevy::render::render_resource::{
+    Extent3d, TextureDimension, TextureFormat, TextureViewDescriptor, TextureViewDimension,
+};
+
+use crate::fog::FogSettings;
+
+/// The light a vertex gives off by itself, in linear RGB. Chunk meshes always have it, and it's 0 for voxels that don't
+/// glow.
+pub const ATTRIBUTE_EMISSIVE: MeshVertexAttribute =
+    MeshVertexAttribute::new("Voxel_Emissive", 713_502_946, VertexFormat::Float32x3);
+
+/// How much of [`VoxelMaterial::uv_offset`] a vertex gets, 1 for flowing voxels like water and 0 for everything else.
+pub const ATTRIBUTE_FLOW: MeshVertexAttribute =
+    MeshVertexAttribute::new("Voxel_Flow", 713_502_947, VertexFormat::Float32);
+
+/// The ambient occlusion of a vertex as a color, see [`crate::ao::AoSettings::color`]. It's kept apart from the voxel
+/// colors so [`VoxelMaterial::ao_shading`] decides how it's applied, and it's left out when AO is off.
+pub const ATTRIBUTE_AO: MeshVertexAttribute =
+    MeshVertexAttribute::new("Voxel_Ao", 713_502_948, VertexFormat::Float32x4);
+
+/// Where the vertex and fragment shader of [`VoxelMaterial`] are loaded from.
+pub const VOXEL_SHADER_PATH: &str = "shaders/voxel.wgsl";
+
+/// A texture array with a single white texel, bound as [`VoxelMaterial::texture_array`] by materials that sample the
+/// atlas. Bevy's own fallback image is plain 2D, which can't stand in for an array. [`crate::plugin::VoxelMeshPlugin`]
+/// adds the image, see [`fallback_texture_array`].
+pub const FALLBACK_TEXTURE_ARRAY: HandleUntyped =
+    HandleUntyped::weak_from_u64(Image::TYPE_UUID, 7_103_562_190_254_116_311);
+
+/// The image behind [`FALLBACK_TEXTURE_ARRAY`].
+pub fn fallback_texture_array() -> Image {
+    let mut image = Image::new_fill(
+        Extent3d::default(),
+        TextureDimension::D2,
+        &[255; 4],
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    image.texture_view_descriptor = Some(TextureViewDescriptor {
+        dimension: Some(TextureViewDimension::D2Array),
+        ..default()
+    });
+    image
+}
+
+/// How the fragment shader applies [`ATTRIBUTE_AO`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AoShading {
+    /// Multiplies the atlas sample, so corners are darker under every light, the sun included.
+    #[default]
+    Albedo,
+    /// Only occludes the ambient light, like the occlusion texture of a [`StandardMaterial`]. Corners in the sun
+    /// stay bright, and the tint of the AO colors is left out.
+    Ambient,
+}
+
+/// How see-through translucent voxels are, as a factor on the alpha of their texture and color: 1 leaves them as the
+/// atlas has them and 0 hides them. It goes into the `base_color` of the translucent chunk material, and the settings panel
+/// has a slider for it.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct Translucency(pub f32);
+
+impl Default for Translucency {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+/// The alpha below which the opaque chunk material discards a texel, for cutouts like leaves. Bevy keeps texels whose
+/// alpha is at least the cutoff, so a cutoff of 1 also discards every texel linear filtering, mipmaps or an edited AO
+/// stop left slightly see-through, and cutouts get thinner with distance until they vanish. The settings panel has a
+/// slider for it.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct AlphaCutoff(pub f32);
+
+impl Default for AlphaCutoff {
+    fn default() -> Self {
+        Self(0.5)
+    }
+}
+
+/// Thin lines along the edges of every voxel face, drawn by the chunk material into the base color so they're lit like
+/// the texture. The lines follow the UVs, which count voxels across a quad, so merged greedy quads get a line between
+/// each of their voxels too, without splitting the quad. The settings panel has the controls.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct GridLines {
+    pub enabled: bool,
+    /// The alpha is how much the line covers the texture.
+    pub color: Color,
+    /// How far each line reaches into the face from its edge, in voxels, so neighbouring faces together draw a line
+    /// twice as thick.
+    pub thickness: f32,
+}
+
+impl Default for GridLines {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            color: Color::rgba(0.0, 0.0, 0.0, 0.8),
+            thickness: 0.03,
+        }
+    }
+}
+
+impl GridLines {
+    /// How much of the line color a point `voxel_uv` voxels into a quad gets, from 0 to 1. This is what `voxel.wgsl`
+    /// does, without the smoothing across a pixel.
+    pub fn coverage(&self, voxel_uv: Vec2) -> f32 {
+        if !self.enabled {
+            return 0.0;
+        }
+        let fract = voxel_uv.fract();
+        let edge = fract.min(Vec2::ONE - fract).min_element();
+        if edge < self.thickness {
+            self.color.a()
+        } else {
+            0.0
+        }
+    }
+}
+
+/// The material of the chunk meshes. It's lit like a [`StandardMaterial`], but its fragment shader wraps the UVs
+/// made by [`crate::atlas::AtlasConfig::repeat_tile`] into their tile, so merged greedy quads show the tile once per
+/// voxel instead of stretching it.
+#[derive(AsBindGroup, TypeUuid, Debug, Clone)]
+#[uuid = "ad8b97a6-c1dc-4398-a70c-8446b12968c9"]
+#[bind_group_data(VoxelMaterialKey)]
+#[uniform(0, VoxelMaterialUniform)]
+pub struct VoxelMaterial {
+    pub base_color: Color,
+    /// The texture atlas. Without one the faces only show the vertex colors.
+    #[texture(1)]
+    #[sampler(2)]
+    pub base_color_texture: Option<Handle<Image>>,
+    /// A tangent space normal map laid out like the atlas, which gets the same tile wrapping. Like the normal maps of a
+    /// [`StandardMaterial`] it has to be loaded as linear rather than sRGB.
+    #[texture(3)]
+    #[sampler(4)]
+    pub normal_map_texture: Option<Handle<Image>>,
+    /// The tiles of the atlas as layers, see [`crate::atlas::atlas_to_texture_array`]. The shader picks the layer from
+    /// the tile the UVs point at and samples it instead of the atlas, unless this is left at
+    /// [`FALLBACK_TEXTURE_ARRAY`].
+    #[texture(5, dimension = "2d_array")]
+    #[sampler(6)]
+    pub texture_array: Handle<Image>,
+    /// How many tiles fit along each side of the atlas, see [`crate::atlas::AtlasConfig::tiles`].
+    pub tiles: Vec2,
+    /// How far the texture of flowing voxels has scrolled, in voxels. Only the fractional part matters, since the
+    /// texture repeats every voxel.
+    pub uv_offset: Vec2,
+    pub perceptual_roughness: f32,
+    pub ao_shading: AoShading,
+    /// Fades the faces into the fog color with distance. Without it, or while it's disabled, nothing is fogged.
+    pub fog: Option<FogSettings>,
+    /// Lines along the voxel edges. Without them, or while they're disabled, there are none.
+    pub grid_lines: Option<GridLines>,
+    pub alpha_mode: AlphaMode,
+}
+
+impl Default for VoxelMaterial {
+    fn default() -> Self {
+        Self {
+            base_color: Color::WHITE,
+            base_color_texture: None,
+            normal_map_texture: None,
+            texture_array: FALLBACK_TEXTURE_ARRAY.typed(),
+            tiles: Vec2::ONE,
+            uv_offset: Vec2::ZERO,
+            perceptual_roughness: 1.0,
+            ao_shading: AoShading::default(),
+            fog: None,
+            grid_lines: None,
+            alpha_mode: AlphaMode::Opaque,
+        }
+    }
+}
+
+pub use uniform::VoxelMaterialUniform;
+
+mod uniform {
+    // The `ShaderType` derive checks every field with a function that is never called, which rustc reports.
+    #![allow(dead_code)]
+
+    use bevy::prelude::*;
+    use bevy::render::render_resource::ShaderType;
+
+    /// The uniform `voxel.wgsl` sees. The flags are [`bevy::pbr::StandardMaterialFlags`], so the PBR functions can be
+    /// reused.
+    #[derive(Clone, Default, ShaderType)]
+    pub struct VoxelMaterialUniform {
+        pub base_color: Vec4,
+        pub tiles: Vec2,
+        pub uv_offset: Vec2,
+        pub perceptual_roughness: f32,
+        pub alpha_cutoff: f32,
+        pub flags: u32,
+        /// The [`super::AoShading`], counted from 0 in declaration order.
+        pub ao_shading: u32,
+        /// Linear RGB.
+        pub fog_color: Vec4,
+        /// Both 0 when there's no fog, which the shader checks for.
+        pub fog_start: f32,
+        pub fog_end: f32,
+        /// 0 when there are no grid lines.
+        pub grid_line_thickness: f32,
+        /// Linear RGB, with the coverage in alpha.
+        pub grid_line_color: Vec4,
+    }
+}
+
+impl AsBindGroupShaderType<VoxelMaterialUniform> for VoxelMaterial {
+    fn as_bind_group_shader_type(&self, _images: &RenderAssets<Image>) -> VoxelMaterialUniform {
+        let mut flags = StandardMaterialFlags::NONE;
+        if self.base_color_texture.is_some() {
+            flags |= StandardMaterialFlags::BASE_COLOR_TEXTURE;
+        }
+        let mut alpha_cutoff = 0.5;
+        match self.alpha_mode {
+            AlphaMode::Opaque => flags |= StandardMaterialFlags::ALPHA_MODE_OPAQUE,
+            AlphaMode::Mask(cutoff) => {
+                alpha_cutoff = cutoff;
+                flags |= StandardMaterialFlags::ALPHA_MODE_MASK;
+            }
+            AlphaMode::Blend => flags |= StandardMaterialFlags::ALPHA_MODE_BLEND,
+        }
+        let (fog_color, fog_start, fog_end) = match &self.fog {
+            Some(fog) if fog.enabled => (fog.color.as_linear_rgba_f32().into(), fog.start, fog.end),
+            _ => (Vec4::ZERO, 0.0, 0.0),
+        };
+        let (grid_line_color, grid_line_thickness) = match &self.grid_lines {
+            Some(lines) if lines.enabled => {
+                (lines.color.as_linear_rgba_f32().into(), lines.thickness)
+            }
+            _ => (Vec4::ZERO, 0.0),
+        };
+        VoxelMaterialUniform {
+            base_color: self.base_color.as_linear_rgba_f32().into(),
+            tiles: self.tiles,
+            uv_offset: self.uv_offset,
+            perceptual_roughness: self.perceptual_roughness,
+            alpha_cutoff,
+            flags: flags.bits(),
+            ao_shading: self.ao_shading as u32,
+            fog_color,
+            fog_start,
+            fog_end,
+            grid_line_thickness,
+            grid_line_color,
+        }
+    }
+}
+
+/// The parts of a [`VoxelMaterial`] that need a different pipeline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VoxelMaterialKey {
+    normal_map: bool,
+    texture_array: bool,
+}
+
+impl From<&VoxelMaterial> for VoxelMaterialKey {
+    fn from(material: &VoxelMaterial) -> Self {
+        Self {
+            normal_map: material.normal_map_texture.is_some(),
+            texture_array: material.texture_array.id() != FALLBACK_TEXTURE_ARRAY.id,
+        }
+    }
+}
+
+impl Material for VoxelMaterial {
+    fn vertex_shader() -> ShaderRef {
+        VOXEL_SHADER_PATH.into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        VOXEL_SHADER_PATH.into()
+    }
+
+    /// Chunk meshes have a fixed set of attributes, see [`crate::meshing::MeshData::into_mesh`]. Only the colors and
+    /// the AO can be missing, which the shader handles through `VERTEX_COLORS` and `VOXEL_AO`.
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayout,
+        key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        let mut attributes = vec![
+            Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+            Mesh::ATTRIBUTE_NORMAL.at_shader_location(1),
+            Mesh::ATTRIBUTE_UV_0.at_shader_location(2),
+            Mesh::ATTRIBUTE_TANGENT.at_shader_location(3),
+            ATTRIBUTE_EMISSIVE.at_shader_location(5),
+            ATTRIBUTE_FLOW.at_shader_location(6),
+        ];
+        if layout.contains(Mesh::ATTRIBUTE_COLOR) {
+            attributes.push(Mesh::ATTRIBUTE_COLOR.at_shader_location(4));
+        }
+        let ao = layout.contains(ATTRIBUTE_AO);
+        if ao {
+            attributes.push(ATTRIBUTE_AO.at_shader_location(7));
+            descriptor.vertex.shader_defs.push("VOXEL_AO".to_string());
+        }
+        let vertex_layout = layout.get_layout(&attributes)?;
+        descriptor.vertex.buffers = vec![vertex_layout];
+        if let Some(fragment) = &mut descriptor.fragment {
+            if ao {
+                fragment.shader_defs.push("VOXEL_AO".to_string());
+            }
+            if key.bind_group_data.normal_map {
+                fragment.shader_defs.push("VOXEL_NORMAL_MAP".to_string());
+            }
+            if key.bind_group_data.texture_array {
+                fragment.shader_defs.push("VOXEL_TEXTURE_ARRAY".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_lines_run_along_every_voxel_edge() {
+        let lines = GridLines {
+            enabled: true,
+            thickness: 0.1,
+            ..default()
+        };
+        let alpha = lines.color.a();
+        // Inside a merged quad, the edges between its voxels get lines as well as the quad's own edges.
+        assert_eq!(lines.coverage(Vec2::new(0.5, 0.5)), 0.0);
+        assert_eq!(lines.coverage(Vec2::new(2.5, 1.5)), 0.0);
+        assert_eq!(lines.coverage(Vec2::new(0.05, 0.5)), alpha);
+        assert_eq!(lines.coverage(Vec2::new(2.5, 1.97)), alpha);
+        assert_eq!(lines.coverage(Vec2::new(3.02, 0.5)), alpha);
+
+        let off = GridLines {
+            enabled: false,
+            ..lines
+        };
+        assert_eq!(off.coverage(Vec2::new(0.05, 0.5)), 0.0);
+    }
+}
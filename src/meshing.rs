@@ -0,0 +1,1384 @@
+use bevy::prelude::*;
+use bevy::render::mesh::Indices;
+use bevy::render::render_resource::PrimitiveTopology;
+use block_mesh::ndshape::ConstShape;
+use block_mesh::{
+    greedy_quads_with_merge_strategy, visible_block_faces, Axis, AxisPermutation,
+    GreedyQuadsBuffer, OrientedBlockFace, QuadCoordinateConfig, UnitQuadBuffer, UnorientedQuad,
+    VoxelMerger, VoxelVisibility, RIGHT_HANDED_Y_UP_CONFIG,
+};
+
+use crate::ao::{ao_convert, ao_quad_indices, corner_ao, AoQuality, AoSettings, MAX_AO};
+use crate::atlas::AtlasConfig;
+use crate::input::{Action, KeyBindings};
+use crate::light::{light_factor, propagate_light, quad_corner_light, LIGHT_COLOR};
+use crate::material::{ATTRIBUTE_AO, ATTRIBUTE_EMISSIVE, ATTRIBUTE_FLOW};
+use crate::normals::{smooth_normals, NormalMode};
+use crate::volume::VoxelVolume;
+use crate::voxel::{RegisteredVoxel, VoxelFace, VoxelRegistry, VoxelShape};
+
+/// Which block-mesh algorithm is used to turn the voxels into quads. Press G to switch between them.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MeshingMode {
+    /// One quad for every visible voxel face.
+    #[default]
+    Simple,
+    /// Adjacent faces with the same merge value are joined into larger quads.
+    Greedy,
+}
+
+pub fn toggle_meshing_mode(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut meshing_mode: ResMut<MeshingMode>,
+) {
+    if bindings.just_pressed(&keys, Action::ToggleMeshingMode) {
+        *meshing_mode = match *meshing_mode {
+            MeshingMode::Simple => MeshingMode::Greedy,
+            MeshingMode::Greedy => MeshingMode::Simple,
+        };
+        info!("meshing mode: {:?}", *meshing_mode);
+    }
+}
+
+/// How chunk faces are colored. Press V to switch between them.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RenderMode {
+    /// Faces show their tile of the texture atlas, repeated once per voxel on merged greedy quads.
+    #[default]
+    Textured,
+    /// Faces are filled with [`crate::voxel::VoxelType::color`], without any texture.
+    SolidColor,
+    /// A debug view of how well greedy meshing merges: every quad gets the [`merge_size_color`] of its area, without
+    /// texture, AO or light.
+    MergeSize,
+}
+
+pub fn toggle_render_mode(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut render_mode: ResMut<RenderMode>,
+) {
+    if bindings.just_pressed(&keys, Action::ToggleRenderMode) {
+        *render_mode = match *render_mode {
+            RenderMode::Textured => RenderMode::SolidColor,
+            RenderMode::SolidColor => RenderMode::MergeSize,
+            RenderMode::MergeSize => RenderMode::Textured,
+        };
+        info!("render mode: {:?}", *render_mode);
+    }
+}
+
+/// How far every quad is pushed out along its normal, in voxels, to pull apart faces that overlap or lie in the same
+/// plane. 0 leaves the mesh as it is. Y pushes the quads further out, shift+Y brings them back.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+pub struct ExplodeFactor(pub f32);
+
+pub fn explode_controls_system(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut factor: ResMut<ExplodeFactor>,
+) {
+    let step = if bindings.just_pressed(&keys, Action::Explode) {
+        0.1
+    } else if bindings.just_pressed(&keys, Action::Implode) {
+        -0.1
+    } else {
+        return;
+    };
+    factor.0 = (factor.0 + step).max(0.0);
+    info!("explode factor: {:.1}", factor.0);
+}
+
+/// The color [`RenderMode::MergeSize`] gives a quad covering `area` voxels: red for single voxels, through yellow and
+/// green to blue for quads of 64 voxels or more. The hue goes with the logarithm of the area, so every doubling is the
+/// same step.
+pub fn merge_size_color(area: u32) -> [f32; 4] {
+    const LARGEST: f32 = 64.0;
+    let t = ((area.max(1) as f32).log2() / LARGEST.log2()).min(1.0);
+    Color::hsl(240.0 * t, 0.9, 0.5).as_linear_rgba_f32()
+}
+
+/// The coordinate convention the chunk meshes are built for. It's read when the meshes are built, so the same voxels
+/// can be meshed for another engine or file format; the demo itself only looks right with the default.
+///
+/// Every convention winds front faces counterclockwise as seen from outside, in its own handedness.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CoordinateSystem {
+    /// Bevy's convention, and the one block-mesh is set up for.
+    #[default]
+    RightHandedYUp,
+    /// Y up with the Z axis mirrored, like Unity or Direct3D.
+    LeftHandedYUp,
+    /// Z up, like Blender or most CAD tools.
+    RightHandedZUp,
+}
+
+/// block-mesh only ships [`RIGHT_HANDED_Y_UP_CONFIG`]. This is the same for Z up: the side faces have Z as their V
+/// axis, and the Y faces need their U flipped so textures aren't mirrored.
+const RIGHT_HANDED_Z_UP_CONFIG: QuadCoordinateConfig = QuadCoordinateConfig {
+    faces: [
+        OrientedBlockFace::new(-1, AxisPermutation::Xyz),
+        OrientedBlockFace::new(-1, AxisPermutation::Yxz),
+        OrientedBlockFace::new(-1, AxisPermutation::Zxy),
+        OrientedBlockFace::new(1, AxisPermutation::Xyz),
+        OrientedBlockFace::new(1, AxisPermutation::Yxz),
+        OrientedBlockFace::new(1, AxisPermutation::Zxy),
+    ],
+    u_flip_face: Axis::Y,
+};
+
+impl CoordinateSystem {
+    /// The faces and texture flip block-mesh meshes with. A left-handed space uses the right-handed faces, its
+    /// mirroring is undone by [`CoordinateSystem::tex_coords`] and [`CoordinateSystem::is_left_handed`].
+    pub fn quad_config(self) -> QuadCoordinateConfig {
+        match self {
+            Self::RightHandedYUp | Self::LeftHandedYUp => RIGHT_HANDED_Y_UP_CONFIG,
+            Self::RightHandedZUp => RIGHT_HANDED_Z_UP_CONFIG,
+        }
+    }
+
+    /// Left-handed spaces see every face in a mirror, so their triangles get the opposite winding.
+    pub fn is_left_handed(self) -> bool {
+        self == Self::LeftHandedYUp
+    }
+
+    /// Which texture axes of `face` are flipped against block-mesh's plain UVs, where U and V follow the face's
+    /// U and V axes. Those never read right in Y up: the -X, +Y and +Z faces come out mirrored and the -Y, -Z and +X
+    /// faces upside down. So V always points down like image rows, and U is flipped on the faces the config's
+    /// `u_flip_face` picks. Mirroring Z mirrors every texture as well, which left-handed spaces undo by flipping U
+    /// once more.
+    pub fn uv_flip(self, face: &OrientedBlockFace) -> UvFlip {
+        // The same rule as `OrientedBlockFace::tex_coords`.
+        let u_flip_face = self.quad_config().u_flip_face;
+        let normal_axis = face.permutation().axes()[0];
+        let u = if face.n_sign() < 0 {
+            u_flip_face != normal_axis
+        } else {
+            u_flip_face == normal_axis
+        };
+        UvFlip {
+            u: u != self.is_left_handed(),
+            v: true,
+        }
+    }
+
+    /// The UVs of a quad's corners, flipped by [`CoordinateSystem::uv_flip`] so the texture is the right way round
+    /// when looked at from outside and side faces have its top at the top.
+    pub fn tex_coords(self, face: &OrientedBlockFace, quad: &UnorientedQuad) -> [[f32; 2]; 4] {
+        let flip = self.uv_flip(face);
+        let (width, height) = (quad.width as f32, quad.height as f32);
+        [[0.0, 0.0], [width, 0.0], [0.0, height], [width, height]].map(|[u, v]| {
+            [
+                if flip.u { width - u } else { u },
+                if flip.v { height - v } else { v },
+            ]
+        })
+    }
+}
+
+/// Flips of a face's texture axes, see [`CoordinateSystem::uv_flip`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UvFlip {
+    pub u: bool,
+    pub v: bool,
+}
+
+/// Runs the chosen meshing algorithm. Both paths produce the same kind of quads, unit quads just have a size of 1x1.
+pub fn generate_quads<S: ConstShape<3, Coord = u32>>(
+    volume: &VoxelVolume<S>,
+    registry: &VoxelRegistry,
+    mode: MeshingMode,
+    faces: &[OrientedBlockFace; 6],
+) -> [Vec<UnorientedQuad>; 6] {
+    generate_quads_merging(volume, registry, mode, faces, true)
+}
+
+/// [`generate_quads`], with greedy meshing only merging faces with the same AO if `respect_ao` is set, see
+/// [`AoSettings::merge_respecting_ao`].
+pub fn generate_quads_merging<S: ConstShape<3, Coord = u32>>(
+    volume: &VoxelVolume<S>,
+    registry: &VoxelRegistry,
+    mode: MeshingMode,
+    faces: &[OrientedBlockFace; 6],
+    respect_ao: bool,
+) -> [Vec<UnorientedQuad>; 6] {
+    let mut buffers = QuadBuffers::default();
+    generate_quads_into(volume, registry, mode, faces, respect_ao, &mut buffers);
+    buffers.groups
+}
+
+/// The buffers block-mesh reads its voxels from and writes its quads into, kept between remeshes so a chunk doesn't
+/// allocate them all over again every time it's edited. Every use clears them first, so no quads of the previous chunk
+/// are left behind.
+pub struct QuadBuffers {
+    voxels: Vec<RegisteredVoxel>,
+    unit: UnitQuadBuffer,
+    greedy: GreedyQuadsBuffer,
+    groups: [Vec<UnorientedQuad>; 6],
+}
+
+impl Default for QuadBuffers {
+    fn default() -> Self {
+        Self {
+            voxels: Vec::new(),
+            unit: UnitQuadBuffer::new(),
+            // Sized on first use, and only resized when a volume of another size comes along.
+            greedy: GreedyQuadsBuffer::new(0),
+            groups: Default::default(),
+        }
+    }
+}
+
+/// [`generate_quads_merging`], writing the quads into `buffers` instead of new vectors.
+pub fn generate_quads_into<'a, S: ConstShape<3, Coord = u32>>(
+    volume: &VoxelVolume<S>,
+    registry: &VoxelRegistry,
+    mode: MeshingMode,
+    faces: &[OrientedBlockFace; 6],
+    respect_ao: bool,
+    buffers: &'a mut QuadBuffers,
+) -> &'a [Vec<UnorientedQuad>; 6] {
+    RegisteredVoxel::wrap_into(volume.as_slice(), registry, &mut buffers.voxels);
+    let voxels = buffers.voxels.as_slice();
+    let (min, max) = volume.mesh_bounds();
+    for group in &mut buffers.groups {
+        group.clear();
+    }
+    match mode {
+        MeshingMode::Simple => {
+            // Simple meshing works on web and makes texture atlases easier. It adds to whatever is in the buffer, so
+            // that has to be emptied first.
+            buffers.unit.reset();
+            visible_block_faces(voxels, volume.shape(), min, max, faces, &mut buffers.unit);
+            for (group, unit) in buffers.groups.iter_mut().zip(&buffers.unit.groups) {
+                group.extend(unit.iter().map(|&quad| UnorientedQuad::from(quad)));
+            }
+        }
+        MeshingMode::Greedy => {
+            // Greedy meshing resets its buffer itself.
+            let buffer = &mut buffers.greedy;
+            if respect_ao {
+                greedy_quads_with_merge_strategy::<_, _, VoxelMerger<_, true, true>>(
+                    voxels,
+                    volume.shape(),
+                    min,
+                    max,
+                    faces,
+                    buffer,
+                );
+            } else {
+                greedy_quads_with_merge_strategy::<_, _, VoxelMerger<_, false, false>>(
+                    voxels,
+                    volume.shape(),
+                    min,
+                    max,
+                    faces,
+                    buffer,
+                );
+            }
+            for (group, quads) in buffers.groups.iter_mut().zip(&buffer.quads.groups) {
+                group.extend_from_slice(quads);
+            }
+        }
+    }
+    for (group, partial) in buffers
+        .groups
+        .iter_mut()
+        .zip(partial_voxel_quads(volume, registry, faces))
+    {
+        group.extend(partial);
+    }
+    &buffers.groups
+}
+
+/// The faces of the voxels that aren't [`VoxelShape::Full`], which block-mesh leaves out. They're unit quads over the
+/// whole cell, and [`mesh_chunk`] squashes them down to the voxel's shape.
+///
+/// A slab's top sits halfway up its cell, so it's always drawn. Its bottom is hidden by an opaque cube below it, and
+/// each side by an opaque cube or an opaque slab next to it. The other way round a slab hides nothing, so cubes keep
+/// their faces towards slabs even where the slab covers them.
+pub fn partial_voxel_quads<S: ConstShape<3, Coord = u32>>(
+    volume: &VoxelVolume<S>,
+    registry: &VoxelRegistry,
+    faces: &[OrientedBlockFace; 6],
+) -> [Vec<UnorientedQuad>; 6] {
+    let mut groups: [Vec<UnorientedQuad>; 6] = Default::default();
+    let [dx, dy, dz] = volume.dims();
+    let covers = |neighbour: IVec3, slab_side: bool| {
+        let Some(voxel) = volume.get(neighbour.x as u32, neighbour.y as u32, neighbour.z as u32)
+        else {
+            return false;
+        };
+        registry.visibility(voxel) == VoxelVisibility::Opaque
+            && match registry.shape(voxel) {
+                VoxelShape::Full => true,
+                VoxelShape::BottomSlab => slab_side,
+            }
+    };
+    for [x, y, z] in volume.iter_filled() {
+        // Like block-mesh, the padding only counts as neighbours.
+        if ![x, y, z]
+            .iter()
+            .zip([dx, dy, dz])
+            .all(|(&p, d)| (1..d - 1).contains(&p))
+        {
+            continue;
+        }
+        if registry.shape(volume[[x, y, z]]) != VoxelShape::BottomSlab {
+            continue;
+        }
+        let position = UVec3::new(x, y, z).as_ivec3();
+        for (group, face) in groups.iter_mut().zip(faces) {
+            let normal = IVec3::from(face.signed_normal().to_array());
+            let visible = match normal.y {
+                1 => true,
+                -1 => !covers(position + normal, false),
+                _ => !covers(position + normal, true),
+            };
+            if visible {
+                group.push(UnorientedQuad {
+                    minimum: [x, y, z],
+                    width: 1,
+                    height: 1,
+                    // Only block-mesh's own AO goes here, `mesh_chunk` works out its own.
+                    ao: [MAX_AO; 4],
+                });
+            }
+        }
+    }
+    groups
+}
+
+/// Squashes the unit quad of a [`VoxelShape::BottomSlab`] face into the lower half of its cell, whose floor is at
+/// `bottom`. The UVs of the sides are cut in half along with them, so the texture isn't squashed.
+fn squash_to_slab(positions: &mut [[f32; 3]; 4], uvs: &mut [[f32; 2]; 4], bottom: f32) {
+    let original = *positions;
+    for (i, [x, y, z]) in original.into_iter().enumerate() {
+        if y <= bottom {
+            continue;
+        }
+        // The corner below this one, which only side faces have.
+        let below = original
+            .iter()
+            .position(|&[bx, by, bz]| by <= bottom && bx == x && bz == z);
+        if let Some(below) = below {
+            uvs[i] = Vec2::from(uvs[below])
+                .lerp(Vec2::from(uvs[i]), 0.5)
+                .to_array();
+        }
+        positions[i][1] = bottom + (y - bottom) * 0.5;
+    }
+}
+
+/// The geometry of one chunk mesh, independent of Bevy's render types. Every quad has its own 4 vertices and 6
+/// indices.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeshData {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub uvs: Vec<[f32; 2]>,
+    /// Points along increasing U, with the sign of the bitangent in W, see [`quad_tangent`].
+    pub tangents: Vec<[f32; 4]>,
+    /// The AO level of each vertex, from 0 for the most occluded to [`AoSettings::max_level`].
+    pub ao: Vec<u8>,
+    /// The color of each vertex, without AO.
+    pub colors: Vec<[f32; 4]>,
+    /// The [`crate::voxel::VoxelType::emissive_strength`] of each vertex.
+    pub emissive: Vec<f32>,
+    /// The block light level in front of each vertex, see [`crate::light::propagate_light`].
+    pub light: Vec<u8>,
+    /// 1 for vertices of [`crate::voxel::VoxelType::flowing`] voxels and 0 for the rest.
+    pub flow: Vec<f32>,
+    pub indices: Vec<u32>,
+}
+
+impl MeshData {
+    /// Moves every quad `distance` along its face normal, see [`ExplodeFactor`].
+    pub fn explode(&mut self, distance: f32) {
+        if distance == 0.0 {
+            return;
+        }
+        for (position, normal) in self.positions.iter_mut().zip(&self.normals) {
+            *position = (Vec3::from(*position) + Vec3::from(*normal) * distance).to_array();
+        }
+    }
+
+    /// Builds the render mesh, with the AO levels turned into colors by `ao_settings` and the normals picked by
+    /// `normal_mode`. The attributes are the ones [`crate::material::VoxelMaterial`] expects.
+    pub fn into_mesh(self, ao_settings: &AoSettings, normal_mode: NormalMode) -> Mesh {
+        let normals = match normal_mode {
+            NormalMode::Flat => self.normals,
+            NormalMode::Smooth => smooth_normals(&self.positions, &self.normals),
+        };
+        let mut render_mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        render_mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.positions);
+        render_mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+        render_mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs);
+        render_mesh.insert_attribute(Mesh::ATTRIBUTE_TANGENT, self.tangents);
+        let ao = ao_convert(self.ao, ao_settings);
+        // The glow of the voxel itself leaves AO out, so glowing blocks stay bright in corners too. Block light from
+        // around the face lights the texture the same way, but does get darker in the corners.
+        let emissive: Vec<_> = self
+            .colors
+            .iter()
+            .zip(self.emissive)
+            .zip(self.light.iter().zip(&ao))
+            .map(|((color, strength), (&light, ao))| {
+                let light = light_factor(light);
+                [0, 1, 2].map(|i| color[i] * strength + LIGHT_COLOR[i] * light * ao[i])
+            })
+            .collect();
+        render_mesh.insert_attribute(ATTRIBUTE_EMISSIVE, emissive);
+        render_mesh.insert_attribute(ATTRIBUTE_FLOW, self.flow);
+        // White vertex colors and no AO wouldn't change anything, so textured meshes skip those attributes.
+        if ao_settings.quality != AoQuality::Off {
+            render_mesh.insert_attribute(ATTRIBUTE_AO, ao);
+        }
+        if self.colors.iter().any(|&color| color != [1.0; 4]) {
+            render_mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, self.colors);
+        }
+        render_mesh.set_indices(Some(Indices::U32(self.indices)));
+        render_mesh
+    }
+}
+
+/// A chunk's faces, split by the visibility of the voxel each face belongs to. Every face ends up in exactly one of
+/// the two, so nothing is drawn twice: the plugin gives the opaque mesh a masked material and the translucent one a
+/// blended material, each on its own entity under the chunk.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ChunkMeshData {
+    pub opaque: MeshData,
+    pub translucent: MeshData,
+}
+
+/// Meshes the voxels of one chunk, with the padding of the volume holding the neighbouring voxels. This
+/// only needs the registry and the atlas layout, so it can be used without any of the rendering setup.
+///
+/// Every voxel is `voxel_scale` units across in the mesh, see [`VoxelScale`](crate::chunk::VoxelScale).
+#[allow(clippy::too_many_arguments)]
+pub fn mesh_chunk<S: ConstShape<3, Coord = u32>>(
+    voxels: &VoxelVolume<S>,
+    registry: &VoxelRegistry,
+    atlas: &AtlasConfig,
+    ao_settings: &AoSettings,
+    mode: MeshingMode,
+    render_mode: RenderMode,
+    coordinates: CoordinateSystem,
+    voxel_scale: f32,
+) -> ChunkMeshData {
+    mesh_chunk_with_buffers(
+        voxels,
+        registry,
+        atlas,
+        ao_settings,
+        mode,
+        render_mode,
+        coordinates,
+        voxel_scale,
+        &mut QuadBuffers::default(),
+    )
+}
+
+/// Splits every quad of `quads` larger than a voxel whose voxels don't all have the same four corner AO, and merges
+/// its evenly shaded voxels again like block-mesh does. block-mesh only merges faces whose AO is uniform in its four
+/// levels, but [`corner_ao`] looks further for ramps with more levels than that, so faces it merged can still be
+/// shaded differently.
+fn split_unevenly_shaded<S: ConstShape<3, Coord = u32>>(
+    quads: &mut Vec<UnorientedQuad>,
+    voxels: &VoxelVolume<S>,
+    registry: &VoxelRegistry,
+    face: &OrientedBlockFace,
+    settings: &AoSettings,
+) {
+    let [_, u, v] = face.permutation().axes().map(|axis| axis.index());
+    for i in 0..quads.len() {
+        let quad = quads[i];
+        if quad.width * quad.height == 1 {
+            continue;
+        }
+        let unit = |du: u32, dv: u32, width: u32, height: u32| {
+            let mut minimum = quad.minimum;
+            minimum[u] += du;
+            minimum[v] += dv;
+            UnorientedQuad {
+                minimum,
+                width,
+                height,
+                ao: quad.ao,
+            }
+        };
+        // The AO of every voxel under the quad, row by row, or `None` where its corners differ.
+        let (width, height) = (quad.width, quad.height);
+        let shading: Vec<Option<[u8; 4]>> = (0..height)
+            .flat_map(|dv| (0..width).map(move |du| (du, dv)))
+            .map(|(du, dv)| {
+                let ao = corner_ao(voxels, registry, face, &unit(du, dv, 1, 1), settings);
+                ao.iter().all(|&level| level == ao[0]).then_some(ao)
+            })
+            .collect();
+        if shading.iter().all(|ao| ao.is_some() && *ao == shading[0]) {
+            continue;
+        }
+
+        let mut pieces = Vec::new();
+        let mut visited = vec![false; shading.len()];
+        let at = |du: u32, dv: u32| (du + dv * width) as usize;
+        for dv in 0..height {
+            for du in 0..width {
+                if visited[at(du, dv)] {
+                    continue;
+                }
+                let ao = shading[at(du, dv)];
+                let mut piece_width = 1;
+                let mut piece_height = 1;
+                if ao.is_some() {
+                    let fits = |du: u32, dv: u32| !visited[at(du, dv)] && shading[at(du, dv)] == ao;
+                    while du + piece_width < width && fits(du + piece_width, dv) {
+                        piece_width += 1;
+                    }
+                    while dv + piece_height < height
+                        && (du..du + piece_width).all(|du| fits(du, dv + piece_height))
+                    {
+                        piece_height += 1;
+                    }
+                }
+                for dv in dv..dv + piece_height {
+                    for du in du..du + piece_width {
+                        visited[at(du, dv)] = true;
+                    }
+                }
+                pieces.push(unit(du, dv, piece_width, piece_height));
+            }
+        }
+        quads[i] = pieces[0];
+        quads.extend_from_slice(&pieces[1..]);
+    }
+}
+
+/// [`mesh_chunk`], reusing `buffers` for the quads.
+#[allow(clippy::too_many_arguments)]
+pub fn mesh_chunk_with_buffers<S: ConstShape<3, Coord = u32>>(
+    voxels: &VoxelVolume<S>,
+    registry: &VoxelRegistry,
+    atlas: &AtlasConfig,
+    ao_settings: &AoSettings,
+    mode: MeshingMode,
+    render_mode: RenderMode,
+    coordinates: CoordinateSystem,
+    voxel_scale: f32,
+    buffers: &mut QuadBuffers,
+) -> ChunkMeshData {
+    // Chunks of nothing but air, like the ones above the terrain, don't need the mesher at all.
+    if voxels.iter_filled().next().is_none() {
+        return ChunkMeshData::default();
+    }
+    let faces = coordinates.quad_config().faces;
+
+    generate_quads_into(
+        voxels,
+        registry,
+        mode,
+        &faces,
+        ao_settings.merge_respecting_ao,
+        buffers,
+    );
+    if ao_settings.merge_respecting_ao && ao_settings.max_level() > MAX_AO {
+        for (group, face) in buffers.groups.iter_mut().zip(&faces) {
+            split_unevenly_shaded(group, voxels, registry, face, ao_settings);
+        }
+    }
+    let groups = &buffers.groups;
+    let light = propagate_light(voxels, registry);
+    let mut data = ChunkMeshData::default();
+    for ((group, face), voxel_face) in groups.iter().zip(faces).zip(VoxelFace::ALL) {
+        for &quad in group {
+            let voxel = voxels[quad.minimum];
+            let mesh = match registry.visibility(voxel) {
+                VoxelVisibility::Translucent => &mut data.translucent,
+                _ => &mut data.opaque,
+            };
+
+            let merge_size = render_mode == RenderMode::MergeSize;
+            let ao = if merge_size {
+                [ao_settings.max_level(); 4]
+            } else {
+                corner_ao(voxels, registry, &face, &quad, ao_settings)
+            };
+            mesh.indices.extend_from_slice(&ao_quad_indices(
+                &face,
+                mesh.positions.len() as u32,
+                ao,
+                ao_settings.flip_quads,
+                coordinates.is_left_handed(),
+            ));
+            let voxel_type = registry.get_or_missing(voxel);
+            let mut positions = face.quad_mesh_positions(&quad, voxel_scale);
+            let mut face_tex = coordinates.tex_coords(&face, &quad);
+            if voxel_type.shape == VoxelShape::BottomSlab {
+                let bottom = quad.minimum[1] as f32 * voxel_scale;
+                squash_to_slab(&mut positions, &mut face_tex, bottom);
+            }
+            let normals = face.quad_mesh_normals();
+            mesh.positions.extend_from_slice(&positions);
+            mesh.normals.extend_from_slice(&normals);
+            mesh.ao.extend_from_slice(&ao);
+            mesh.light.extend_from_slice(&if merge_size {
+                [0; 4]
+            } else {
+                quad_corner_light(voxels, &light, &face, &quad)
+            });
+            let mut color = [1.0; 4];
+            let mut emissive = 0.0;
+            let mut flow = 0.0;
+            match render_mode {
+                RenderMode::MergeSize => {
+                    let [r, g, b, _] = merge_size_color(quad.width * quad.height);
+                    // Translucent voxels keep their alpha so they stay see-through.
+                    color = [r, g, b, voxel_type.color[3]];
+                }
+                RenderMode::SolidColor => {
+                    color = voxel_type.color;
+                    emissive = voxel_type.emissive_strength;
+                }
+                RenderMode::Textured => {
+                    emissive = voxel_type.emissive_strength;
+                    flow = if voxel_type.flowing { 1.0 } else { 0.0 };
+                    let [column, row] = voxel_type.textures.for_face(voxel_face);
+                    face_tex = atlas.repeat_tile(column, row, face_tex);
+                }
+            }
+            mesh.uvs.extend_from_slice(&face_tex);
+            mesh.tangents
+                .extend_from_slice(&[quad_tangent(&positions, &face_tex, normals[0]); 4]);
+            mesh.colors.extend_from_slice(&[color; 4]);
+            mesh.emissive.extend_from_slice(&[emissive; 4]);
+            mesh.flow.extend_from_slice(&[flow; 4]);
+        }
+    }
+    data
+}
+
+/// The tangent of a flat quad, worked out from how its UVs change across it so it follows whatever U direction and
+/// flips the face config gives the texture. W is the handedness, so the bitangent `w * cross(normal, tangent)` points
+/// along increasing V like Bevy's normal mapping expects.
+pub fn quad_tangent(positions: &[[f32; 3]; 4], uvs: &[[f32; 2]; 4], normal: [f32; 3]) -> [f32; 4] {
+    let [p0, p1, p2] = [0, 1, 2].map(|i| Vec3::from(positions[i]));
+    let [uv0, uv1, uv2] = [0, 1, 2].map(|i| Vec2::from(uvs[i]));
+    let (edge1, edge2) = (p1 - p0, p2 - p0);
+    let (duv1, duv2) = (uv1 - uv0, uv2 - uv0);
+    // The UV mapping of the quad is linear, so two edges are enough to invert it.
+    let r = 1.0 / (duv1.x * duv2.y - duv2.x * duv1.y);
+    let tangent = ((edge1 * duv2.y - edge2 * duv1.y) * r).normalize();
+    let bitangent = (edge2 * duv1.x - edge1 * duv2.x) * r;
+    let handedness = if Vec3::from(normal).cross(tangent).dot(bitangent) < 0.0 {
+        -1.0
+    } else {
+        1.0
+    };
+    tangent.extend(handedness).to_array()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use block_mesh::ndshape::ConstShape3u32;
+
+    use crate::voxel::{Voxel, MISSING_VOXEL_TYPE};
+
+    fn chunk_with(solid: &[[u32; 3]]) -> VoxelVolume {
+        let mut voxels = VoxelVolume::default();
+        for &position in solid {
+            voxels[position] = Voxel::STONE_VOXEL;
+        }
+        voxels
+    }
+
+    fn mesh(voxels: &VoxelVolume) -> MeshData {
+        let data = mesh_chunk(
+            voxels,
+            &VoxelRegistry::default(),
+            &AtlasConfig::default(),
+            &AoSettings::default(),
+            MeshingMode::Simple,
+            RenderMode::Textured,
+            CoordinateSystem::default(),
+            1.0,
+        );
+        assert_eq!(data.translucent, MeshData::default());
+        data.opaque
+    }
+
+    #[test]
+    fn single_voxel_has_six_faces() {
+        let data = mesh(&chunk_with(&[[5, 5, 5]]));
+        assert_eq!(data.positions.len(), 24);
+        assert_eq!(data.normals.len(), 24);
+        assert_eq!(data.uvs.len(), 24);
+        assert_eq!(data.tangents.len(), 24);
+        assert_eq!(data.ao.len(), 24);
+        assert_eq!(data.colors.len(), 24);
+        assert_eq!(data.emissive.len(), 24);
+        assert_eq!(data.light.len(), 24);
+        assert_eq!(data.flow.len(), 24);
+        assert_eq!(data.indices.len(), 36);
+    }
+
+    #[test]
+    fn empty_chunk_has_no_faces() {
+        assert_eq!(mesh(&chunk_with(&[])), MeshData::default());
+    }
+
+    #[test]
+    fn adjacent_voxels_hide_the_shared_face() {
+        let data = mesh(&chunk_with(&[[5, 5, 5], [6, 5, 5]]));
+        assert_eq!(data.positions.len(), 10 * 4);
+        assert_eq!(data.indices.len(), 10 * 6);
+        // Neither voxel keeps its face on the plane x = 6 between them.
+        let shared = data
+            .positions
+            .chunks_exact(4)
+            .filter(|quad| quad.iter().all(|position| position[0] == 6.0))
+            .count();
+        assert_eq!(shared, 0);
+    }
+
+    #[test]
+    fn greedy_quads_repeat_the_tile_per_voxel() {
+        // A 3x1x1 bar of stone, which greedy meshing turns into 6 quads.
+        let voxels = chunk_with(&[[5, 5, 5], [6, 5, 5], [7, 5, 5]]);
+        let data = mesh_chunk(
+            &voxels,
+            &VoxelRegistry::default(),
+            &AtlasConfig::default(),
+            &AoSettings::default(),
+            MeshingMode::Greedy,
+            RenderMode::Textured,
+            CoordinateSystem::default(),
+            1.0,
+        )
+        .opaque;
+        assert_eq!(data.uvs.len(), 6 * 4);
+
+        // Stone uses tile 4, 4 everywhere. The UVs of every quad span its size in voxels on top of the tile's origin.
+        let origin = 3.0 * crate::atlas::TILE_UV_STRIDE;
+        for (positions, uvs) in data.positions.chunks_exact(4).zip(data.uvs.chunks_exact(4)) {
+            let extent = |axis: usize| {
+                let values = positions.iter().map(|position| position[axis]);
+                values.clone().fold(f32::MIN, f32::max) - values.fold(f32::MAX, f32::min)
+            };
+            let mut quad_size = [extent(0), extent(1), extent(2)];
+            quad_size.sort_by(f32::total_cmp);
+            let mut uv_size = [0, 1].map(|axis| {
+                let values = uvs.iter().map(|uv| uv[axis]);
+                assert!(values.clone().all(|value| value >= origin));
+                values.clone().fold(f32::MIN, f32::max) - values.fold(f32::MAX, f32::min)
+            });
+            uv_size.sort_by(f32::total_cmp);
+            assert_eq!(uv_size, [quad_size[1], quad_size[2]]);
+        }
+    }
+
+    #[test]
+    fn non_cubic_volumes_mesh_every_axis() {
+        // A 4x10x2 box of stone filling the inside of a padded 6x12x4 volume.
+        let mut voxels = VoxelVolume::filled(ConstShape3u32::<6, 12, 4> {}, Voxel::EMPTY_VOXEL);
+        for x in 1..5 {
+            for y in 1..11 {
+                for z in 1..3 {
+                    voxels.set(x, y, z, Voxel::STONE_VOXEL);
+                }
+            }
+        }
+        let mesh = |mode| {
+            mesh_chunk(
+                &voxels,
+                &VoxelRegistry::default(),
+                &AtlasConfig::default(),
+                &AoSettings::default(),
+                mode,
+                RenderMode::Textured,
+                CoordinateSystem::default(),
+                1.0,
+            )
+            .opaque
+        };
+
+        let simple = mesh(MeshingMode::Simple);
+        assert_eq!(simple.positions.len(), 2 * (4 * 10 + 10 * 2 + 4 * 2) * 4);
+        let greedy = mesh(MeshingMode::Greedy);
+        assert_eq!(greedy.positions.len(), 6 * 4);
+        for data in [simple, greedy] {
+            for position in data.positions {
+                assert!((1.0..=5.0).contains(&position[0]));
+                assert!((1.0..=11.0).contains(&position[1]));
+                assert!((1.0..=3.0).contains(&position[2]));
+            }
+        }
+    }
+
+    #[test]
+    fn tangents_follow_the_uvs() {
+        let data = mesh(&chunk_with(&[[5, 5, 5]]));
+        for quad in 0..6 {
+            let vertices = quad * 4..quad * 4 + 4;
+            let normal = Vec3::from(data.normals[quad * 4]);
+            let [x, y, z, w] = data.tangents[quad * 4];
+            let tangent = Vec3::new(x, y, z);
+            let bitangent = w * normal.cross(tangent);
+            assert!(tangent.dot(normal).abs() < 1e-6);
+            // The UVs count voxels, so walking one voxel along the tangent or bitangent moves U or V by one.
+            for i in vertices.clone() {
+                for j in vertices.clone() {
+                    let step = Vec3::from(data.positions[j]) - Vec3::from(data.positions[i]);
+                    let uv_step = Vec2::from(data.uvs[j]) - Vec2::from(data.uvs[i]);
+                    assert!((step.dot(tangent) - uv_step.x).abs() < 1e-5);
+                    assert!((step.dot(bitangent) - uv_step.y).abs() < 1e-5);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn every_coordinate_system_winds_front_faces_outwards() {
+        // An L of three voxels, so some quads get AO and are split along the other diagonal.
+        let voxels = chunk_with(&[[5, 5, 5], [6, 5, 5], [5, 6, 5]]);
+        for (coordinates, up, winding) in [
+            (CoordinateSystem::RightHandedYUp, Vec3::Y, 1.0),
+            (CoordinateSystem::LeftHandedYUp, Vec3::Y, -1.0),
+            (CoordinateSystem::RightHandedZUp, Vec3::Z, 1.0),
+        ] {
+            let data = mesh_chunk(
+                &voxels,
+                &VoxelRegistry::default(),
+                &AtlasConfig::default(),
+                &AoSettings::default(),
+                MeshingMode::Simple,
+                RenderMode::SolidColor,
+                coordinates,
+                1.0,
+            )
+            .opaque;
+            let position = |i: u32| Vec3::from(data.positions[i as usize]);
+            for triangle in data.indices.chunks_exact(3) {
+                let normal = Vec3::from(data.normals[triangle[0] as usize]);
+                let (a, b, c) = (
+                    position(triangle[0]),
+                    position(triangle[1]),
+                    position(triangle[2]),
+                );
+                // A left-handed space turns the right-handed cross product around.
+                let facing = (b - a).cross(c - a).dot(normal) * winding;
+                assert!(facing > 0.0, "{coordinates:?} triangle {triangle:?}");
+            }
+
+            // Seen from outside, U runs to the right and V runs down on every side face.
+            for quad in 0..data.positions.len() / 4 {
+                let normal = Vec3::from(data.normals[quad * 4]);
+                if normal.dot(up) != 0.0 {
+                    continue;
+                }
+                let right = up.cross(normal) * winding;
+                for i in quad * 4 + 1..quad * 4 + 4 {
+                    let step = Vec3::from(data.positions[i]) - Vec3::from(data.positions[quad * 4]);
+                    let uv_step = Vec2::from(data.uvs[i]) - Vec2::from(data.uvs[quad * 4]);
+                    assert_eq!(uv_step.x, step.dot(right), "{coordinates:?} U of {normal}");
+                    assert_eq!(uv_step.y, -step.dot(up), "{coordinates:?} V of {normal}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn greedy_quads_keep_top_and_side_tiles_apart() {
+        // A 3x1x3 slab of grass, which has different tiles on top, bottom and sides.
+        let mut solid = Vec::new();
+        for x in 5..8 {
+            for z in 5..8 {
+                solid.push([x, 5, z]);
+            }
+        }
+        let mut voxels = chunk_with(&[]);
+        for &position in &solid {
+            voxels[position] = Voxel::GRASS_VOXEL;
+        }
+        let registry = VoxelRegistry::default();
+        let data = mesh_chunk(
+            &voxels,
+            &registry,
+            &AtlasConfig::default(),
+            &AoSettings::default(),
+            MeshingMode::Greedy,
+            RenderMode::Textured,
+            CoordinateSystem::default(),
+            1.0,
+        )
+        .opaque;
+        assert_eq!(data.positions.len(), 6 * 4);
+
+        let textures = registry.get(Voxel::GRASS_VOXEL).unwrap().textures;
+        for quad in 0..6 {
+            let normal = Vec3::from(data.normals[quad * 4]).as_ivec3();
+            let face = VoxelFace::from_normal(normal).unwrap();
+            let [column, row] = textures.for_face(face);
+            let origin = Vec2::new(column - 1.0, row - 1.0) * crate::atlas::TILE_UV_STRIDE;
+            for uv in &data.uvs[quad * 4..quad * 4 + 4] {
+                let tile = (Vec2::from(*uv) / crate::atlas::TILE_UV_STRIDE).floor();
+                assert_eq!(tile * crate::atlas::TILE_UV_STRIDE, origin, "{face:?} face");
+            }
+        }
+    }
+
+    #[test]
+    fn positions_scale_with_the_voxel_scale() {
+        let voxels = chunk_with(&[[5, 5, 5], [6, 5, 5]]);
+        let mesh_at = |voxel_scale| {
+            mesh_chunk(
+                &voxels,
+                &VoxelRegistry::default(),
+                &AtlasConfig::default(),
+                &AoSettings::default(),
+                MeshingMode::Greedy,
+                RenderMode::Textured,
+                CoordinateSystem::default(),
+                voxel_scale,
+            )
+            .opaque
+        };
+        let unit = mesh_at(1.0);
+        for voxel_scale in [0.25, 2.5] {
+            let scaled = mesh_at(voxel_scale);
+            let expected: Vec<_> = unit
+                .positions
+                .iter()
+                .map(|&position| (Vec3::from(position) * voxel_scale).to_array())
+                .collect();
+            assert_eq!(scaled.positions, expected);
+            // Only the size of the mesh changes, the faces and their texturing stay the same.
+            assert_eq!(scaled.indices, unit.indices);
+            assert_eq!(scaled.uvs, unit.uvs);
+        }
+    }
+
+    #[test]
+    fn merge_size_colors_quads_by_area() {
+        let data = mesh_chunk(
+            &chunk_with(&[[5, 5, 5], [6, 5, 5], [7, 5, 5], [8, 5, 5]]),
+            &VoxelRegistry::default(),
+            &AtlasConfig::default(),
+            &AoSettings::default(),
+            MeshingMode::Greedy,
+            RenderMode::MergeSize,
+            CoordinateSystem::default(),
+            1.0,
+        )
+        .opaque;
+        // The two ends of the bar are single voxels, the four sides cover all four.
+        let single = merge_size_color(1);
+        let bar = merge_size_color(4);
+        assert_ne!(single, bar);
+        for quad in data.colors.chunks_exact(4) {
+            assert!(quad[0] == single || quad[0] == bar, "{:?}", quad[0]);
+        }
+        assert_eq!(data.colors.iter().filter(|&&c| c == bar).count(), 4 * 4);
+        assert!(data.ao.iter().all(|&ao| ao == MAX_AO));
+        assert_eq!(merge_size_color(64), merge_size_color(1000));
+    }
+
+    #[test]
+    fn explode_moves_quads_along_their_normals() {
+        let unchanged = mesh(&chunk_with(&[[5, 5, 5]]));
+        let mut data = unchanged.clone();
+        data.explode(0.0);
+        assert_eq!(data, unchanged);
+
+        data.explode(0.5);
+        for ((exploded, position), normal) in data
+            .positions
+            .iter()
+            .zip(&unchanged.positions)
+            .zip(&unchanged.normals)
+        {
+            let moved = Vec3::from(*exploded) - Vec3::from(*position);
+            assert_eq!(moved, Vec3::from(*normal) * 0.5);
+        }
+    }
+
+    #[test]
+    fn faces_go_into_one_mesh_each() {
+        let mut voxels = chunk_with(&[[5, 5, 5]]);
+        voxels[[6, 5, 5]] = Voxel::A1_VOXEL;
+        let data = mesh_chunk(
+            &voxels,
+            &VoxelRegistry::default(),
+            &AtlasConfig::default(),
+            &AoSettings::default(),
+            MeshingMode::Simple,
+            RenderMode::Textured,
+            CoordinateSystem::default(),
+            1.0,
+        );
+        // The stone keeps its face towards the glass, which loses the one towards the stone.
+        assert_eq!(data.opaque.positions.len(), 6 * 4);
+        assert_eq!(data.translucent.positions.len(), 5 * 4);
+        let opaque: Vec<_> = data.opaque.positions.chunks_exact(4).collect();
+        assert!(data
+            .translucent
+            .positions
+            .chunks_exact(4)
+            .all(|quad| !opaque.contains(&quad)));
+    }
+
+    #[test]
+    fn unregistered_ids_get_the_missing_tile() {
+        let mut voxels = VoxelVolume::default();
+        voxels[[5, 5, 5]] = Voxel(200);
+        let data = mesh(&voxels);
+        assert_eq!(data.positions.len(), 6 * 4);
+
+        // Every UV lands in the tile of the fallback type instead of the corner of the atlas.
+        let [column, row] = MISSING_VOXEL_TYPE.textures.sides;
+        let origin = AtlasConfig::default().repeat_tile(column, row, [[0.0; 2]; 4])[0];
+        for &[u, v] in &data.uvs {
+            assert!((origin[0]..=origin[0] + 1.0).contains(&u), "{u}");
+            assert!((origin[1]..=origin[1] + 1.0).contains(&v), "{v}");
+        }
+    }
+
+    #[test]
+    fn ao_stays_within_its_levels_on_random_grids() {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+
+        for seed in 0..4 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut voxels = VoxelVolume::default();
+            let [dx, dy, dz] = voxels.dims();
+            for z in 0..dz {
+                for y in 0..dy {
+                    for x in 0..dx {
+                        // Half of the voxels are empty, the rest cover every registered type and a few unregistered ones.
+                        let voxel = if rng.gen_bool(0.5) {
+                            Voxel::EMPTY_VOXEL
+                        } else {
+                            Voxel(rng.gen_range(1..=16))
+                        };
+                        voxels.set(x, y, z, voxel);
+                    }
+                }
+            }
+            for quality in [
+                AoQuality::Full,
+                AoQuality::EdgesOnly,
+                AoQuality::Off,
+                AoQuality::Raymarched,
+            ] {
+                let ao_settings = AoSettings {
+                    quality,
+                    ..default()
+                };
+                for mode in [MeshingMode::Simple, MeshingMode::Greedy] {
+                    let data = mesh_chunk(
+                        &voxels,
+                        &VoxelRegistry::default(),
+                        &AtlasConfig::default(),
+                        &ao_settings,
+                        mode,
+                        RenderMode::Textured,
+                        CoordinateSystem::default(),
+                        1.0,
+                    );
+                    for mesh in [&data.opaque, &data.translucent] {
+                        assert!(!mesh.ao.is_empty());
+                        assert!(
+                            mesh.ao.iter().all(|&ao| ao <= MAX_AO),
+                            "seed {seed}, {quality:?}, {mode:?}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn slabs_fill_the_lower_half_of_their_cell() {
+        let mut voxels = chunk_with(&[[5, 4, 5], [6, 4, 5]]);
+        voxels[[5, 5, 5]] = Voxel::STONE_SLAB_VOXEL;
+        voxels[[6, 5, 5]] = Voxel::STONE_SLAB_VOXEL;
+        let data = mesh(&voxels);
+        // A slab doesn't hide anything, so the cubes keep their tops and have 10 faces between them. Each slab has its
+        // top and the three sides that aren't against the other slab.
+        assert_eq!(data.positions.len(), (10 + 2 * 4) * 4);
+
+        let slab_vertices: Vec<_> = data
+            .positions
+            .iter()
+            .zip(&data.uvs)
+            .filter(|(p, _)| p[1] > 5.0)
+            .collect();
+        assert!(!slab_vertices.is_empty());
+        assert!(slab_vertices.iter().all(|(p, _)| p[1] == 5.5));
+
+        // The side faces show the lower half of the tile instead of all of it squashed.
+        let side_v: Vec<f32> = data
+            .positions
+            .chunks_exact(4)
+            .zip(data.normals.chunks_exact(4))
+            .zip(data.uvs.chunks_exact(4))
+            .filter(|((p, n), _)| n[0][1] == 0.0 && p.iter().any(|p| p[1] == 5.5))
+            .map(|(_, uvs)| {
+                let v = uvs.iter().map(|uv| uv[1]);
+                v.clone().fold(f32::MIN, f32::max) - v.fold(f32::MAX, f32::min)
+            })
+            .collect();
+        assert_eq!(side_v.len(), 6);
+        assert!(side_v.iter().all(|&v| v == 0.5));
+    }
+
+    #[test]
+    fn textures_read_the_right_way_round_on_every_face() {
+        let mut voxels = VoxelVolume::default();
+        voxels[[5, 5, 5]] = Voxel::STONE_VOXEL;
+        for coordinates in [
+            CoordinateSystem::RightHandedYUp,
+            CoordinateSystem::LeftHandedYUp,
+            CoordinateSystem::RightHandedZUp,
+        ] {
+            let up = match coordinates {
+                CoordinateSystem::RightHandedZUp => Vec3::Z,
+                _ => Vec3::Y,
+            };
+            let data = mesh_chunk(
+                &voxels,
+                &VoxelRegistry::default(),
+                &AtlasConfig::default(),
+                &AoSettings::default(),
+                MeshingMode::Simple,
+                RenderMode::Textured,
+                coordinates,
+                1.0,
+            )
+            .opaque;
+            for (corners, (uvs, normals)) in data
+                .positions
+                .chunks_exact(4)
+                .zip(data.uvs.chunks_exact(4).zip(data.normals.chunks_exact(4)))
+            {
+                // A texture with an arrow to the right along U and one up against V, which is how images are stored.
+                // Seen from outside, the right arrow has to turn counterclockwise onto the up arrow, in the handedness
+                // of the space.
+                let [p0, p1, p2] = [0, 1, 2].map(|i| Vec3::from(corners[i]));
+                let [uv0, uv1, uv2] = [0, 1, 2].map(|i| Vec2::from(uvs[i]));
+                let (du1, du2) = (uv1 - uv0, uv2 - uv0);
+                let det = du1.x * du2.y - du2.x * du1.y;
+                let right = ((p1 - p0) * du2.y - (p2 - p0) * du1.y) / det;
+                let texture_up = -((p2 - p0) * du1.x - (p1 - p0) * du2.x) / det;
+                let normal = Vec3::from(normals[0]);
+                let handedness = if coordinates.is_left_handed() {
+                    -1.0
+                } else {
+                    1.0
+                };
+                assert_eq!(
+                    right.cross(texture_up),
+                    normal * handedness,
+                    "{coordinates:?} {normal}"
+                );
+                if normal.dot(up) == 0.0 {
+                    assert_eq!(texture_up, up, "{coordinates:?} {normal}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn greedy_merging_can_ignore_ao() {
+        // A floor with a pillar in the middle, whose shadow breaks up the floor's quads.
+        let mut floor = Vec::new();
+        for x in 3..=10 {
+            for z in 3..=10 {
+                floor.push([x, 5, z]);
+            }
+        }
+        floor.push([6, 6, 6]);
+        let voxels = chunk_with(&floor);
+        let mesh = |merge_respecting_ao| {
+            mesh_chunk(
+                &voxels,
+                &VoxelRegistry::default(),
+                &AtlasConfig::default(),
+                &AoSettings {
+                    merge_respecting_ao,
+                    ..default()
+                },
+                MeshingMode::Greedy,
+                RenderMode::Textured,
+                CoordinateSystem::default(),
+                1.0,
+            )
+            .opaque
+        };
+        // The occlusion of every quad larger than a voxel.
+        let merged_ao = |data: &MeshData| -> Vec<[u8; 4]> {
+            data.positions
+                .chunks_exact(4)
+                .zip(data.ao.chunks_exact(4))
+                .filter(|(corners, _)| {
+                    let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(corners[i]));
+                    (b - a).cross(c - a).length() > 1.0
+                })
+                .map(|(_, ao)| [ao[0], ao[1], ao[2], ao[3]])
+                .collect()
+        };
+
+        let respecting = mesh(true);
+        let ignoring = mesh(false);
+        assert!(ignoring.indices.len() < respecting.indices.len());
+        // Respecting the AO keeps the shadow of the pillar on unit quads, while ignoring it spreads the shadow of a
+        // corner over merged quads.
+        assert!(merged_ao(&respecting)
+            .iter()
+            .all(|ao| ao.iter().all(|&ao| ao == MAX_AO)));
+        assert!(merged_ao(&ignoring)
+            .iter()
+            .any(|ao| ao.iter().any(|&ao| ao < MAX_AO)));
+    }
+
+    #[test]
+    fn fine_ao_keeps_merged_quads_evenly_shaded() {
+        // The same floor and pillar as above, shaded with a ramp of 16 levels, which sees the pillar from further away
+        // than block-mesh's four.
+        let mut floor = Vec::new();
+        for x in 3..=10 {
+            for z in 3..=10 {
+                floor.push([x, 5, z]);
+            }
+        }
+        floor.push([6, 6, 6]);
+        let voxels = chunk_with(&floor);
+        let registry = VoxelRegistry::default();
+        let mut ao_settings = AoSettings::default();
+        ao_settings.stops = ao_settings.resampled(16);
+        let faces = RIGHT_HANDED_Y_UP_CONFIG.faces;
+        let top = VoxelFace::ALL
+            .iter()
+            .position(|face| face.normal() == IVec3::Y)
+            .unwrap();
+        let face = &faces[top];
+
+        // Whether every voxel under each merged quad has the same four corners.
+        let evenly_shaded = |quads: &[UnorientedQuad]| {
+            let mut units = quads.to_vec();
+            units.retain(|quad| quad.width * quad.height > 1);
+            let merged = units.len();
+            split_unevenly_shaded(&mut units, &voxels, &registry, face, &ao_settings);
+            units.len() == merged
+        };
+        let mut quads =
+            generate_quads_merging(&voxels, &registry, MeshingMode::Greedy, &faces, true);
+        assert!(!evenly_shaded(&quads[top]));
+        split_unevenly_shaded(&mut quads[top], &voxels, &registry, face, &ao_settings);
+        assert!(evenly_shaded(&quads[top]));
+        // The floor around the pillar and the pillar's top still cover everything, and the far side still merges.
+        let area: u32 = quads[top].iter().map(|quad| quad.width * quad.height).sum();
+        assert_eq!(area, 8 * 8);
+        assert!(quads[top].iter().any(|quad| quad.width * quad.height > 1));
+    }
+
+    #[test]
+    fn reused_buffers_leave_no_quads_behind() {
+        // A busy chunk first, so a sparser one after it would show any quads the buffers kept.
+        let mut busy = Vec::new();
+        for x in 1..=12 {
+            for z in 1..=12 {
+                busy.push([x, (x * z) % 5 + 1, z]);
+            }
+        }
+        let chunks = [chunk_with(&busy), chunk_with(&[[5, 5, 5]]), chunk_with(&[])];
+        let mesh = |voxels, mode, buffers: &mut QuadBuffers| {
+            mesh_chunk_with_buffers(
+                voxels,
+                &VoxelRegistry::default(),
+                &AtlasConfig::default(),
+                &AoSettings::default(),
+                mode,
+                RenderMode::Textured,
+                CoordinateSystem::default(),
+                1.0,
+                buffers,
+            )
+        };
+
+        let mut buffers = QuadBuffers::default();
+        for mode in [
+            MeshingMode::Greedy,
+            MeshingMode::Simple,
+            MeshingMode::Greedy,
+        ] {
+            for voxels in &chunks {
+                assert_eq!(
+                    mesh(voxels, mode, &mut buffers),
+                    mesh(voxels, mode, &mut QuadBuffers::default()),
+                    "{mode:?}"
+                );
+            }
+        }
+        // The voxels handed to block-mesh are only allocated once.
+        assert!(buffers.voxels.capacity() >= chunks[0].as_slice().len());
+    }
+
+    #[test]
+    fn greedy_quads_cover_the_same_surface_as_simple_ones() {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+        use std::collections::BTreeMap;
+
+        // The area of the quads facing each way, per voxel, counted in voxel faces.
+        let areas = |groups: &[Vec<UnorientedQuad>; 6], voxels: &VoxelVolume| {
+            let mut areas = BTreeMap::new();
+            for (face, group) in groups.iter().enumerate() {
+                for quad in group {
+                    *areas.entry((face, voxels[quad.minimum].0)).or_insert(0) +=
+                        quad.width * quad.height;
+                }
+            }
+            areas
+        };
+        let faces = CoordinateSystem::default().quad_config().faces;
+        for seed in 0..6 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut voxels = VoxelVolume::default();
+            let [dx, dy, dz] = voxels.dims();
+            for z in 0..dz {
+                for y in 0..dy {
+                    for x in 0..dx {
+                        // Few kinds of voxels, so greedy meshing finds something to merge.
+                        if rng.gen_bool(0.4) {
+                            voxels.set(x, y, z, Voxel(rng.gen_range(1..=4)));
+                        }
+                    }
+                }
+            }
+            let registry = VoxelRegistry::default();
+            let simple = generate_quads(&voxels, &registry, MeshingMode::Simple, &faces);
+            let count =
+                |groups: &[Vec<UnorientedQuad>; 6]| groups.iter().map(Vec::len).sum::<usize>();
+            for respect_ao in [true, false] {
+                let greedy = generate_quads_merging(
+                    &voxels,
+                    &registry,
+                    MeshingMode::Greedy,
+                    &faces,
+                    respect_ao,
+                );
+                assert_eq!(
+                    areas(&greedy, &voxels),
+                    areas(&simple, &voxels),
+                    "seed {seed}, respecting AO: {respect_ao}"
+                );
+                assert!(count(&greedy) < count(&simple));
+            }
+        }
+    }
+}
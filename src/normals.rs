@@ -0,0 +1,59 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::input::{Action, KeyBindings};
+
+/// Which normals the chunk meshes get. Press N to switch between them.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NormalMode {
+    /// Every quad uses its face normal, which gives the usual blocky look.
+    #[default]
+    Flat,
+    /// Vertices at the same position share the average of the normals of the faces that meet there.
+    Smooth,
+}
+
+pub fn toggle_normal_mode(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut normal_mode: ResMut<NormalMode>,
+) {
+    if bindings.just_pressed(&keys, Action::ToggleNormalMode) {
+        *normal_mode = match *normal_mode {
+            NormalMode::Flat => NormalMode::Smooth,
+            NormalMode::Smooth => NormalMode::Flat,
+        };
+        info!("normal mode: {:?}", *normal_mode);
+    }
+}
+
+/// Averages the flat `normals` of all vertices that share a position. Each quad has its own 4 vertices, so the
+/// vertices are matched up by position rather than by index. Only vertices within one mesh get merged, so there are
+/// still seams between chunks.
+pub fn smooth_normals(positions: &[[f32; 3]], normals: &[[f32; 3]]) -> Vec<[f32; 3]> {
+    let key = |position: &[f32; 3]| position.map(f32::to_bits);
+    let mut sums: HashMap<[u32; 3], Vec3> = HashMap::default();
+    for (position, normal) in positions.iter().zip(normals) {
+        *sums.entry(key(position)).or_default() += Vec3::from(*normal);
+    }
+    positions
+        .iter()
+        .map(|position| sums[&key(position)].normalize_or_zero().to_array())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_vertices_average_their_normals() {
+        // A vertex on the edge between a top and a side face, and one that only belongs to the top face.
+        let positions = [[0.0, 1.0, 0.0], [0.0, 1.0, 0.0], [1.0, 1.0, 1.0]];
+        let normals = [[0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+        let smoothed = smooth_normals(&positions, &normals);
+
+        let diagonal = Vec3::new(1.0, 1.0, 0.0).normalize().to_array();
+        assert_eq!(smoothed, vec![diagonal, diagonal, [0.0, 1.0, 0.0]]);
+    }
+}
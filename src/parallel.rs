@@ -0,0 +1,119 @@
+use bevy::prelude::*;
+use rayon::prelude::*;
+
+use crate::ao::AoSettings;
+use crate::atlas::AtlasConfig;
+use crate::chunk::{gather_padding, ChunkGrid};
+use crate::meshing::{
+    mesh_chunk_with_buffers, ChunkMeshData, CoordinateSystem, MeshingMode, QuadBuffers, RenderMode,
+};
+use crate::voxel::VoxelRegistry;
+
+/// Meshes every chunk of the grid, one after the other, with the chunk coordinates in the order of
+/// [`ChunkGrid::chunks`]. Each chunk is meshed from its own padded copy of the voxels, so the chunks don't depend on
+/// each other, see [`mesh_grid_parallel`]. The chunks share one set of [`QuadBuffers`].
+pub fn mesh_grid(
+    grid: &ChunkGrid,
+    registry: &VoxelRegistry,
+    atlas: &AtlasConfig,
+    ao_settings: &AoSettings,
+    mode: MeshingMode,
+    render_mode: RenderMode,
+    coordinates: CoordinateSystem,
+) -> Vec<(UVec3, ChunkMeshData)> {
+    let mut buffers = QuadBuffers::default();
+    grid.chunks()
+        .map(|(coord, _)| {
+            let voxels = gather_padding(grid, coord);
+            let data = mesh_chunk_with_buffers(
+                &voxels,
+                registry,
+                atlas,
+                ao_settings,
+                mode,
+                render_mode,
+                coordinates,
+                grid.voxel_scale(),
+                &mut buffers,
+            );
+            (coord, data)
+        })
+        .collect()
+}
+
+/// Like [`mesh_grid`], but the chunks are spread over rayon's thread pool. Meshing only reads the grid and the
+/// settings, so nothing has to be locked, and the results come back in the same order as [`mesh_grid`]'s. Turning
+/// them into Bevy meshes is left to the caller, since that needs the `Assets<Mesh>` of the main thread.
+pub fn mesh_grid_parallel(
+    grid: &ChunkGrid,
+    registry: &VoxelRegistry,
+    atlas: &AtlasConfig,
+    ao_settings: &AoSettings,
+    mode: MeshingMode,
+    render_mode: RenderMode,
+    coordinates: CoordinateSystem,
+) -> Vec<(UVec3, ChunkMeshData)> {
+    let coords: Vec<UVec3> = grid.chunks().map(|(coord, _)| coord).collect();
+    coords
+        .into_par_iter()
+        // Every thread keeps its own buffers for the chunks it meshes.
+        .map_init(QuadBuffers::default, |buffers, coord| {
+            let voxels = gather_padding(grid, coord);
+            let data = mesh_chunk_with_buffers(
+                &voxels,
+                registry,
+                atlas,
+                ao_settings,
+                mode,
+                render_mode,
+                coordinates,
+                grid.voxel_scale(),
+                buffers,
+            );
+            (coord, data)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::worldgen::{generate_world, RandomSeed, TerrainConfig, WorldGenerator};
+
+    #[test]
+    fn parallel_meshing_matches_sequential() {
+        let mut grid = ChunkGrid::new(2);
+        generate_world(
+            &mut grid,
+            WorldGenerator::Random,
+            &TerrainConfig::default(),
+            RandomSeed(3),
+        );
+        let registry = VoxelRegistry::default();
+        let atlas = AtlasConfig::default();
+        let ao_settings = AoSettings::default();
+        let sequential = mesh_grid(
+            &grid,
+            &registry,
+            &atlas,
+            &ao_settings,
+            MeshingMode::Greedy,
+            RenderMode::Textured,
+            CoordinateSystem::default(),
+        );
+        assert_eq!(sequential.len(), 8);
+        assert!(sequential
+            .iter()
+            .all(|(_, data)| !data.opaque.positions.is_empty()));
+        let parallel = mesh_grid_parallel(
+            &grid,
+            &registry,
+            &atlas,
+            &ao_settings,
+            MeshingMode::Greedy,
+            RenderMode::Textured,
+            CoordinateSystem::default(),
+        );
+        assert_eq!(parallel, sequential);
+    }
+}
@@ -0,0 +1,826 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::asset::HandleId;
+use bevy::asset::LoadState;
+use bevy::prelude::*;
+#[cfg(not(target_arch = "wasm32"))]
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+use bevy::utils::HashMap;
+use bevy_inspector_egui::bevy_egui::EguiContext;
+use bevy_inspector_egui::egui;
+#[cfg(not(target_arch = "wasm32"))]
+use futures_lite::future;
+
+use crate::ao::{ao_controls_system, AoSettings};
+use crate::atlas::{
+    apply_sampler_settings, atlas_to_texture_array, has_sampler_settings, placeholder_atlas,
+    toggle_sampler_filter, toggle_texture_layout, AtlasConfig, SamplerSettings, TextureLayout,
+};
+use crate::camera::{
+    camera_rotation_system, fly_camera_system, orbit_controls_system, toggle_camera_mode,
+    toggle_camera_projection, CameraMode, CameraProjection, CameraRotationState, OrbitSettings,
+};
+use crate::chunk::{gather_padding, ChunkGrid, ChunkMesh, Dirty, VoxelScale};
+use crate::cross_section::{cross_section_controls_system, CrossSection};
+use crate::culling::chunk_frustum_culling_system;
+use crate::dedup::MeshDedupCache;
+use crate::diagnostics::{
+    count_visible_faces, ChunkStats, FaceVisibility, GeometryCount, MeshStats,
+};
+use crate::fog::{FogSettings, SKY_COLOR};
+use crate::input::{Action, KeyBindings};
+use crate::material::{
+    fallback_texture_array, AlphaCutoff, AoShading, GridLines, Translucency, VoxelMaterial,
+    FALLBACK_TEXTURE_ARRAY, VOXEL_SHADER_PATH,
+};
+use crate::meshing::{
+    explode_controls_system, mesh_chunk_with_buffers, toggle_meshing_mode, toggle_render_mode,
+    CoordinateSystem, ExplodeFactor, MeshingMode, QuadBuffers, RenderMode,
+};
+use crate::normals::{toggle_normal_mode, NormalMode};
+use crate::sorting::{resort_translucent_system, sort_quads_back_to_front, viewer_position};
+use crate::sun::{sun_bundle, sun_system, toggle_day_cycle, DayCycle};
+use crate::vox::{orient_model_system, place_model, VoxModel};
+use crate::voxel::VoxelRegistry;
+use crate::water::WaterSettings;
+use crate::worldgen::{
+    generate_world, regenerate_world_system, RandomSeed, TerrainConfig, WorldGenerator,
+};
+
+/// Loads the atlas, builds the chunk grid and keeps its meshes up to date, and drives the camera and the lighting.
+///
+/// Every resource is only initialised if the app doesn't have it yet, so insert a [`VoxelRegistry`],
+/// [`WorldGenerator`], [`RandomSeed`], [`VoxModel`] (which can also come from a schematic) or any of the settings
+/// before adding the plugin to change them. The [`ClearColor`] becomes the sky color unless it was changed from Bevy's
+/// default.
+/// Systems that should only run once the world exists go in [`AppState::Run`].
+pub struct VoxelMeshPlugin;
+
+impl Plugin for VoxelMeshPlugin {
+    fn build(&self, app: &mut App) {
+        // The default clear color is a dark gray, which fogged chunks wouldn't blend into.
+        let clear_color = app.world.get_resource::<ClearColor>();
+        if clear_color.is_none_or(|color| color.0 == ClearColor::default().0) {
+            app.insert_resource(ClearColor(SKY_COLOR));
+        }
+        // Added by the `ImagePlugin`, which has to come first.
+        app.world
+            .resource_mut::<Assets<Image>>()
+            .set_untracked(FALLBACK_TEXTURE_ARRAY, fallback_texture_array());
+        app.add_plugin(MaterialPlugin::<VoxelMaterial>::default())
+            .init_resource::<MeshingMode>()
+            .init_resource::<NormalMode>()
+            .init_resource::<RenderMode>()
+            .init_resource::<CoordinateSystem>()
+            .init_resource::<ExplodeFactor>()
+            .init_resource::<CrossSection>()
+            .init_resource::<VoxelScale>()
+            .init_resource::<VoxelRegistry>()
+            .init_resource::<AtlasConfig>()
+            .init_resource::<SamplerSettings>()
+            .init_resource::<TextureLayout>()
+            .init_resource::<AoSettings>()
+            .init_resource::<FogSettings>()
+            .init_resource::<Translucency>()
+            .init_resource::<AlphaCutoff>()
+            .init_resource::<GridLines>()
+            .init_resource::<CameraMode>()
+            .init_resource::<CameraProjection>()
+            .init_resource::<OrbitSettings>()
+            .init_resource::<DayCycle>()
+            .init_resource::<WaterSettings>()
+            .init_resource::<WorldGenerator>()
+            .init_resource::<TerrainConfig>()
+            .init_resource::<RandomSeed>()
+            .init_resource::<ChunkMeshTasks>()
+            .init_resource::<KeyBindings>()
+            .init_resource::<QuadBufferPool>()
+            .init_resource::<MeshStats>()
+            .init_resource::<MeshDedupCache>()
+            .add_state(AppState::Loading)
+            .add_system_set(SystemSet::on_enter(AppState::Loading).with_system(load_assets))
+            .add_system_set(
+                SystemSet::on_update(AppState::Loading)
+                    .with_system(check_loaded)
+                    .with_system(loading_progress_system),
+            )
+            .add_system_set(SystemSet::on_enter(AppState::Run).with_system(setup))
+            .add_system_set(
+                SystemSet::on_update(AppState::Run)
+                    .with_system(camera_rotation_system)
+                    .with_system(orbit_controls_system)
+                    .with_system(toggle_camera_mode)
+                    .with_system(toggle_camera_projection)
+                    .with_system(fly_camera_system)
+                    .with_system(toggle_meshing_mode)
+                    .with_system(toggle_normal_mode)
+                    .with_system(toggle_render_mode)
+                    .with_system(explode_controls_system)
+                    .with_system(cross_section_controls_system)
+                    .with_system(apply_render_mode)
+                    .with_system(apply_fog)
+                    .with_system(apply_grid_lines)
+                    .with_system(apply_translucency)
+                    .with_system(apply_alpha_cutoff)
+                    .with_system(apply_voxel_scale)
+                    .with_system(toggle_sampler_filter)
+                    .with_system(update_atlas_sampler)
+                    .with_system(reload_atlas_system)
+                    .with_system(toggle_texture_layout)
+                    .with_system(apply_texture_layout)
+                    .with_system(toggle_day_cycle)
+                    .with_system(sun_system)
+                    .with_system(ao_controls_system)
+                    .with_system(toggle_ao_shading)
+                    .with_system(scroll_water_system)
+                    .with_system(regenerate_world_system)
+                    .with_system(orient_model_system)
+                    .with_system(remesh_system)
+                    .with_system(poll_mesh_tasks)
+                    .with_system(resort_translucent_system)
+                    .with_system(chunk_frustum_culling_system),
+            );
+    }
+}
+
+/// The plugin waits in `Loading` for the texture atlas and builds the world when it enters `Run`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum AppState {
+    Loading,
+    Run,
+}
+
+/// The world is a cube of this many chunks along each axis.
+const GRID_CHUNKS: u32 = 2;
+
+/// The texture atlas. It stays around after loading so the atlas can be swapped for the placeholder or hot reloaded.
+#[derive(Resource)]
+struct AtlasTexture(Handle<Image>);
+
+/// The two materials every chunk entity shares, created once in `setup`. Changing one of them changes all chunks, so
+/// systems that restyle the world edit these instead of adding materials.
+#[derive(Resource)]
+pub struct MaterialCache {
+    pub opaque: Handle<VoxelMaterial>,
+    pub translucent: Handle<VoxelMaterial>,
+}
+
+impl MaterialCache {
+    pub fn handles(&self) -> [&Handle<VoxelMaterial>; 2] {
+        [&self.opaque, &self.translucent]
+    }
+}
+
+/// Chunk meshes that are still being built on the [`AsyncComputeTaskPool`](bevy::tasks::AsyncComputeTaskPool).
+/// Queuing another task for a chunk drops the previous one, which cancels it, so an outdated mesh can never replace a
+/// newer one.
+#[derive(Resource, Default)]
+struct ChunkMeshTasks(HashMap<UVec3, ChunkMeshTask>);
+
+/// The [`QuadBuffers`] of the mesh tasks that have finished, for the next tasks to reuse instead of allocating their
+/// own. A task takes one out while it runs and puts it back when it's done, so there are never more buffers than tasks
+/// that ran at the same time. Cancelled tasks drop theirs.
+#[derive(Resource, Clone, Default)]
+struct QuadBufferPool(Arc<Mutex<Vec<QuadBuffers>>>);
+
+impl QuadBufferPool {
+    fn take(&self) -> QuadBuffers {
+        self.0.lock().unwrap().pop().unwrap_or_default()
+    }
+
+    fn give_back(&self, buffers: QuadBuffers) {
+        self.0.lock().unwrap().push(buffers);
+    }
+}
+
+struct ChunkMeshTask {
+    translucent_mesh: Handle<Mesh>,
+    task: MeshTask,
+}
+
+/// What a mesh task hands back: the opaque and translucent meshes and the face counts for the stats.
+type ChunkMeshes = (Mesh, Mesh, FaceVisibility);
+
+#[cfg(not(target_arch = "wasm32"))]
+type MeshTask = Task<ChunkMeshes>;
+
+/// The browser only gives us one thread, and its task pool can't hand results back, so on the web the meshing waits in
+/// [`poll_mesh_tasks`] to be done a few chunks a frame instead. That keeps the page responsive while a whole grid is
+/// remeshed.
+#[cfg(target_arch = "wasm32")]
+type MeshTask = Option<Box<dyn FnOnce() -> ChunkMeshes + Send + Sync>>;
+
+/// How many chunks the web build meshes each frame.
+#[cfg(target_arch = "wasm32")]
+const WEB_MESHES_PER_FRAME: u32 = 2;
+
+#[cfg(not(target_arch = "wasm32"))]
+fn spawn_mesh_task(build: impl FnOnce() -> ChunkMeshes + Send + 'static) -> MeshTask {
+    AsyncComputeTaskPool::get().spawn(async move { build() })
+}
+
+#[cfg(target_arch = "wasm32")]
+fn spawn_mesh_task(build: impl FnOnce() -> ChunkMeshes + Send + Sync + 'static) -> MeshTask {
+    Some(Box::new(build))
+}
+
+/// The meshes of `task` if they're done. `budget` is how many more chunks may still be meshed this frame on the web.
+#[cfg(not(target_arch = "wasm32"))]
+fn poll_mesh_task(task: &mut MeshTask, _budget: &mut u32) -> Option<ChunkMeshes> {
+    future::block_on(future::poll_once(task))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn poll_mesh_task(task: &mut MeshTask, budget: &mut u32) -> Option<ChunkMeshes> {
+    if *budget == 0 {
+        return None;
+    }
+    *budget -= 1;
+    task.take().map(|build| build())
+}
+
+/// The texture atlas, relative to the assets folder.
+const ATLAS_PATH: &str = "uv_checker.png";
+
+/// Everything `Loading` waits for, by the path it's loaded from.
+#[derive(Resource, Default)]
+struct PendingAssets(Vec<(&'static str, HandleUntyped)>);
+
+impl PendingAssets {
+    fn states<'a>(
+        &'a self,
+        asset_server: &'a AssetServer,
+    ) -> impl Iterator<Item = (&'static str, LoadState)> + 'a {
+        self.0
+            .iter()
+            .map(|(path, handle)| (*path, asset_server.get_load_state(handle)))
+    }
+}
+
+fn load_assets(mut commands: Commands, asset_server: Res<AssetServer>) {
+    debug!("load");
+    let handle = asset_server.load(ATLAS_PATH);
+    // The material loads its shader by itself, this only keeps track of it.
+    let shader = asset_server.load_untyped(VOXEL_SHADER_PATH);
+    commands.insert_resource(PendingAssets(vec![
+        (ATLAS_PATH, handle.clone_untyped()),
+        (VOXEL_SHADER_PATH, shader),
+    ]));
+    commands.insert_resource(AtlasTexture(handle));
+}
+
+/// Moves on to `Run` once every pending asset is loaded, or as soon as one of them fails, so the atlas can have its
+/// settings changed later. An atlas that can't be loaded is replaced by a placeholder checkerboard.
+fn check_loaded(
+    mut state: ResMut<State<AppState>>,
+    mut handle: ResMut<AtlasTexture>,
+    pending: Res<PendingAssets>,
+    asset_server: Res<AssetServer>,
+    atlas: Res<AtlasConfig>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    debug!("check loaded");
+    let mut loaded = true;
+    for (path, load_state) in pending.states(&asset_server) {
+        match load_state {
+            LoadState::Loaded => {}
+            LoadState::Failed if path == ATLAS_PATH => {
+                error!("failed to load the texture atlas, using a placeholder");
+                handle.0 = images.add(placeholder_atlas(&atlas));
+                state.set(AppState::Run).unwrap();
+                return;
+            }
+            LoadState::Failed => {
+                error!("failed to load {path}");
+                state.set(AppState::Run).unwrap();
+                return;
+            }
+            _ => loaded = false,
+        }
+    }
+    if loaded {
+        state.set(AppState::Run).unwrap();
+    }
+}
+
+/// Shows how many of the pending assets are loaded while in `Loading`, and which ones are still missing. It needs the
+/// egui plugin, and shows nothing without it.
+fn loading_progress_system(
+    egui_context: Option<ResMut<EguiContext>>,
+    pending: Res<PendingAssets>,
+    asset_server: Res<AssetServer>,
+) {
+    let Some(mut egui_context) = egui_context else {
+        return;
+    };
+    let waiting: Vec<_> = pending
+        .states(&asset_server)
+        .filter(|(_, state)| *state != LoadState::Loaded)
+        .map(|(path, _)| path)
+        .collect();
+    let total = pending.0.len();
+    let loaded = total - waiting.len();
+    egui::Window::new("Loading")
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .collapsible(false)
+        .resizable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.add(
+                egui::ProgressBar::new(loaded as f32 / total.max(1) as f32)
+                    .text(format!("{loaded} of {total} assets")),
+            );
+            for path in waiting {
+                ui.label(format!("waiting for {path}"));
+            }
+        });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn setup(
+    mut commands: Commands,
+    texture_handle: Res<AtlasTexture>,
+    world_generator: Res<WorldGenerator>,
+    terrain_config: Res<TerrainConfig>,
+    seed: Res<RandomSeed>,
+    voxel_scale: Res<VoxelScale>,
+    vox_model: Option<Res<VoxModel>>,
+    atlas: Res<AtlasConfig>,
+    alpha_cutoff: Res<AlphaCutoff>,
+    meshes: Res<Assets<Mesh>>,
+    mut materials: ResMut<Assets<VoxelMaterial>>,
+    // mut textures: ResMut<Assets<Image>>,
+) {
+    debug!("setup");
+    // let mut texture = textures.get_mut(&texture_handle.0).unwrap();
+
+    // We only fill the grid itself since we need some empty voxels around it to form a boundary for the mesh.
+    // Models too large for the usual grid get one that fits them.
+    let chunks_per_axis = vox_model.as_ref().map_or(GRID_CHUNKS, |model| {
+        GRID_CHUNKS.max(ChunkGrid::chunks_to_fit(model.size))
+    });
+    let mut grid = ChunkGrid::new(chunks_per_axis).with_voxel_scale(*voxel_scale);
+    match vox_model {
+        Some(model) => place_model(&mut grid, &model.voxels, model.size),
+        None => {
+            info!("random fill seed: {}", seed.0);
+            generate_world(&mut grid, *world_generator, &terrain_config, *seed);
+        }
+    }
+
+    let opaque_material = materials.add(VoxelMaterial {
+        base_color_texture: Some(texture_handle.0.clone()),
+        tiles: atlas.tiles(),
+        alpha_mode: AlphaMode::Mask(alpha_cutoff.0),
+        ..default()
+    });
+    let translucent_material = materials.add(VoxelMaterial {
+        base_color_texture: Some(texture_handle.0.clone()),
+        tiles: atlas.tiles(),
+        alpha_mode: AlphaMode::Blend,
+        ..default()
+    });
+
+    let chunks_per_axis = grid.chunks_per_axis();
+    for z in 0..chunks_per_axis {
+        for y in 0..chunks_per_axis {
+            for x in 0..chunks_per_axis {
+                let coord = UVec3::new(x, y, z);
+                // The meshes themselves are built by `remesh_system` once the grid is inserted. Until then the handles
+                // have no asset, so the chunk simply isn't drawn.
+                let opaque_mesh = meshes.get_handle(HandleId::random::<Mesh>());
+                let translucent_mesh = meshes.get_handle(HandleId::random::<Mesh>());
+
+                let transform = Transform::from_translation(grid.chunk_translation(coord));
+                let entity = commands
+                    .spawn(SpatialBundle::from_transform(transform))
+                    .with_children(|chunk| {
+                        chunk.spawn((
+                            MaterialMeshBundle {
+                                mesh: opaque_mesh.clone(),
+                                material: opaque_material.clone(),
+                                ..Default::default()
+                            },
+                            ChunkMesh,
+                        ));
+                        // The translucent quads are kept sorted back to front, see `sort_quads_back_to_front`.
+                        chunk.spawn((
+                            MaterialMeshBundle {
+                                mesh: translucent_mesh.clone(),
+                                material: translucent_material.clone(),
+                                ..Default::default()
+                            },
+                            ChunkMesh,
+                        ));
+                    })
+                    .id();
+
+                let chunk = grid.chunk_mut(coord);
+                chunk.opaque_mesh = opaque_mesh;
+                chunk.translucent_mesh = translucent_mesh;
+                chunk.entity = Some(entity);
+            }
+        }
+    }
+    commands.insert_resource(grid);
+    commands.insert_resource(MaterialCache {
+        opaque: opaque_material,
+        translucent: translucent_material,
+    });
+
+    // `sun_system` moves the sun and sets the ambient light from the time of day.
+    commands.spawn(sun_bundle());
+    let camera = commands.spawn(Camera3dBundle::default()).id();
+    commands.insert_resource(CameraRotationState::new(camera));
+    commands.insert_resource(AmbientLight {
+        color: Color::WHITE,
+        brightness: 0.5,
+    });
+}
+
+/// Takes the atlas off the chunk materials in the render modes other than [`RenderMode::Textured`], so only the
+/// vertex colors are left.
+fn apply_render_mode(
+    render_mode: Res<RenderMode>,
+    texture_handle: Res<AtlasTexture>,
+    material_cache: Res<MaterialCache>,
+    mut materials: ResMut<Assets<VoxelMaterial>>,
+) {
+    if !render_mode.is_changed() {
+        return;
+    }
+    let texture = match *render_mode {
+        RenderMode::Textured => Some(texture_handle.0.clone()),
+        RenderMode::SolidColor | RenderMode::MergeSize => None,
+    };
+    for handle in material_cache.handles() {
+        if let Some(material) = materials.get_mut(handle) {
+            material.base_color_texture = texture.clone();
+        }
+    }
+}
+
+/// Copies the [`FogSettings`] into the chunk materials whenever they change, and on the first run.
+fn apply_fog(
+    fog: Res<FogSettings>,
+    material_cache: Res<MaterialCache>,
+    mut materials: ResMut<Assets<VoxelMaterial>>,
+) {
+    if !fog.is_changed() {
+        return;
+    }
+    for handle in material_cache.handles() {
+        if let Some(material) = materials.get_mut(handle) {
+            material.fog = Some(fog.clone());
+        }
+    }
+}
+
+/// Copies the [`GridLines`] into the chunk materials whenever they change, and on the first run.
+fn apply_grid_lines(
+    grid_lines: Res<GridLines>,
+    material_cache: Res<MaterialCache>,
+    mut materials: ResMut<Assets<VoxelMaterial>>,
+) {
+    if !grid_lines.is_changed() {
+        return;
+    }
+    for handle in material_cache.handles() {
+        if let Some(material) = materials.get_mut(handle) {
+            material.grid_lines = Some(*grid_lines);
+        }
+    }
+}
+
+/// Rescales the grid when the [`VoxelScale`] changes after the world is built, and moves the chunk entities to their
+/// new places. Changing the grid remeshes every chunk, which scales the meshes themselves.
+fn apply_voxel_scale(
+    voxel_scale: Res<VoxelScale>,
+    mut grid: ResMut<ChunkGrid>,
+    mut transforms: Query<&mut Transform>,
+) {
+    if !voxel_scale.is_changed() || voxel_scale.is_added() {
+        return;
+    }
+    grid.set_voxel_scale(*voxel_scale);
+    for (coord, chunk) in grid.chunks() {
+        if let Some(mut transform) = chunk
+            .entity
+            .and_then(|entity| transforms.get_mut(entity).ok())
+        {
+            transform.translation = grid.chunk_translation(coord);
+        }
+    }
+}
+
+/// Puts the [`Translucency`] into the alpha of the translucent material's base color whenever it changes, and on the
+/// first run.
+fn apply_translucency(
+    translucency: Res<Translucency>,
+    material_cache: Res<MaterialCache>,
+    mut materials: ResMut<Assets<VoxelMaterial>>,
+) {
+    if !translucency.is_changed() {
+        return;
+    }
+    if let Some(material) = materials.get_mut(&material_cache.translucent) {
+        material.base_color.set_a(translucency.0);
+    }
+}
+
+/// Puts the [`AlphaCutoff`] into the opaque material's alpha mode whenever it changes.
+fn apply_alpha_cutoff(
+    alpha_cutoff: Res<AlphaCutoff>,
+    material_cache: Res<MaterialCache>,
+    mut materials: ResMut<Assets<VoxelMaterial>>,
+) {
+    if !alpha_cutoff.is_changed() {
+        return;
+    }
+    if let Some(material) = materials.get_mut(&material_cache.opaque) {
+        material.alpha_mode = AlphaMode::Mask(alpha_cutoff.0);
+    }
+}
+
+/// J switches the chunk materials between darkening the texture with AO and only occluding the ambient light.
+fn toggle_ao_shading(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    material_cache: Res<MaterialCache>,
+    mut materials: ResMut<Assets<VoxelMaterial>>,
+) {
+    if !bindings.just_pressed(&keys, Action::ToggleAoShading) {
+        return;
+    }
+    let Some(current) = materials
+        .get(&material_cache.opaque)
+        .map(|material| material.ao_shading)
+    else {
+        return;
+    };
+    let shading = match current {
+        AoShading::Albedo => AoShading::Ambient,
+        AoShading::Ambient => AoShading::Albedo,
+    };
+    info!("AO shading: {shading:?}");
+    for handle in material_cache.handles() {
+        if let Some(material) = materials.get_mut(handle) {
+            material.ao_shading = shading;
+        }
+    }
+}
+
+/// Scrolls the texture of flowing voxels. Both materials get the offset, so flowing voxels don't have to be
+/// translucent.
+fn scroll_water_system(
+    time: Res<Time>,
+    settings: Res<WaterSettings>,
+    material_cache: Res<MaterialCache>,
+    mut materials: ResMut<Assets<VoxelMaterial>>,
+) {
+    let uv_offset = settings.uv_offset(time.elapsed_seconds_f64());
+    for handle in material_cache.handles() {
+        if let Some(material) = materials.get_mut(handle) {
+            material.uv_offset = uv_offset;
+        }
+    }
+}
+
+/// Keeps the atlas sampler in sync with [`SamplerSettings`]. The first run counts both as changed, which sets up the
+/// sampler as soon as the atlas is loaded.
+fn update_atlas_sampler(
+    settings: Res<SamplerSettings>,
+    texture_handle: Res<AtlasTexture>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    if !(settings.is_changed() || texture_handle.is_changed()) {
+        return;
+    }
+    if let Some(image) = images.get_mut(&texture_handle.0) {
+        apply_sampler_settings(image, &settings);
+    }
+}
+
+/// Picks up changes to the atlas on disk. The reloaded image comes with the default sampler, so the [`SamplerSettings`]
+/// are put back, and the chunk materials are touched so they get bound to the new texture. Changing the sampler
+/// modifies the image as well, which refreshes the materials for [`update_atlas_sampler`] too.
+fn reload_atlas_system(
+    mut events: EventReader<AssetEvent<Image>>,
+    texture_handle: Res<AtlasTexture>,
+    settings: Res<SamplerSettings>,
+    material_cache: Res<MaterialCache>,
+    mut images: ResMut<Assets<Image>>,
+    mut materials: ResMut<Assets<VoxelMaterial>>,
+) {
+    let modified = events.iter().any(
+        |event| matches!(event, AssetEvent::Modified { handle } if *handle == texture_handle.0),
+    );
+    if !modified {
+        return;
+    }
+    // Only writing the sampler when it's missing keeps this from modifying the image, and running again, every frame.
+    let needs_sampler = images
+        .get(&texture_handle.0)
+        .is_some_and(|image| !has_sampler_settings(image, &settings));
+    if needs_sampler {
+        if let Some(image) = images.get_mut(&texture_handle.0) {
+            apply_sampler_settings(image, &settings);
+        }
+    }
+    for handle in material_cache.handles() {
+        materials.get_mut(handle);
+    }
+}
+
+/// Builds the texture array from the atlas for [`TextureLayout::Array`] and hands it to the chunk materials, again
+/// whenever the atlas changes, so reloads and sampler changes carry over. [`TextureLayout::Atlas`] puts the
+/// [`FALLBACK_TEXTURE_ARRAY`] back, which drops the array.
+fn apply_texture_layout(
+    mut events: EventReader<AssetEvent<Image>>,
+    layout: Res<TextureLayout>,
+    texture_handle: Res<AtlasTexture>,
+    material_cache: Res<MaterialCache>,
+    atlas: Res<AtlasConfig>,
+    mut images: ResMut<Assets<Image>>,
+    mut materials: ResMut<Assets<VoxelMaterial>>,
+) {
+    let modified = events.iter().any(
+        |event| matches!(event, AssetEvent::Modified { handle } if *handle == texture_handle.0),
+    );
+    if !(modified || layout.is_changed() || atlas.is_changed()) {
+        return;
+    }
+    let texture_array = match *layout {
+        TextureLayout::Atlas => FALLBACK_TEXTURE_ARRAY.typed(),
+        TextureLayout::Array => match images.get(&texture_handle.0) {
+            Some(image) => {
+                let array = atlas_to_texture_array(image, &atlas);
+                images.add(array)
+            }
+            None => return,
+        },
+    };
+    for handle in material_cache.handles() {
+        if let Some(material) = materials.get_mut(handle) {
+            material.texture_array = texture_array.clone();
+        }
+    }
+}
+
+/// Queues a rebuild of every chunk mesh whenever the grid or any of the settings that go into the mesh change, and of
+/// the [`Dirty`] chunks otherwise. Edits mark the chunks they touch instead of changing the grid, see
+/// [`crate::editing`].
+///
+/// The meshing itself runs on the [`AsyncComputeTaskPool`](bevy::tasks::AsyncComputeTaskPool) with its own copy of
+/// the data, see [`poll_mesh_tasks`].
+#[allow(clippy::too_many_arguments)]
+fn remesh_system(
+    meshing_mode: Res<MeshingMode>,
+    normal_mode: Res<NormalMode>,
+    render_mode: Res<RenderMode>,
+    coordinates: Res<CoordinateSystem>,
+    explode: Res<ExplodeFactor>,
+    cross_section: Res<CrossSection>,
+    registry: Res<VoxelRegistry>,
+    atlas: Res<AtlasConfig>,
+    ao_settings: Res<AoSettings>,
+    grid: Res<ChunkGrid>,
+    dirty: Query<Entity, With<Dirty>>,
+    mut commands: Commands,
+    mut tasks: ResMut<ChunkMeshTasks>,
+    buffer_pool: Res<QuadBufferPool>,
+) {
+    let remesh_all = grid.is_changed()
+        || registry.is_changed()
+        || atlas.is_changed()
+        || ao_settings.is_changed()
+        || meshing_mode.is_changed()
+        || normal_mode.is_changed()
+        || render_mode.is_changed()
+        || coordinates.is_changed()
+        || explode.is_changed()
+        || cross_section.is_changed();
+    if !remesh_all && dirty.is_empty() {
+        return;
+    }
+    for entity in &dirty {
+        commands.entity(entity).remove::<Dirty>();
+    }
+    for (coord, chunk) in grid.chunks() {
+        let is_dirty = chunk.entity.is_some_and(|entity| dirty.contains(entity));
+        if !(remesh_all || is_dirty) {
+            continue;
+        }
+        let mut voxels = gather_padding(&grid, coord);
+        cross_section.apply(&mut voxels, coord);
+        let registry = registry.clone();
+        let atlas = *atlas;
+        let ao_settings = ao_settings.clone();
+        let mode = *meshing_mode;
+        let normal_mode = *normal_mode;
+        let render_mode = *render_mode;
+        let coordinates = *coordinates;
+        let voxel_scale = grid.voxel_scale();
+        let explode = explode.0 * voxel_scale;
+        let buffer_pool = buffer_pool.clone();
+        let task = spawn_mesh_task(move || {
+            let mut buffers = buffer_pool.take();
+            let mut data = mesh_chunk_with_buffers(
+                &voxels,
+                &registry,
+                &atlas,
+                &ao_settings,
+                mode,
+                render_mode,
+                coordinates,
+                voxel_scale,
+                &mut buffers,
+            );
+            buffer_pool.give_back(buffers);
+            data.opaque.explode(explode);
+            data.translucent.explode(explode);
+            (
+                data.opaque.into_mesh(&ao_settings, normal_mode),
+                data.translucent.into_mesh(&ao_settings, normal_mode),
+                count_visible_faces(&voxels, &registry),
+            )
+        });
+        tasks.0.insert(
+            coord,
+            ChunkMeshTask {
+                translucent_mesh: chunk.translucent_mesh.clone(),
+                task,
+            },
+        );
+    }
+}
+
+/// Moves finished chunk meshes into their assets, sorted for the current camera position, and records their size.
+///
+/// Opaque meshes go through the [`MeshDedupCache`], which can hand the chunk another chunk's handle. The chunk's mesh
+/// entity is pointed at the new handle, bypassing change detection on the grid so that doesn't remesh everything
+/// again. Translucent meshes are sorted for their own chunk's position, so they're never shared.
+#[allow(clippy::too_many_arguments)]
+fn poll_mesh_tasks(
+    camera: Res<CameraRotationState>,
+    transforms: Query<&GlobalTransform>,
+    children: Query<&Children>,
+    mut chunk_meshes: Query<&mut Handle<Mesh>, With<ChunkMesh>>,
+    mut grid: ResMut<ChunkGrid>,
+    mut tasks: ResMut<ChunkMeshTasks>,
+    mut dedup: ResMut<MeshDedupCache>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut stats: ResMut<MeshStats>,
+) {
+    let viewer = viewer_position(&camera, &transforms);
+    #[cfg(target_arch = "wasm32")]
+    let mut budget = WEB_MESHES_PER_FRAME;
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut budget = 0;
+    tasks.0.retain(|coord, chunk_task| {
+        let Some((opaque, mut translucent, visibility)) =
+            poll_mesh_task(&mut chunk_task.task, &mut budget)
+        else {
+            return true;
+        };
+        if let Some(viewer) = viewer {
+            sort_quads_back_to_front(&mut translucent, viewer - grid.chunk_translation(*coord));
+        }
+        stats.0.insert(
+            *coord,
+            ChunkStats {
+                geometry: GeometryCount::of(&opaque) + GeometryCount::of(&translucent),
+                visibility,
+            },
+        );
+        set_chunk_mesh(&mut meshes, &chunk_task.translucent_mesh, translucent);
+
+        // Empty meshes get a handle without an asset, so the chunk isn't drawn.
+        let opaque = if opaque.count_vertices() == 0 {
+            Handle::default()
+        } else {
+            dedup.share(&mut meshes, opaque)
+        };
+        let chunk = grid.bypass_change_detection().chunk_mut(*coord);
+        if opaque != chunk.opaque_mesh {
+            let entities = chunk.entity.and_then(|entity| children.get(entity).ok());
+            for &entity in entities.into_iter().flatten() {
+                if let Ok(mut handle) = chunk_meshes.get_mut(entity) {
+                    if *handle == chunk.opaque_mesh {
+                        *handle = opaque.clone();
+                    }
+                }
+            }
+            chunk.opaque_mesh = opaque;
+        }
+        false
+    });
+}
+
+/// Leaves the asset out for meshes without any faces, so empty chunks don't get drawn at all.
+fn set_chunk_mesh(meshes: &mut Assets<Mesh>, handle: &Handle<Mesh>, mesh: Mesh) {
+    if mesh.count_vertices() == 0 {
+        meshes.remove(handle);
+    } else {
+        meshes.set_untracked(handle.clone(), mesh);
+    }
+}
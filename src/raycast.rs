@@ -0,0 +1,163 @@
+use bevy::prelude::*;
+use block_mesh::VoxelVisibility;
+
+use crate::chunk::ChunkGrid;
+use crate::voxel::VoxelRegistry;
+
+/// The first non-empty voxel along a ray.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RayHit {
+    /// Grid coordinates of the voxel.
+    pub voxel: UVec3,
+    /// Normal of the face the ray entered through, or zero if the ray started inside the voxel.
+    pub normal: IVec3,
+}
+
+impl RayHit {
+    /// The corners of the face the ray entered through, in world space and in order around the face, or `None` if
+    /// the ray started inside the voxel. This is the face a placed voxel attaches to.
+    pub fn face_corners(&self, grid: &ChunkGrid) -> Option<[Vec3; 4]> {
+        if self.normal == IVec3::ZERO {
+            return None;
+        }
+        let scale = grid.voxel_scale();
+        let normal = self.normal.as_vec3();
+        let center = grid.voxel_origin() + (self.voxel.as_vec3() + 0.5 + normal * 0.5) * scale;
+        // The two axes along the face.
+        let (u, v) = match self.normal.abs() {
+            IVec3::X => (Vec3::Y, Vec3::Z),
+            IVec3::Y => (Vec3::Z, Vec3::X),
+            _ => (Vec3::X, Vec3::Y),
+        };
+        let (u, v) = (u * scale * 0.5, v * scale * 0.5);
+        Some([
+            center - u - v,
+            center + u - v,
+            center + u + v,
+            center - u + v,
+        ])
+    }
+}
+
+/// Walks the voxels along a world space ray using the DDA traversal from Amanatides and Woo, "A Fast Voxel Traversal
+/// Algorithm for Ray Tracing". Voxels outside the grid are treated as empty.
+pub fn raycast(
+    grid: &ChunkGrid,
+    registry: &VoxelRegistry,
+    origin: Vec3,
+    direction: Vec3,
+    max_distance: f32,
+) -> Option<RayHit> {
+    let direction = direction.normalize_or_zero();
+    if direction == Vec3::ZERO {
+        return None;
+    }
+
+    // In grid space voxel `v` covers `[v, v + 1)` on every axis.
+    let start = (origin - grid.voxel_origin()) / grid.voxel_scale();
+    let max_distance = max_distance / grid.voxel_scale();
+    let mut cell = start.floor().as_ivec3();
+    let step = IVec3::new(
+        step_for(direction.x),
+        step_for(direction.y),
+        step_for(direction.z),
+    );
+    // How far along the ray we have to go to cross one voxel on each axis, and to cross the next boundary on each axis.
+    let t_delta = Vec3::select(
+        direction.cmpeq(Vec3::ZERO),
+        Vec3::splat(f32::INFINITY),
+        (1.0 / direction).abs(),
+    );
+    let next_boundary = cell.as_vec3() + step.max(IVec3::ZERO).as_vec3();
+    let mut t_max = Vec3::select(
+        direction.cmpeq(Vec3::ZERO),
+        Vec3::splat(f32::INFINITY),
+        (next_boundary - start) / direction,
+    );
+
+    let mut normal = IVec3::ZERO;
+    let mut t = 0.0;
+    while t <= max_distance {
+        if grid.contains(cell) {
+            let voxel = cell.as_uvec3();
+            if registry.visibility(grid.voxel(voxel)) != VoxelVisibility::Empty {
+                return Some(RayHit { voxel, normal });
+            }
+        }
+
+        if t_max.x < t_max.y && t_max.x < t_max.z {
+            cell.x += step.x;
+            t = t_max.x;
+            t_max.x += t_delta.x;
+            normal = IVec3::new(-step.x, 0, 0);
+        } else if t_max.y < t_max.z {
+            cell.y += step.y;
+            t = t_max.y;
+            t_max.y += t_delta.y;
+            normal = IVec3::new(0, -step.y, 0);
+        } else {
+            cell.z += step.z;
+            t = t_max.z;
+            t_max.z += t_delta.z;
+            normal = IVec3::new(0, 0, -step.z);
+        }
+    }
+
+    None
+}
+
+fn step_for(direction: f32) -> i32 {
+    if direction > 0.0 {
+        1
+    } else if direction < 0.0 {
+        -1
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::VoxelScale;
+    use crate::voxel::Voxel;
+
+    #[test]
+    fn hits_report_the_face_they_entered() {
+        let mut grid = ChunkGrid::new(1).with_voxel_scale(VoxelScale(0.5));
+        let voxel = UVec3::new(4, 6, 8);
+        grid.set_voxel(voxel, Voxel::STONE_VOXEL);
+        let registry = VoxelRegistry::default();
+        let center = grid.voxel_origin() + (voxel.as_vec3() + 0.5) * 0.5;
+
+        // Coming down from above hits the top face, whose corners sit on the top of the voxel.
+        let hit = raycast(&grid, &registry, center + Vec3::Y * 3.0, -Vec3::Y, 10.0).unwrap();
+        assert_eq!(
+            hit,
+            RayHit {
+                voxel,
+                normal: IVec3::Y
+            }
+        );
+        let corners = hit.face_corners(&grid).unwrap();
+        for corner in corners {
+            assert_eq!(corner.y, center.y + 0.25);
+            assert_eq!((corner - center).abs().max_element(), 0.25);
+        }
+        // Neighbouring corners are one edge apart, so they go around the face.
+        for i in 0..4 {
+            assert_eq!(corners[i].distance(corners[(i + 1) % 4]), 0.5);
+        }
+
+        let side = raycast(&grid, &registry, center - Vec3::X * 3.0, Vec3::X, 10.0).unwrap();
+        assert_eq!(side.normal, -IVec3::X);
+        assert!(side
+            .face_corners(&grid)
+            .unwrap()
+            .iter()
+            .all(|corner| corner.x == center.x - 0.25));
+
+        let inside = raycast(&grid, &registry, center, Vec3::X, 10.0).unwrap();
+        assert_eq!(inside.face_corners(&grid), None);
+    }
+}
@@ -0,0 +1,72 @@
+use block_mesh::ndshape::ConstShape;
+
+use crate::chunk::ChunkShape;
+use crate::volume::VoxelVolume;
+use crate::voxel::Voxel;
+
+/// A [`VoxelVolume`] stored as runs of equal voxels in the volume's own layout. Chunks of nothing but air, or solid
+/// stone, shrink to a single run, so this is meant for keeping chunks around that aren't being edited or meshed. The
+/// mesher only works on the flat layout, so expand the chunk with [`RleChunk::to_flat`] first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RleChunk<S = ChunkShape> {
+    /// Run lengths and their voxel. Runs longer than `u16::MAX` are split up.
+    runs: Vec<(u16, Voxel)>,
+    shape: S,
+}
+
+impl<S: ConstShape<3, Coord = u32> + Clone> RleChunk<S> {
+    pub fn from_flat(volume: &VoxelVolume<S>) -> Self {
+        let mut runs: Vec<(u16, Voxel)> = Vec::new();
+        for &voxel in volume.as_slice() {
+            match runs.last_mut() {
+                Some((length, last)) if *last == voxel && *length < u16::MAX => *length += 1,
+                _ => runs.push((1, voxel)),
+            }
+        }
+        runs.shrink_to_fit();
+        Self {
+            runs,
+            shape: volume.shape().clone(),
+        }
+    }
+
+    pub fn to_flat(&self) -> VoxelVolume<S> {
+        let mut voxels = Vec::with_capacity(S::USIZE);
+        for &(length, voxel) in &self.runs {
+            voxels.resize(voxels.len() + length as usize, voxel);
+        }
+        VoxelVolume::from_voxels(self.shape.clone(), voxels)
+    }
+
+    pub fn run_count(&self) -> usize {
+        self.runs.len()
+    }
+
+    /// Roughly how much memory the chunk takes up, in bytes, to compare with [`VoxelVolume::memory_bytes`].
+    pub fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() + self.runs.capacity() * std::mem::size_of::<(u16, Voxel)>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_and_shrinks_uniform_chunks() {
+        let air = VoxelVolume::default();
+        let rle = RleChunk::from_flat(&air);
+        assert_eq!(rle.run_count(), 1);
+        assert_eq!(rle.to_flat(), air);
+        assert!(rle.memory_bytes() * 100 < air.memory_bytes());
+
+        let mut mixed = VoxelVolume::default();
+        mixed.set(0, 0, 0, Voxel::STONE_VOXEL);
+        mixed.set(1, 0, 0, Voxel::STONE_VOXEL);
+        mixed.set(5, 6, 7, Voxel::GRASS_VOXEL);
+        let rle = RleChunk::from_flat(&mixed);
+        // Two stone, air up to the grass, the grass and air to the end.
+        assert_eq!(rle.run_count(), 4);
+        assert_eq!(rle.to_flat(), mixed);
+    }
+}
@@ -0,0 +1,183 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use bevy::prelude::*;
+
+use crate::chunk::ChunkGrid;
+use crate::input::{Action, KeyBindings};
+use crate::voxel::Voxel;
+
+/// Where Ctrl+S saves the world and Ctrl+L loads it from.
+const SAVE_PATH: &str = "world.voxels";
+
+const MAGIC: &[u8; 4] = b"HBMV";
+/// Bumped whenever the layout below changes.
+const VERSION: u16 = 1;
+
+/// Writes voxels as the magic bytes, the format version, the voxel count and then runs of equal voxels, each a `u16`
+/// length followed by the voxel id. All numbers are little endian.
+pub fn save_voxels(path: impl AsRef<Path>, voxels: &[Voxel]) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    file.write_all(MAGIC)?;
+    file.write_all(&VERSION.to_le_bytes())?;
+    file.write_all(&(voxels.len() as u32).to_le_bytes())?;
+
+    let mut runs = voxels.iter().peekable();
+    while let Some(&voxel) = runs.next() {
+        let mut length: u16 = 1;
+        while length < u16::MAX && runs.next_if_eq(&&voxel).is_some() {
+            length += 1;
+        }
+        file.write_all(&length.to_le_bytes())?;
+        file.write_all(&[voxel.0])?;
+    }
+    file.flush()
+}
+
+/// Reads voxels written by [`save_voxels`]. A file that says it holds more than `max_count` voxels is rejected before
+/// anything is allocated for them, so a corrupt count can't ask for gigabytes.
+pub fn load_voxels(path: impl AsRef<Path>, max_count: usize) -> io::Result<Vec<Voxel>> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut magic = [0; 4];
+    file.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid_data("not a voxel file".to_string()));
+    }
+    let version = u16::from_le_bytes(read_array(&mut file)?);
+    if version != VERSION {
+        return Err(invalid_data(format!("unsupported version {version}")));
+    }
+
+    let count = u32::from_le_bytes(read_array(&mut file)?) as usize;
+    if count > max_count {
+        return Err(invalid_data(format!(
+            "{count} voxels, but at most {max_count} fit"
+        )));
+    }
+    let mut voxels = Vec::with_capacity(count);
+    while voxels.len() < count {
+        let length = u16::from_le_bytes(read_array(&mut file)?) as usize;
+        let [id] = read_array(&mut file)?;
+        if length == 0 {
+            return Err(invalid_data("a run of no voxels".to_string()));
+        }
+        if voxels.len() + length > count {
+            return Err(invalid_data(
+                "runs are longer than the voxel count".to_string(),
+            ));
+        }
+        voxels.resize(voxels.len() + length, Voxel(id));
+    }
+    Ok(voxels)
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Every voxel of the grid, indexed `x + size.x * (y + size.y * z)` with `size` the world size.
+pub fn grid_voxels(grid: &ChunkGrid) -> Vec<Voxel> {
+    let size = grid.world_size();
+    let mut voxels = Vec::with_capacity(grid_voxel_count(grid));
+    for z in 0..size.z {
+        for y in 0..size.y {
+            for x in 0..size.x {
+                voxels.push(grid.voxel(UVec3::new(x, y, z)));
+            }
+        }
+    }
+    voxels
+}
+
+/// How many voxels the grid holds, which is as many as [`grid_voxels`] returns.
+pub fn grid_voxel_count(grid: &ChunkGrid) -> usize {
+    let size = grid.world_size();
+    size.x as usize * size.y as usize * size.z as usize
+}
+
+/// The reverse of [`grid_voxels`]. Fails if the voxels are for a world of another size.
+pub fn set_grid_voxels(grid: &mut ChunkGrid, voxels: &[Voxel]) -> Result<(), String> {
+    let size = grid.world_size();
+    let count = grid_voxel_count(grid);
+    if voxels.len() != count {
+        return Err(format!("expected {count} voxels but got {}", voxels.len()));
+    }
+    for (voxel, i) in voxels.iter().zip(0..) {
+        let position = UVec3::new(i % size.x, i / size.x % size.y, i / (size.x * size.y));
+        grid.set_voxel(position, *voxel);
+    }
+    Ok(())
+}
+
+/// Ctrl+S saves the world and Ctrl+L loads the last save, which gets it remeshed.
+pub fn save_load_system(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut grid: ResMut<ChunkGrid>,
+) {
+    if bindings.just_pressed(&keys, Action::Save) {
+        match save_voxels(SAVE_PATH, &grid_voxels(&grid)) {
+            Ok(()) => info!("saved the world to {SAVE_PATH}"),
+            Err(err) => error!("failed to save {SAVE_PATH}: {err}"),
+        }
+    } else if bindings.just_pressed(&keys, Action::Load) {
+        let loaded = load_voxels(SAVE_PATH, grid_voxel_count(&grid))
+            .map_err(|err| err.to_string())
+            .and_then(|voxels| set_grid_voxels(&mut grid, &voxels));
+        match loaded {
+            Ok(()) => info!("loaded the world from {SAVE_PATH}"),
+            Err(err) => error!("failed to load {SAVE_PATH}: {err}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::worldgen::{generate_world, RandomSeed, TerrainConfig, WorldGenerator};
+
+    #[test]
+    fn saved_grids_load_unchanged() {
+        let mut grid = ChunkGrid::new(2);
+        generate_world(
+            &mut grid,
+            WorldGenerator::Terrain,
+            &TerrainConfig::default(),
+            RandomSeed(0),
+        );
+        let voxels = grid_voxels(&grid);
+
+        let path = std::env::temp_dir().join("hacky_block_mesh_round_trip.voxels");
+        save_voxels(&path, &voxels).unwrap();
+        let loaded = load_voxels(&path, voxels.len()).unwrap();
+        // More voxels than the grid holds are rejected up front.
+        assert!(load_voxels(&path, voxels.len() - 1).is_err());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, voxels);
+
+        // So is a run of no voxels, which would otherwise only end at the end of the file.
+        let bad = std::env::temp_dir().join("hacky_block_mesh_empty_run.voxels");
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(VERSION.to_le_bytes());
+        bytes.extend(1u32.to_le_bytes());
+        bytes.extend(0u16.to_le_bytes());
+        bytes.push(Voxel::STONE_VOXEL.0);
+        std::fs::write(&bad, bytes).unwrap();
+        let empty_run = load_voxels(&bad, 1).unwrap_err();
+        std::fs::remove_file(&bad).unwrap();
+        assert_eq!(empty_run.kind(), io::ErrorKind::InvalidData);
+
+        let mut loaded_grid = ChunkGrid::new(2);
+        set_grid_voxels(&mut loaded_grid, &loaded).unwrap();
+        for ((_, chunk), (_, loaded_chunk)) in grid.chunks().zip(loaded_grid.chunks()) {
+            assert_eq!(chunk.voxels, loaded_chunk.voxels);
+        }
+    }
+}
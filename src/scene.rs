@@ -0,0 +1,156 @@
+use bevy::prelude::*;
+
+use crate::voxelize::VoxelizeFill;
+
+/// What gets shown at startup.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DemoScene {
+    /// Random voxel ids everywhere.
+    #[default]
+    Random,
+    /// The Perlin heightmap terrain.
+    Noise,
+    /// A MagicaVoxel model, see [`SceneConfig::file`].
+    Vox,
+    /// An MCEdit schematic, see [`SceneConfig::file`] and [`crate::schematic::BlockMapping`].
+    Schematic,
+    /// A triangle mesh from an OBJ file, voxelized at [`SceneConfig::resolution`], see
+    /// [`crate::voxelize::voxelize_obj`].
+    Obj,
+}
+
+/// The scene picked on the command line, for example `cargo run -- --scene vox --file model.vox`.
+#[derive(Resource, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SceneConfig {
+    pub scene: DemoScene,
+    /// The model to load for [`DemoScene::Vox`], [`DemoScene::Schematic`] or [`DemoScene::Obj`].
+    pub file: Option<String>,
+    /// The [`crate::worldgen::RandomSeed`] for the random scene.
+    pub seed: Option<u64>,
+    /// How many voxels across the largest side of an OBJ model gets, up to [`MAX_OBJ_RESOLUTION`].
+    /// [`DEFAULT_OBJ_RESOLUTION`] if not given.
+    pub resolution: Option<u32>,
+    /// Whether an OBJ model is voxelized hollow or solid.
+    pub fill: VoxelizeFill,
+}
+
+/// The [`SceneConfig::resolution`] of OBJ models unless another one is given, which fits them into three chunks.
+pub const DEFAULT_OBJ_RESOLUTION: u32 = 48;
+
+/// The highest [`SceneConfig::resolution`], as large as the largest MagicaVoxel model. That's already a grid of 13
+/// chunks along each side, and every one of them gets meshed at startup.
+pub const MAX_OBJ_RESOLUTION: u32 = 256;
+
+const USAGE: &str = concat!(
+    "usage: uv_mapping [--scene random|noise|vox|schematic|obj] ",
+    "[--file <model.vox|structure.schematic|mesh.obj>] [--resolution <voxels>] [--fill shell|solid] ",
+    "[--seed <number>]"
+);
+
+impl SceneConfig {
+    /// Parses the process arguments, exiting with the usage if they don't make sense.
+    pub fn from_env() -> Self {
+        Self::parse(std::env::args().skip(1)).unwrap_or_else(|err| {
+            eprintln!("{err}\n{USAGE}");
+            std::process::exit(2);
+        })
+    }
+
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut config = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+            match arg.as_str() {
+                "--scene" => {
+                    config.scene = match value()?.as_str() {
+                        "random" => DemoScene::Random,
+                        "noise" => DemoScene::Noise,
+                        "vox" => DemoScene::Vox,
+                        "schematic" => DemoScene::Schematic,
+                        "obj" => DemoScene::Obj,
+                        other => return Err(format!("unknown scene {other}")),
+                    }
+                }
+                "--file" => config.file = Some(value()?),
+                "--resolution" => {
+                    let resolution = value()?;
+                    config.resolution = Some(
+                        resolution
+                            .parse()
+                            .ok()
+                            .filter(|resolution| (1..=MAX_OBJ_RESOLUTION).contains(resolution))
+                            .ok_or_else(|| format!("bad resolution {resolution}"))?,
+                    );
+                }
+                "--fill" => {
+                    config.fill = match value()?.as_str() {
+                        "shell" => VoxelizeFill::Shell,
+                        "solid" => VoxelizeFill::Solid,
+                        other => return Err(format!("unknown fill {other}")),
+                    }
+                }
+                "--seed" => {
+                    let seed = value()?;
+                    config.seed = Some(seed.parse().map_err(|_| format!("bad seed {seed}"))?);
+                }
+                _ => return Err(format!("unknown argument {arg}")),
+            }
+        }
+        if matches!(
+            config.scene,
+            DemoScene::Vox | DemoScene::Schematic | DemoScene::Obj
+        ) && config.file.is_none()
+        {
+            return Err("the vox, schematic and obj scenes need a --file".to_string());
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<SceneConfig, String> {
+        SceneConfig::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_scenes() {
+        assert_eq!(parse(&[]), Ok(SceneConfig::default()));
+        assert_eq!(
+            parse(&["--scene", "noise"]).unwrap().scene,
+            DemoScene::Noise
+        );
+        assert_eq!(
+            parse(&["--scene", "vox", "--file", "model.vox"]),
+            Ok(SceneConfig {
+                scene: DemoScene::Vox,
+                file: Some("model.vox".to_string()),
+                seed: None,
+                resolution: None,
+                fill: VoxelizeFill::Solid,
+            })
+        );
+        assert_eq!(parse(&["--seed", "42"]).unwrap().seed, Some(42));
+        assert!(parse(&["--seed", "many"]).is_err());
+        assert!(parse(&["--scene", "vox"]).is_err());
+        assert!(parse(&["--scene", "schematic"]).is_err());
+        assert_eq!(
+            parse(&["--scene", "obj", "--file", "a.obj", "--resolution", "32"])
+                .unwrap()
+                .resolution,
+            Some(32)
+        );
+        assert!(parse(&["--scene", "obj", "--file", "a.obj", "--resolution", "0"]).is_err());
+        assert!(parse(&["--resolution", "2000"]).is_err());
+        assert_eq!(
+            parse(&["--fill", "shell"]).unwrap().fill,
+            VoxelizeFill::Shell
+        );
+        assert!(parse(&["--fill", "hollow"]).is_err());
+        assert!(parse(&["--scene"]).is_err());
+        assert!(parse(&["--scene", "caves"]).is_err());
+    }
+}
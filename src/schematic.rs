@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+use std::io::Read;
+
+use bevy::prelude::*;
+use flate2::read::GzDecoder;
+
+use crate::voxel::Voxel;
+
+/// Which [`Voxel`] every block id of a schematic turns into, see [`load_schematic`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockMapping {
+    pub ids: HashMap<u16, Voxel>,
+    /// What blocks missing from `ids` become. `None` skips them, leaving air behind.
+    pub unknown: Option<Voxel>,
+}
+
+impl Default for BlockMapping {
+    /// The classic Minecraft ids of the blocks the default registry has something close to. Anything else is stone,
+    /// so the shape of a structure survives even if its materials don't.
+    fn default() -> Self {
+        let ids = [
+            (1, Voxel::STONE_VOXEL),
+            (2, Voxel::GRASS_VOXEL),
+            (3, Voxel::DIRT_VOXEL),
+            (4, Voxel::STONE_VOXEL),
+            (8, Voxel::WATER_VOXEL),
+            (9, Voxel::WATER_VOXEL),
+            (12, Voxel::SAND_VOXEL),
+            (24, Voxel::SAND_VOXEL),
+            (44, Voxel::STONE_SLAB_VOXEL),
+            (78, Voxel::SNOW_VOXEL),
+            (80, Voxel::SNOW_VOXEL),
+            (89, Voxel::GLOWSTONE_VOXEL),
+        ];
+        Self {
+            ids: ids.into_iter().collect(),
+            unknown: Some(Voxel::STONE_VOXEL),
+        }
+    }
+}
+
+impl BlockMapping {
+    /// Maps block `id` to `voxel`, replacing whatever it mapped to before.
+    pub fn with(mut self, id: u16, voxel: Voxel) -> Self {
+        self.ids.insert(id, voxel);
+        self
+    }
+
+    /// The voxel for block `id`. Block 0 is always air.
+    pub fn voxel(&self, id: u16) -> Voxel {
+        if id == 0 {
+            return Voxel::EMPTY_VOXEL;
+        }
+        self.ids
+            .get(&id)
+            .copied()
+            .or(self.unknown)
+            .unwrap_or(Voxel::EMPTY_VOXEL)
+    }
+}
+
+/// Reads an MCEdit `.schematic` file into a dense array along with its size, see [`parse_schematic`].
+pub fn load_schematic(path: &str, mapping: &BlockMapping) -> Result<(Vec<Voxel>, UVec3), String> {
+    let bytes = std::fs::read(path).map_err(|err| format!("failed to load {path}: {err}"))?;
+    parse_schematic(&bytes, mapping).map_err(|err| format!("failed to load {path}: {err}"))
+}
+
+/// Turns the contents of a `.schematic` file, gzipped or not, into voxels through `mapping`.
+///
+/// The voxels are indexed `x + size.x * (y + size.y * z)` like [`crate::vox::load_vox`], so the structure can be
+/// placed with [`crate::vox::place_model`]. Schematics are Y up already. Their block data values are ignored, so
+/// for example every kind of slab becomes the same voxel.
+pub fn parse_schematic(
+    bytes: &[u8],
+    mapping: &BlockMapping,
+) -> Result<(Vec<Voxel>, UVec3), String> {
+    let mut unzipped = Vec::new();
+    let bytes = if bytes.starts_with(&[0x1f, 0x8b]) {
+        GzDecoder::new(bytes)
+            .read_to_end(&mut unzipped)
+            .map_err(|err| format!("bad gzip data: {err}"))?;
+        &unzipped[..]
+    } else {
+        bytes
+    };
+
+    let mut reader = NbtReader { bytes };
+    if reader.u8()? != TAG_COMPOUND {
+        return Err("the root tag isn't a compound".to_string());
+    }
+    reader.string()?;
+    let (mut width, mut height, mut length) = (None, None, None);
+    let (mut blocks, mut add_blocks) = (None, None);
+    loop {
+        let tag = reader.u8()?;
+        if tag == TAG_END {
+            break;
+        }
+        match (tag, reader.string()?.as_str()) {
+            (TAG_SHORT, "Width") => width = Some(reader.i16()?),
+            (TAG_SHORT, "Height") => height = Some(reader.i16()?),
+            (TAG_SHORT, "Length") => length = Some(reader.i16()?),
+            (TAG_BYTE_ARRAY, "Blocks") => blocks = Some(reader.byte_array()?),
+            (TAG_BYTE_ARRAY, "AddBlocks") => add_blocks = Some(reader.byte_array()?),
+            _ => reader.skip(tag)?,
+        }
+    }
+
+    let missing = |name: &str| format!("the schematic has no {name}");
+    let dimension = |value: Option<i16>, name: &str| {
+        let value = value.ok_or_else(|| missing(name))?;
+        u32::try_from(value).map_err(|_| format!("negative {name} {value}"))
+    };
+    let size = UVec3::new(
+        dimension(width, "Width")?,
+        dimension(height, "Height")?,
+        dimension(length, "Length")?,
+    );
+    let blocks = blocks.ok_or_else(|| missing("Blocks"))?;
+    let [sx, sy, sz] = size.to_array().map(|side| side as usize);
+    let count = sx.checked_mul(sy).and_then(|count| count.checked_mul(sz));
+    if count != Some(blocks.len()) {
+        return Err(format!("{} blocks for a size of {size}", blocks.len()));
+    }
+
+    let mut voxels = vec![Voxel::EMPTY_VOXEL; blocks.len()];
+    for y in 0..sy {
+        for z in 0..sz {
+            for x in 0..sx {
+                // Schematics are indexed Y first, then Z.
+                let index = (y * sz + z) * sx + x;
+                // AddBlocks holds the upper 4 bits of the ids above 255, two blocks to a byte, the first in the high
+                // nibble.
+                let add = add_blocks
+                    .and_then(|add: &[u8]| add.get(index / 2))
+                    .map_or(0, |&add| if index & 1 == 0 { add >> 4 } else { add & 0xf });
+                let id = u16::from(blocks[index]) | u16::from(add) << 8;
+                voxels[x + sx * (y + sy * z)] = mapping.voxel(id);
+            }
+        }
+    }
+    Ok((voxels, size))
+}
+
+const TAG_END: u8 = 0;
+const TAG_SHORT: u8 = 2;
+const TAG_BYTE_ARRAY: u8 = 7;
+const TAG_LIST: u8 = 9;
+const TAG_COMPOUND: u8 = 10;
+
+/// Just enough of an NBT reader for schematics: the few tags they're read from, and skipping over everything else.
+/// NBT is big endian.
+struct NbtReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> NbtReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.bytes.len() < len {
+            return Err("the schematic ends early".to_string());
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    /// A short, which NBT stores signed.
+    fn i16(&mut self) -> Result<i16, String> {
+        Ok(i16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    /// A length, which NBT stores as a signed int.
+    fn len(&mut self) -> Result<usize, String> {
+        let len = i32::from_be_bytes(self.take(4)?.try_into().unwrap());
+        usize::try_from(len).map_err(|_| format!("negative length {len}"))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let len = self.u16()?;
+        Ok(String::from_utf8_lossy(self.take(len.into())?).into_owned())
+    }
+
+    fn byte_array(&mut self) -> Result<&'a [u8], String> {
+        let len = self.len()?;
+        self.take(len)
+    }
+
+    /// Skips the payload of a tag of type `tag`.
+    fn skip(&mut self, tag: u8) -> Result<(), String> {
+        match tag {
+            1 => self.take(1).map(drop),
+            TAG_SHORT => self.take(2).map(drop),
+            3 | 5 => self.take(4).map(drop),
+            4 | 6 => self.take(8).map(drop),
+            TAG_BYTE_ARRAY => self.byte_array().map(drop),
+            8 => self.string().map(drop),
+            TAG_LIST => {
+                let item = self.u8()?;
+                for _ in 0..self.len()? {
+                    self.skip(item)?;
+                }
+                Ok(())
+            }
+            TAG_COMPOUND => loop {
+                let tag = self.u8()?;
+                if tag == TAG_END {
+                    return Ok(());
+                }
+                self.string()?;
+                self.skip(tag)?;
+            },
+            11 => {
+                let len = self.len()?;
+                self.take(len * 4).map(drop)
+            }
+            12 => {
+                let len = self.len()?;
+                self.take(len * 8).map(drop)
+            }
+            _ => Err(format!("unknown tag {tag}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use super::*;
+    use crate::chunk::ChunkGrid;
+    use crate::vox::place_model;
+
+    fn named(out: &mut Vec<u8>, tag: u8, name: &str) {
+        out.push(tag);
+        out.extend((name.len() as u16).to_be_bytes());
+        out.extend(name.as_bytes());
+    }
+
+    /// A gzipped schematic of the given size, with a list of entities in front of the blocks to be skipped.
+    fn schematic(size: [u16; 3], blocks: &[u8]) -> Vec<u8> {
+        let mut nbt = Vec::new();
+        named(&mut nbt, TAG_COMPOUND, "Schematic");
+        named(&mut nbt, TAG_LIST, "Entities");
+        nbt.push(TAG_COMPOUND);
+        nbt.extend(1i32.to_be_bytes());
+        named(&mut nbt, 8, "id");
+        nbt.extend(3u16.to_be_bytes());
+        nbt.extend(b"Pig");
+        nbt.push(TAG_END);
+        for (name, value) in ["Width", "Height", "Length"].into_iter().zip(size) {
+            named(&mut nbt, TAG_SHORT, name);
+            nbt.extend(value.to_be_bytes());
+        }
+        named(&mut nbt, TAG_BYTE_ARRAY, "Blocks");
+        nbt.extend((blocks.len() as i32).to_be_bytes());
+        nbt.extend(blocks);
+        nbt.push(TAG_END);
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&nbt).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn schematic_blocks_map_to_voxels() {
+        // 2 wide, 2 high and 3 long, stored a layer of Y at a time.
+        let blocks = [
+            1, 2, 3, 12, 89, 0, // y = 0
+            0, 0, 0, 200, 0, 0, // y = 1
+        ];
+        let bytes = schematic([2, 2, 3], &blocks);
+        let (voxels, size) = parse_schematic(&bytes, &BlockMapping::default()).unwrap();
+        assert_eq!(size, UVec3::new(2, 2, 3));
+        let at = |x: u32, y: u32, z: u32| voxels[(x + size.x * (y + size.y * z)) as usize];
+        assert_eq!(at(0, 0, 0), Voxel::STONE_VOXEL);
+        assert_eq!(at(1, 0, 0), Voxel::GRASS_VOXEL);
+        assert_eq!(at(0, 0, 1), Voxel::DIRT_VOXEL);
+        assert_eq!(at(1, 0, 1), Voxel::SAND_VOXEL);
+        assert_eq!(at(0, 0, 2), Voxel::GLOWSTONE_VOXEL);
+        assert_eq!(at(1, 0, 2), Voxel::EMPTY_VOXEL);
+        // Block 200 isn't mapped, so it becomes stone by default, or nothing at all when unknown blocks are skipped.
+        assert_eq!(at(1, 1, 1), Voxel::STONE_VOXEL);
+        let skipping = BlockMapping {
+            unknown: None,
+            ..default()
+        }
+        .with(3, Voxel::A1_VOXEL);
+        let (voxels, _) = parse_schematic(&bytes, &skipping).unwrap();
+        assert_eq!(
+            voxels[(1 + size.x * (1 + size.y)) as usize],
+            Voxel::EMPTY_VOXEL
+        );
+        assert_eq!(voxels[(size.x * size.y) as usize], Voxel::A1_VOXEL);
+
+        // Structures larger than a chunk get a grid large enough to hold them.
+        let bytes = schematic([30, 1, 5], &[1; 150]);
+        let (voxels, size) = parse_schematic(&bytes, &BlockMapping::default()).unwrap();
+        assert_eq!(ChunkGrid::chunks_to_fit(size), 2);
+        let mut grid = ChunkGrid::new(ChunkGrid::chunks_to_fit(size));
+        place_model(&mut grid, &voxels, size);
+        let placed = (0..grid.world_size().x)
+            .filter(|&x| grid.voxel(UVec3::new(x, 19, 20)) == Voxel::STONE_VOXEL)
+            .count();
+        assert_eq!(placed, 30);
+
+        assert!(parse_schematic(&schematic([2, 2, 2], &[1; 3]), &BlockMapping::default()).is_err());
+        // A negative width, and a size whose block count doesn't fit into 32 bits, are both errors rather than panics.
+        let negative =
+            parse_schematic(&schematic([u16::MAX, 1, 1], &[1]), &BlockMapping::default());
+        assert_eq!(negative, Err("negative Width -1".to_string()));
+        let huge = schematic([i16::MAX as u16; 3], &[1; 8]);
+        assert!(parse_schematic(&huge, &BlockMapping::default()).is_err());
+    }
+}
@@ -0,0 +1,272 @@
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use bevy::render::camera::RenderTarget;
+use bevy::render::render_asset::RenderAssets;
+use bevy::render::render_resource::{
+    Buffer, BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Extent3d, ImageCopyBuffer,
+    ImageDataLayout, MapMode, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+};
+use bevy::render::renderer::{RenderDevice, RenderQueue};
+use bevy::render::{Extract, RenderApp, RenderStage};
+use bevy::tasks::IoTaskPool;
+
+use crate::camera::CameraRotationState;
+use crate::input::{Action, KeyBindings};
+
+/// Where the screenshots end up, relative to the working directory.
+const SCREENSHOT_DIR: &str = "screenshots";
+/// The screenshot image format, 4 bytes per pixel.
+const FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+/// wgpu wants every row of a texture copy to start on a multiple of this many bytes.
+const COPY_BYTES_PER_ROW_ALIGNMENT: u32 = 256;
+
+/// Press F12 to save what the camera sees to `screenshots/screenshot-<unix time in ms>.png`.
+///
+/// Bevy has no screenshot API yet, so this renders one frame of a second camera into an image and reads that back
+/// from the GPU. The readback and the PNG encoding happen in the background, the app never waits for them.
+pub struct ScreenshotPlugin;
+
+impl Plugin for ScreenshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<KeyBindings>()
+            .add_system(take_screenshot_system)
+            .add_system(finish_screenshots_system);
+        if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .init_resource::<ScreenshotQueue>()
+                .add_system_to_stage(RenderStage::Extract, extract_screenshots)
+                .add_system_to_stage(RenderStage::Cleanup, copy_screenshots)
+                .add_system_to_stage(RenderStage::Cleanup, save_screenshots);
+        }
+    }
+}
+
+/// A camera that renders into `image` until its frame has been copied.
+#[derive(Component, Clone)]
+struct Screenshot {
+    image: Handle<Image>,
+    path: PathBuf,
+    size: UVec2,
+    copied: Arc<AtomicBool>,
+}
+
+/// Spawns a copy of the main camera that renders into an image the size of the window.
+fn take_screenshot_system(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    windows: Res<Windows>,
+    camera_state: Option<Res<CameraRotationState>>,
+    cameras: Query<(&GlobalTransform, &Projection)>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    if !bindings.just_pressed(&keys, Action::Screenshot) {
+        return;
+    }
+    let (Some(camera_state), Some(window)) = (camera_state, windows.get_primary()) else {
+        return;
+    };
+    let Ok((transform, projection)) = cameras.get(camera_state.camera) else {
+        return;
+    };
+    let size = UVec2::new(window.physical_width(), window.physical_height());
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let path = PathBuf::from(SCREENSHOT_DIR).join(format!("screenshot-{millis}.png"));
+
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: Some("screenshot"),
+            size: Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+            dimension: TextureDimension::D2,
+            format: FORMAT,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_DST
+                | TextureUsages::COPY_SRC
+                | TextureUsages::RENDER_ATTACHMENT,
+        },
+        ..default()
+    };
+    image.resize(image.texture_descriptor.size);
+    let image = images.add(image);
+
+    commands.spawn((
+        Camera3dBundle {
+            camera: Camera {
+                target: RenderTarget::Image(image.clone()),
+                ..default()
+            },
+            transform: transform.compute_transform(),
+            projection: projection.clone(),
+            ..default()
+        },
+        // The screenshot is of the world, without the crosshair.
+        UiCameraConfig { show_ui: false },
+        Screenshot {
+            image,
+            path,
+            size,
+            copied: default(),
+        },
+    ));
+}
+
+/// Removes the screenshot cameras and their images once the render world has copied them.
+fn finish_screenshots_system(
+    mut commands: Commands,
+    screenshots: Query<(Entity, &Screenshot)>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    for (entity, screenshot) in &screenshots {
+        if screenshot.copied.load(Ordering::Acquire) {
+            commands.entity(entity).despawn();
+            images.remove(&screenshot.image);
+        }
+    }
+}
+
+/// A copy waiting for its buffer to be mapped.
+struct Readback {
+    buffer: Buffer,
+    path: PathBuf,
+    size: UVec2,
+    padded_bytes_per_row: u32,
+    mapped: Arc<AtomicBool>,
+}
+
+#[derive(Resource, Default)]
+struct ScreenshotQueue {
+    /// Screenshots whose camera renders this frame.
+    requested: Vec<Screenshot>,
+    readbacks: Vec<Readback>,
+}
+
+fn extract_screenshots(
+    mut queue: ResMut<ScreenshotQueue>,
+    screenshots: Extract<Query<&Screenshot>>,
+) {
+    queue.requested = screenshots
+        .iter()
+        .filter(|screenshot| !screenshot.copied.load(Ordering::Acquire))
+        .cloned()
+        .collect();
+}
+
+/// Copies the rendered images into buffers after the frame has been drawn, and starts mapping them.
+fn copy_screenshots(
+    mut queue: ResMut<ScreenshotQueue>,
+    images: Res<RenderAssets<Image>>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    let queue = &mut *queue;
+    for screenshot in queue.requested.drain(..) {
+        // The image is only on the GPU from the frame after it was added, so the camera gets another go.
+        let Some(gpu_image) = images.get(&screenshot.image) else {
+            continue;
+        };
+        let padded_bytes_per_row =
+            (screenshot.size.x * 4).next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = render_device.create_buffer(&BufferDescriptor {
+            label: Some("screenshot_readback"),
+            size: (padded_bytes_per_row * screenshot.size.y) as u64,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = render_device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("screenshot_copy"),
+        });
+        encoder.copy_texture_to_buffer(
+            gpu_image.texture.as_image_copy(),
+            ImageCopyBuffer {
+                buffer: &buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(padded_bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            Extent3d {
+                width: screenshot.size.x,
+                height: screenshot.size.y,
+                depth_or_array_layers: 1,
+            },
+        );
+        render_queue.submit([encoder.finish()]);
+        screenshot.copied.store(true, Ordering::Release);
+
+        // The callback runs when wgpu gets polled, which happens on a later submit.
+        let mapped = Arc::new(AtomicBool::new(false));
+        let on_mapped = mapped.clone();
+        buffer
+            .slice(..)
+            .map_async(MapMode::Read, move |result| match result {
+                Ok(()) => on_mapped.store(true, Ordering::Release),
+                Err(err) => error!("couldn't read the screenshot back: {err}"),
+            });
+        queue.readbacks.push(Readback {
+            buffer,
+            path: screenshot.path,
+            size: screenshot.size,
+            padded_bytes_per_row,
+            mapped,
+        });
+    }
+}
+
+/// Takes the mapped buffers apart and writes the PNGs on the IO task pool.
+fn save_screenshots(mut queue: ResMut<ScreenshotQueue>) {
+    queue.readbacks.retain(|readback| {
+        if !readback.mapped.load(Ordering::Acquire) {
+            return true;
+        }
+        let row_bytes = readback.size.x as usize * 4;
+        let data = readback
+            .buffer
+            .slice(..)
+            .get_mapped_range()
+            .chunks_exact(readback.padded_bytes_per_row as usize)
+            .flat_map(|row| &row[..row_bytes])
+            .copied()
+            .collect();
+        readback.buffer.unmap();
+
+        let image = Image::new(
+            Extent3d {
+                width: readback.size.x,
+                height: readback.size.y,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            FORMAT,
+        );
+        let path = readback.path.clone();
+        IoTaskPool::get()
+            .spawn(async move {
+                let saved = std::fs::create_dir_all(SCREENSHOT_DIR)
+                    .map_err(|err| err.to_string())
+                    .and_then(|()| image.try_into_dynamic().map_err(|err| err.to_string()))
+                    .and_then(|image| image.save(&path).map_err(|err| err.to_string()));
+                match saved {
+                    Ok(()) => info!("saved a screenshot to {}", path.display()),
+                    Err(err) => error!("couldn't save {}: {err}", path.display()),
+                }
+            })
+            .detach();
+        false
+    });
+}
@@ -0,0 +1,282 @@
+use bevy::prelude::*;
+use bevy::render::render_resource::FilterMode;
+use bevy_inspector_egui::bevy_egui::EguiContext;
+use bevy_inspector_egui::egui;
+
+use crate::ao::{AoInterpolation, AoQuality, AoSettings};
+use crate::atlas::{SamplerSettings, TextureLayout};
+use crate::chunk::{ChunkGrid, VoxelScale};
+use crate::input::{Action, KeyBindings};
+use crate::material::{AlphaCutoff, GridLines, Translucency};
+use crate::meshing::{ExplodeFactor, MeshingMode, RenderMode};
+use crate::normals::NormalMode;
+use crate::sun::DayCycle;
+use crate::worldgen::{generate_world, RandomSeed, TerrainConfig, WorldGenerator};
+
+/// Writes `value` into `resource` only if it's different. Any write counts as a change, and most of these settings
+/// remesh the whole world when they change.
+fn set_if_changed<T: Resource + PartialEq>(resource: &mut ResMut<T>, value: T) {
+    if **resource != value {
+        **resource = value;
+    }
+}
+
+/// A combo box that picks one of `options`, labelled with their `Debug` names.
+fn pick<T: Copy + PartialEq + std::fmt::Debug>(
+    ui: &mut egui::Ui,
+    label: &str,
+    value: &mut T,
+    options: &[T],
+) {
+    egui::ComboBox::from_label(label)
+        .selected_text(format!("{value:?}"))
+        .show_ui(ui, |ui| {
+            for &option in options {
+                ui.selectable_value(value, option, format!("{option:?}"));
+            }
+        });
+}
+
+/// A window in the top right corner with the settings that are worth tweaking while the world is running, grouped
+/// into AO, meshing, texturing, lighting and world generation, followed by the [`KeyBindings`]. Every control edits the
+/// same resource its key does, so the world is remeshed or regenerated just like it is for the keys.
+///
+/// Some of the resources are grouped into tuples, since Bevy systems take at most 16 parameters.
+#[allow(clippy::too_many_arguments)]
+pub fn settings_panel_system(
+    mut egui_context: ResMut<EguiContext>,
+    mut ao_settings: ResMut<AoSettings>,
+    mut meshing_mode: ResMut<MeshingMode>,
+    mut normal_mode: ResMut<NormalMode>,
+    mut render_mode: ResMut<RenderMode>,
+    mut explode: ResMut<ExplodeFactor>,
+    mut voxel_scale: ResMut<VoxelScale>,
+    mut sampler: ResMut<SamplerSettings>,
+    mut texture_layout: ResMut<TextureLayout>,
+    (mut translucency, mut alpha_cutoff, mut grid_lines): (
+        ResMut<Translucency>,
+        ResMut<AlphaCutoff>,
+        ResMut<GridLines>,
+    ),
+    (mut generator, mut seed, mut terrain_config): (
+        ResMut<WorldGenerator>,
+        ResMut<RandomSeed>,
+        ResMut<TerrainConfig>,
+    ),
+    key_bindings: Res<KeyBindings>,
+    mut grid: ResMut<ChunkGrid>,
+    mut cycle: ResMut<DayCycle>,
+) {
+    let mut ao = ao_settings.clone();
+    let mut meshing = (
+        *meshing_mode,
+        *normal_mode,
+        *render_mode,
+        *explode,
+        *voxel_scale,
+    );
+    let mut texturing = (
+        *sampler,
+        *texture_layout,
+        *translucency,
+        *alpha_cutoff,
+        *grid_lines,
+    );
+    let (mut world_generator, mut world_seed) = (*generator, *seed);
+    let mut cave_threshold = terrain_config.cave_threshold;
+    let mut regenerate = false;
+
+    egui::Window::new("Settings")
+        .anchor(egui::Align2::RIGHT_TOP, [-8.0, 8.0])
+        .resizable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            egui::CollapsingHeader::new("Ambient occlusion")
+                .default_open(true)
+                .show(ui, |ui| {
+                    pick(
+                        ui,
+                        "quality",
+                        &mut ao.quality,
+                        &[
+                            AoQuality::Full,
+                            AoQuality::EdgesOnly,
+                            AoQuality::Off,
+                            AoQuality::Raymarched,
+                        ],
+                    );
+                    pick(
+                        ui,
+                        "interpolation",
+                        &mut ao.interpolation,
+                        &[AoInterpolation::Step, AoInterpolation::Smooth],
+                    );
+                    ui.add(egui::Slider::new(&mut ao.strength, 0.0..=1.0).text("strength"));
+                    ui.checkbox(&mut ao.flip_quads, "flip quads");
+                    ui.checkbox(&mut ao.merge_respecting_ao, "merge respecting AO");
+                    // Switching between 4 and 8 levels of the same ramp shows how much the extra levels smooth out.
+                    let mut levels = usize::from(ao.levels());
+                    pick(ui, "levels", &mut levels, &[4, 8, 16]);
+                    if levels != usize::from(ao.levels()) {
+                        ao.stops = ao.resampled(levels);
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("stops");
+                        for stop in &mut ao.stops {
+                            ui.color_edit_button_rgba_unmultiplied(stop);
+                        }
+                        if ui.small_button("+").clicked() {
+                            let last = *ao.stops.last().unwrap();
+                            ao.stops.push(last);
+                        }
+                        // There has to be at least one stop.
+                        if ao.stops.len() > 1 && ui.small_button("-").clicked() {
+                            ao.stops.pop();
+                        }
+                    });
+                });
+
+            egui::CollapsingHeader::new("Meshing")
+                .default_open(true)
+                .show(ui, |ui| {
+                    let (mode, normals, render, explode, scale) = &mut meshing;
+                    pick(
+                        ui,
+                        "meshing",
+                        mode,
+                        &[MeshingMode::Simple, MeshingMode::Greedy],
+                    );
+                    pick(
+                        ui,
+                        "normals",
+                        normals,
+                        &[NormalMode::Flat, NormalMode::Smooth],
+                    );
+                    pick(
+                        ui,
+                        "render mode",
+                        render,
+                        &[
+                            RenderMode::Textured,
+                            RenderMode::SolidColor,
+                            RenderMode::MergeSize,
+                        ],
+                    );
+                    ui.add(egui::Slider::new(&mut explode.0, 0.0..=2.0).text("explode"));
+                    ui.add(egui::Slider::new(&mut scale.0, 0.1..=2.0).text("voxel scale"));
+                });
+
+            egui::CollapsingHeader::new("Texturing")
+                .default_open(true)
+                .show(ui, |ui| {
+                    let (sampler, layout, translucency, cutoff, lines) = &mut texturing;
+                    pick(
+                        ui,
+                        "filter",
+                        &mut sampler.filter,
+                        &[FilterMode::Nearest, FilterMode::Linear],
+                    );
+                    pick(
+                        ui,
+                        "layout",
+                        layout,
+                        &[TextureLayout::Atlas, TextureLayout::Array],
+                    );
+                    ui.add(egui::Slider::new(&mut translucency.0, 0.0..=1.0).text("translucency"));
+                    ui.add(egui::Slider::new(&mut cutoff.0, 0.0..=1.0).text("alpha cutoff"));
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut lines.enabled, "grid lines");
+                        let mut color = lines.color.as_rgba_f32();
+                        if ui.color_edit_button_rgba_unmultiplied(&mut color).changed() {
+                            lines.color = Color::from(color);
+                        }
+                    });
+                    ui.add(
+                        egui::Slider::new(&mut lines.thickness, 0.0..=0.25).text("line thickness"),
+                    );
+                });
+
+            // The day cycle writes the lights every frame anyway, so these edit it in place.
+            egui::CollapsingHeader::new("Lighting")
+                .default_open(true)
+                .show(ui, |ui| {
+                    ui.checkbox(&mut cycle.paused, "pause the day");
+                    ui.add(
+                        egui::Slider::new(&mut cycle.time_of_day, 0.0..=1.0).text("time of day"),
+                    );
+                    ui.add(
+                        egui::Slider::new(&mut cycle.noon_illuminance, 0.0..=100000.0)
+                            .logarithmic(true)
+                            .text("sun illuminance"),
+                    );
+                    ui.add(
+                        egui::Slider::new(&mut cycle.day_ambient, 0.0..=2.0).text("day ambient"),
+                    );
+                    ui.add(
+                        egui::Slider::new(&mut cycle.night_ambient, 0.0..=2.0)
+                            .text("night ambient"),
+                    );
+                });
+
+            egui::CollapsingHeader::new("World")
+                .default_open(true)
+                .show(ui, |ui| {
+                    pick(
+                        ui,
+                        "generator",
+                        &mut world_generator,
+                        &[WorldGenerator::Random, WorldGenerator::Terrain],
+                    );
+                    ui.horizontal(|ui| {
+                        ui.label("seed");
+                        ui.add(egui::DragValue::new(&mut world_seed.0));
+                        if ui.button("New seed").clicked() {
+                            world_seed = RandomSeed(rand::random());
+                            regenerate = true;
+                        }
+                    });
+                    ui.add(
+                        egui::Slider::new(&mut cave_threshold, 0.0..=1.0).text("cave threshold"),
+                    );
+                    regenerate |= ui.button("Regenerate").clicked();
+                });
+
+            egui::CollapsingHeader::new("Key bindings").show(ui, |ui| {
+                egui::Grid::new("key bindings")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for action in Action::ALL {
+                            ui.label(format!("{action:?}"));
+                            ui.label(key_bindings.describe(action));
+                            ui.end_row();
+                        }
+                    });
+            });
+        });
+
+    set_if_changed(&mut ao_settings, ao);
+    let (mode, normals, render, explode_factor, scale) = meshing;
+    set_if_changed(&mut meshing_mode, mode);
+    set_if_changed(&mut normal_mode, normals);
+    set_if_changed(&mut render_mode, render);
+    set_if_changed(&mut explode, explode_factor);
+    set_if_changed(&mut voxel_scale, scale);
+    let (sampler_settings, layout, alpha, cutoff, lines) = texturing;
+    set_if_changed(&mut sampler, sampler_settings);
+    set_if_changed(&mut texture_layout, layout);
+    set_if_changed(&mut translucency, alpha);
+    set_if_changed(&mut alpha_cutoff, cutoff);
+    set_if_changed(&mut grid_lines, lines);
+    set_if_changed(&mut seed, world_seed);
+    // A changed config regenerates the world by itself, see `regenerate_world_system`.
+    if cave_threshold != terrain_config.cave_threshold {
+        terrain_config.cave_threshold = cave_threshold;
+    }
+    if world_generator != *generator {
+        *generator = world_generator;
+        regenerate = true;
+    }
+    if regenerate {
+        info!("random fill seed: {}", seed.0);
+        generate_world(&mut grid, *generator, &terrain_config, *seed);
+    }
+}
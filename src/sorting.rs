@@ -0,0 +1,96 @@
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, VertexAttributeValues};
+
+use crate::camera::CameraRotationState;
+use crate::chunk::ChunkGrid;
+
+/// Reorders the quads of a chunk mesh so the ones furthest from `viewer` come first. Bevy sorts translucent entities
+/// against each other but draws each mesh in index order, so without this the blended faces of a chunk cover each other
+/// in whatever order the mesher produced them. `viewer` is in the chunk's local space.
+pub fn sort_quads_back_to_front(mesh: &mut Mesh, viewer: Vec3) {
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return;
+    };
+    let positions = positions.clone();
+    let Some(Indices::U32(indices)) = mesh.indices_mut() else {
+        return;
+    };
+
+    // Every quad is 6 indices into its own 4 vertices.
+    let mut quads: Vec<(f32, [u32; 6])> = indices
+        .chunks_exact(6)
+        .map(|quad| {
+            let quad: [u32; 6] = quad.try_into().unwrap();
+            let center = quad
+                .iter()
+                .map(|&i| Vec3::from(positions[i as usize]))
+                .sum::<Vec3>()
+                / 6.0;
+            (center.distance_squared(viewer), quad)
+        })
+        .collect();
+    quads.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    indices.clear();
+    indices.extend(quads.into_iter().flat_map(|(_, quad)| quad));
+}
+
+/// Where the camera is, in world space.
+pub fn viewer_position(
+    camera: &CameraRotationState,
+    transforms: &Query<&GlobalTransform>,
+) -> Option<Vec3> {
+    transforms
+        .get(camera.camera)
+        .ok()
+        .map(GlobalTransform::translation)
+}
+
+/// Sorts every translucent chunk mesh again once the camera moves into another voxel. Moving less than that rarely
+/// changes the order, and it keeps the meshes from being uploaded every frame.
+pub fn resort_translucent_system(
+    camera: Res<CameraRotationState>,
+    transforms: Query<&GlobalTransform>,
+    grid: Res<ChunkGrid>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut last_cell: Local<Option<IVec3>>,
+) {
+    let Some(viewer) = viewer_position(&camera, &transforms) else {
+        return;
+    };
+    let cell = (viewer / grid.voxel_scale()).floor().as_ivec3();
+    if *last_cell == Some(cell) {
+        return;
+    }
+    *last_cell = Some(cell);
+
+    for (coord, chunk) in grid.chunks() {
+        if let Some(mesh) = meshes.get_mut(&chunk.translucent_mesh) {
+            sort_quads_back_to_front(mesh, viewer - grid.chunk_translation(coord));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::render::render_resource::PrimitiveTopology;
+
+    #[test]
+    fn far_quads_come_first() {
+        let quad_at = |z: f32| [[0.0, 0.0, z], [1.0, 0.0, z], [0.0, 1.0, z], [1.0, 1.0, z]];
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            [quad_at(0.0), quad_at(10.0)].concat(),
+        );
+        mesh.set_indices(Some(Indices::U32(vec![0, 1, 2, 1, 3, 2, 4, 5, 6, 5, 7, 6])));
+
+        sort_quads_back_to_front(&mut mesh, Vec3::new(0.0, 0.0, -5.0));
+        let Some(Indices::U32(indices)) = mesh.indices() else {
+            panic!("the indices should still be u32");
+        };
+        assert_eq!(indices, &[4, 5, 6, 5, 7, 6, 0, 1, 2, 1, 3, 2]);
+    }
+}
@@ -0,0 +1,141 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+
+use crate::input::{Action, KeyBindings};
+
+/// The day/night cycle that moves the [`Sun`] and dims the [`AmbientLight`]. Press K to pause it. The lights
+/// follow every change on the next frame, so the brightness can be tuned in the settings window while looking at the
+/// shading.
+#[derive(Resource, Clone, Debug)]
+pub struct DayCycle {
+    /// How long a whole day and night take, in seconds.
+    pub day_length: f32,
+    pub paused: bool,
+    /// How far through the day it is, from 0 to 1. The sun rises at 0, is highest at 0.25 and sets at 0.5.
+    pub time_of_day: f32,
+    /// The illuminance of the sun at noon, in lux. It fades to nothing towards sunset.
+    pub noon_illuminance: f32,
+    /// The [`AmbientLight`] brightness at noon and at night, blended by the [`DayCycle::daylight`].
+    pub day_ambient: f32,
+    pub night_ambient: f32,
+}
+
+impl Default for DayCycle {
+    fn default() -> Self {
+        Self {
+            day_length: 120.0,
+            paused: false,
+            time_of_day: 0.1,
+            noon_illuminance: 10000.0,
+            day_ambient: 0.4,
+            night_ambient: 0.05,
+        }
+    }
+}
+
+impl DayCycle {
+    /// The direction the sunlight comes from, pointing away from the world. The sun crosses the sky along X, tilted a
+    /// little towards Z so it's never straight overhead.
+    pub fn sun_direction(&self) -> Vec3 {
+        let angle = self.time_of_day * TAU;
+        Vec3::new(angle.cos(), angle.sin(), 0.3).normalize()
+    }
+
+    /// How much daylight there is, from 0 at night to 1 at noon.
+    pub fn daylight(&self) -> f32 {
+        self.sun_direction().y.max(0.0)
+    }
+
+    /// The brightness of the [`AmbientLight`] at the current time of day.
+    pub fn ambient_brightness(&self) -> f32 {
+        self.night_ambient + (self.day_ambient - self.night_ambient) * self.daylight()
+    }
+}
+
+/// Marks the directional light moved by [`sun_system`].
+#[derive(Component)]
+pub struct Sun;
+
+/// The sun, with a shadow volume big enough for the whole grid.
+pub fn sun_bundle() -> (DirectionalLightBundle, Sun) {
+    let half_size = 60.0;
+    (
+        DirectionalLightBundle {
+            directional_light: DirectionalLight {
+                shadows_enabled: true,
+                shadow_projection: OrthographicProjection {
+                    left: -half_size,
+                    right: half_size,
+                    bottom: -half_size,
+                    top: half_size,
+                    near: -4.0 * half_size,
+                    far: 4.0 * half_size,
+                    ..default()
+                },
+                ..default()
+            },
+            ..default()
+        },
+        Sun,
+    )
+}
+
+pub fn toggle_day_cycle(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut cycle: ResMut<DayCycle>,
+) {
+    if bindings.just_pressed(&keys, Action::ToggleDayCycle) {
+        cycle.paused = !cycle.paused;
+        info!("day cycle paused: {}", cycle.paused);
+    }
+}
+
+/// Advances the time of day and points the sun and tints the ambient light to match. Sunrise and sunset are orange,
+/// and at night only a dim blue ambient light is left.
+pub fn sun_system(
+    time: Res<Time>,
+    mut cycle: ResMut<DayCycle>,
+    mut ambient: ResMut<AmbientLight>,
+    mut suns: Query<(&mut Transform, &mut DirectionalLight), With<Sun>>,
+) {
+    if !cycle.paused {
+        cycle.time_of_day = (cycle.time_of_day + time.delta_seconds() / cycle.day_length).fract();
+    }
+    let daylight = cycle.daylight();
+    let direction = cycle.sun_direction();
+    for (mut transform, mut light) in &mut suns {
+        *transform = Transform::IDENTITY.looking_at(-direction, Vec3::Y);
+        light.illuminance = cycle.noon_illuminance * daylight;
+        light.color = lerp_color(Color::rgb(1.0, 0.5, 0.2), Color::WHITE, daylight.sqrt());
+    }
+    ambient.brightness = cycle.ambient_brightness();
+    ambient.color = lerp_color(Color::rgb(0.3, 0.4, 0.8), Color::WHITE, daylight);
+}
+
+fn lerp_color(from: Color, to: Color, t: f32) -> Color {
+    let from = Vec4::from(from.as_rgba_f32());
+    let to = Vec4::from(to.as_rgba_f32());
+    let [r, g, b, a] = from.lerp(to, t).to_array();
+    Color::rgba(r, g, b, a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ambient_light_follows_the_day() {
+        let mut cycle = DayCycle {
+            time_of_day: 0.25,
+            day_ambient: 0.8,
+            night_ambient: 0.1,
+            ..default()
+        };
+        assert!((cycle.ambient_brightness() - (0.1 + 0.7 * cycle.daylight())).abs() < 1e-6);
+        assert!(cycle.ambient_brightness() > 0.7);
+        cycle.time_of_day = 0.75;
+        assert_eq!(cycle.ambient_brightness(), 0.1);
+    }
+}
@@ -0,0 +1,150 @@
+use std::ops::{Index, IndexMut};
+
+use block_mesh::ndshape::ConstShape;
+
+use crate::chunk::ChunkShape;
+use crate::voxel::Voxel;
+
+/// The voxels of one padded chunk, laid out by the shape `S`. Coordinates include the padding, so they run from 0 to
+/// [`VoxelVolume::dims`] minus one, and the flat layout block-mesh wants stays behind [`VoxelVolume::as_slice`].
+///
+/// Chunks always use [`ChunkShape`], other shapes are there so the mesher can be checked against them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VoxelVolume<S = ChunkShape> {
+    voxels: Vec<Voxel>,
+    shape: S,
+}
+
+/// An empty chunk.
+impl Default for VoxelVolume {
+    fn default() -> Self {
+        Self::filled(ChunkShape {}, Voxel::EMPTY_VOXEL)
+    }
+}
+
+impl<S: ConstShape<3, Coord = u32>> VoxelVolume<S> {
+    /// A volume with every voxel set to `voxel`.
+    pub fn filled(shape: S, voxel: Voxel) -> Self {
+        Self {
+            voxels: vec![voxel; S::USIZE],
+            shape,
+        }
+    }
+
+    /// Takes over `voxels`, which have to be laid out by `S`. Panics if there are too few or too many of them.
+    pub fn from_voxels(shape: S, voxels: Vec<Voxel>) -> Self {
+        assert_eq!(
+            voxels.len(),
+            S::USIZE,
+            "wrong number of voxels for the shape"
+        );
+        Self { voxels, shape }
+    }
+
+    /// The shape block-mesh needs along with [`VoxelVolume::as_slice`].
+    pub fn shape(&self) -> &S {
+        &self.shape
+    }
+
+    /// Size of the volume along each axis, padding included.
+    pub fn dims(&self) -> [u32; 3] {
+        S::ARRAY
+    }
+
+    /// The first and last voxel handed to the mesher, which cover the whole volume. The padding voxels themselves never
+    /// get faces, they only hide or show the faces next to them.
+    pub fn mesh_bounds(&self) -> ([u32; 3], [u32; 3]) {
+        ([0; 3], S::ARRAY.map(|extent| extent - 1))
+    }
+
+    /// Whether `x`, `y`, `z` is inside the volume, padding included.
+    pub fn contains(&self, x: u32, y: u32, z: u32) -> bool {
+        let [dx, dy, dz] = self.dims();
+        x < dx && y < dy && z < dz
+    }
+
+    /// The voxel at `x`, `y`, `z`, or `None` outside the volume.
+    pub fn get(&self, x: u32, y: u32, z: u32) -> Option<Voxel> {
+        self.contains(x, y, z)
+            .then(|| self.voxels[S::linearize([x, y, z]) as usize])
+    }
+
+    /// Sets the voxel at `x`, `y`, `z`. Panics outside the volume, like indexing does.
+    pub fn set(&mut self, x: u32, y: u32, z: u32, voxel: Voxel) {
+        self[[x, y, z]] = voxel;
+    }
+
+    /// The voxels laid out by `S`, ready for the block-mesh algorithms.
+    pub fn as_slice(&self) -> &[Voxel] {
+        &self.voxels
+    }
+
+    /// Roughly how much memory the volume takes up, in bytes.
+    pub fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() + self.voxels.capacity() * std::mem::size_of::<Voxel>()
+    }
+
+    /// The coordinates of every voxel that isn't [`Voxel::EMPTY_VOXEL`], padding included.
+    pub fn iter_filled(&self) -> impl Iterator<Item = [u32; 3]> + '_ {
+        self.voxels
+            .iter()
+            .zip(0..)
+            .filter(|(&voxel, _)| voxel != Voxel::EMPTY_VOXEL)
+            .map(|(_, i)| S::delinearize(i))
+    }
+
+    fn index_of(&self, [x, y, z]: [u32; 3]) -> usize {
+        assert!(
+            self.contains(x, y, z),
+            "voxel [{x}, {y}, {z}] is outside a volume of {:?}",
+            self.dims()
+        );
+        S::linearize([x, y, z]) as usize
+    }
+}
+
+impl<S: ConstShape<3, Coord = u32>> Index<[u32; 3]> for VoxelVolume<S> {
+    type Output = Voxel;
+
+    fn index(&self, position: [u32; 3]) -> &Voxel {
+        &self.voxels[self.index_of(position)]
+    }
+}
+
+impl<S: ConstShape<3, Coord = u32>> IndexMut<[u32; 3]> for VoxelVolume<S> {
+    fn index_mut(&mut self, position: [u32; 3]) -> &mut Voxel {
+        let index = self.index_of(position);
+        &mut self.voxels[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn access_is_bounds_checked() {
+        let mut volume = VoxelVolume::default();
+        volume.set(1, 2, 3, Voxel::STONE_VOXEL);
+        assert_eq!(volume.get(1, 2, 3), Some(Voxel::STONE_VOXEL));
+        assert_eq!(volume[[1, 2, 3]], Voxel::STONE_VOXEL);
+        assert_eq!(volume.get(3, 2, 1), Some(Voxel::EMPTY_VOXEL));
+
+        let [x, y, z] = volume.dims();
+        assert_eq!(volume.get(x, 0, 0), None);
+        assert_eq!(volume.get(0, y, 0), None);
+        assert_eq!(volume.get(0, 0, z), None);
+        assert!(std::panic::catch_unwind(move || volume.set(0, 0, z, Voxel::STONE_VOXEL)).is_err());
+    }
+
+    #[test]
+    fn iter_filled_skips_empty_voxels() {
+        let mut volume = VoxelVolume::default();
+        volume.set(0, 0, 0, Voxel::STONE_VOXEL);
+        volume.set(4, 5, 6, Voxel::DIRT_VOXEL);
+        assert_eq!(
+            volume.iter_filled().collect::<Vec<_>>(),
+            [[0, 0, 0], [4, 5, 6]]
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use bevy::prelude::*;
+use block_mesh::VoxelVisibility;
+
+use crate::chunk::ChunkGrid;
+use crate::input::{Action, KeyBindings};
+use crate::voxel::{Voxel, VoxelRegistry, VoxelShape, VoxelType};
+
+/// A model from a MagicaVoxel file that is shown instead of a generated world, see [`crate::scene::DemoScene::Vox`].
+#[derive(Resource, Clone, Debug)]
+pub struct VoxModel {
+    pub voxels: Vec<Voxel>,
+    pub size: UVec3,
+}
+
+impl VoxModel {
+    /// The model turned a quarter turn around the Y axis, so its X axis runs along Z afterwards and its Z axis against
+    /// X. Four turns give back the model.
+    pub fn rotate_y_90(&self) -> Self {
+        let size = UVec3::new(self.size.z, self.size.y, self.size.x);
+        self.remap(size, |[x, y, z]| [self.size.z - 1 - z, y, x])
+    }
+
+    /// The model mirrored along X.
+    pub fn mirror_x(&self) -> Self {
+        self.remap(self.size, |[x, y, z]| [self.size.x - 1 - x, y, z])
+    }
+
+    /// The model mirrored along Z.
+    pub fn mirror_z(&self) -> Self {
+        self.remap(self.size, |[x, y, z]| [x, y, self.size.z - 1 - z])
+    }
+
+    /// A model of `size` with every voxel moved from its position to `to(position)`, ids and all.
+    fn remap(&self, size: UVec3, to: impl Fn([u32; 3]) -> [u32; 3]) -> Self {
+        let mut voxels = vec![Voxel::EMPTY_VOXEL; self.voxels.len()];
+        for z in 0..self.size.z {
+            for y in 0..self.size.y {
+                for x in 0..self.size.x {
+                    let [tx, ty, tz] = to([x, y, z]);
+                    voxels[(tx + size.x * (ty + size.y * tz)) as usize] =
+                        self.voxels[(x + self.size.x * (y + self.size.y * z)) as usize];
+                }
+            }
+        }
+        Self { voxels, size }
+    }
+}
+
+/// Reads the first model of a `.vox` file into a dense array along with its size.
+///
+/// The voxels are indexed `x + size.x * (y + size.y * z)`. MagicaVoxel is Z up, so its Y and Z axes are swapped to
+/// match Bevy. Palette indices map to the voxel id with the same number, which leaves id 0 for empty voxels. The
+/// 256th color has no id of its own, see [`palette_voxel`].
+pub fn load_vox(path: &str) -> Result<(Vec<Voxel>, UVec3), String> {
+    let data = dot_vox::load(path).map_err(|err| format!("failed to load {path}: {err}"))?;
+    let model = data
+        .models
+        .first()
+        .ok_or_else(|| format!("{path} doesn't contain any models"))?;
+
+    let size = UVec3::new(model.size.x, model.size.z, model.size.y);
+    let mut voxels = vec![Voxel::EMPTY_VOXEL; (size.x * size.y * size.z) as usize];
+    let mut clamped = 0;
+    for voxel in &model.voxels {
+        let [x, y, z] = [voxel.x, voxel.z, voxel.y].map(u32::from);
+        clamped += usize::from(voxel.i == u8::MAX);
+        voxels[(x + size.x * (y + size.y * z)) as usize] = palette_voxel(voxel.i);
+    }
+    if clamped > 0 {
+        warn!(
+            "{clamped} voxels of {path} use the last palette color, they get the same id as the color before it"
+        );
+    }
+    Ok((voxels, size))
+}
+
+/// The voxel id of a palette index as dot_vox stores it, counting from 0 while the file counts from 1. Ids are a
+/// byte with 0 kept for empty voxels, so the last of the 256 colors is clamped onto id 255 along with the one before.
+pub fn palette_voxel(index: u8) -> Voxel {
+    Voxel(index.saturating_add(1))
+}
+
+/// Clears the grid and puts a model from [`load_vox`] in its middle. Anything that doesn't fit is cut off.
+pub fn place_model(grid: &mut ChunkGrid, voxels: &[Voxel], size: UVec3) {
+    let world_size = grid.world_size();
+    for z in 0..world_size.z {
+        for y in 0..world_size.y {
+            for x in 0..world_size.x {
+                grid.set_voxel(UVec3::new(x, y, z), Voxel::EMPTY_VOXEL);
+            }
+        }
+    }
+
+    let offset = (world_size.as_ivec3() - size.as_ivec3()) / 2;
+    for z in 0..size.z {
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let voxel = voxels[(x + size.x * (y + size.y * z)) as usize];
+                let world = offset + UVec3::new(x, y, z).as_ivec3();
+                if voxel != Voxel::EMPTY_VOXEL && grid.contains(world) {
+                    grid.set_voxel(world.as_uvec3(), voxel);
+                }
+            }
+        }
+    }
+}
+
+/// U turns the loaded [`VoxModel`] a quarter turn around Y, and shift+U mirrors it along X, placing it in the grid
+/// again, which remeshes it. The grid is as wide as it is deep, so a turned model still fits. Edits to the model are
+/// lost.
+pub fn orient_model_system(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    model: Option<ResMut<VoxModel>>,
+    mut grid: ResMut<ChunkGrid>,
+) {
+    let Some(mut model) = model else {
+        return;
+    };
+    *model = if bindings.just_pressed(&keys, Action::RotateModel) {
+        model.rotate_y_90()
+    } else if bindings.just_pressed(&keys, Action::MirrorModel) {
+        model.mirror_x()
+    } else {
+        return;
+    };
+    place_model(&mut grid, &model.voxels, model.size);
+}
+
+/// Registers every palette index used by the model that the registry doesn't know yet, drawn like stone.
+pub fn register_palette(registry: &mut VoxelRegistry, voxels: &[Voxel]) {
+    let (textures, color) = registry
+        .get(Voxel::STONE_VOXEL)
+        .map(|stone| (stone.textures, stone.color))
+        .expect("the default registry has stone");
+    let mut used = [false; 256];
+    for voxel in voxels {
+        used[voxel.0 as usize] = true;
+    }
+    for id in 1..=u8::MAX {
+        if used[id as usize] && registry.get(Voxel(id)).is_none() {
+            registry.register(
+                id,
+                VoxelType {
+                    name: format!("Palette {id}"),
+                    textures,
+                    color,
+                    visibility: VoxelVisibility::Opaque,
+                    emissive_strength: 0.0,
+                    flowing: false,
+                    shape: VoxelShape::Full,
+                },
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn palette_indices_skip_the_empty_id() {
+        assert_eq!(palette_voxel(0), Voxel::A1_VOXEL);
+        assert_eq!(palette_voxel(253), Voxel(254));
+        assert_eq!(palette_voxel(254), Voxel(255));
+        // There's no id left for the last color.
+        assert_eq!(palette_voxel(255), Voxel(255));
+    }
+
+    #[test]
+    fn small_models_are_centered() {
+        let mut grid = ChunkGrid::new(1);
+        grid.set_voxel(UVec3::ZERO, Voxel::STONE_VOXEL);
+        let size = UVec3::new(2, 1, 1);
+        place_model(&mut grid, &[Voxel::DIRT_VOXEL, Voxel::GRASS_VOXEL], size);
+
+        // The chunk is 20 voxels wide, which leaves 9 voxels on either side along X and 9 and 10 along Y and Z.
+        assert_eq!(grid.voxel(UVec3::ZERO), Voxel::EMPTY_VOXEL);
+        assert_eq!(grid.voxel(UVec3::new(9, 9, 9)), Voxel::DIRT_VOXEL);
+        assert_eq!(grid.voxel(UVec3::new(10, 9, 9)), Voxel::GRASS_VOXEL);
+        assert_eq!(grid.voxel(UVec3::new(11, 9, 9)), Voxel::EMPTY_VOXEL);
+    }
+
+    #[test]
+    fn models_turn_and_mirror_in_place() {
+        // An L of three voxels along X and one along Z from the corner, on a model of 3 by 2 by 2.
+        let size = UVec3::new(3, 2, 2);
+        let mut voxels = vec![Voxel::EMPTY_VOXEL; 12];
+        let at = |size: UVec3, [x, y, z]: [u32; 3]| (x + size.x * (y + size.y * z)) as usize;
+        for x in 0..3 {
+            voxels[at(size, [x, 0, 0])] = Voxel::STONE_VOXEL;
+        }
+        voxels[at(size, [0, 0, 1])] = Voxel::GRASS_VOXEL;
+        let model = VoxModel { voxels, size };
+
+        let turned = model.rotate_y_90();
+        assert_eq!(turned.size, UVec3::new(2, 2, 3));
+        // X runs along Z now, and Z against X.
+        for z in 0..3 {
+            assert_eq!(
+                turned.voxels[at(turned.size, [1, 0, z])],
+                Voxel::STONE_VOXEL
+            );
+        }
+        assert_eq!(
+            turned.voxels[at(turned.size, [0, 0, 0])],
+            Voxel::GRASS_VOXEL
+        );
+        let back = turned.rotate_y_90().rotate_y_90().rotate_y_90();
+        assert_eq!((back.voxels, back.size), (model.voxels.clone(), model.size));
+
+        let mirrored = model.mirror_x();
+        assert_eq!(mirrored.voxels[at(size, [2, 0, 1])], Voxel::GRASS_VOXEL);
+        assert_eq!(mirrored.mirror_x().voxels, model.voxels);
+        assert_eq!(
+            model.mirror_z().voxels[at(size, [0, 0, 0])],
+            Voxel::GRASS_VOXEL
+        );
+    }
+}
@@ -0,0 +1,719 @@
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::sync::Mutex;
+
+use bevy::prelude::*;
+use block_mesh::{MergeVoxel, Voxel as MeshableVoxel, VoxelVisibility};
+
+use crate::atlas::AtlasConfig;
+
+/// The integer types voxel ids can be stored as. Id 0 is always [`Voxel::EMPTY_VOXEL`].
+pub trait VoxelId: Copy + Eq + Hash + Debug + Send + Sync + 'static {
+    const EMPTY: Self;
+
+    /// Where the id's [`VoxelType`] goes in the [`VoxelRegistry`].
+    fn index(self) -> usize;
+}
+
+impl VoxelId for u8 {
+    const EMPTY: Self = 0;
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl VoxelId for u16 {
+    const EMPTY: Self = 0;
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl VoxelId for u32 {
+    const EMPTY: Self = 0;
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// A voxel id. The chunks store `u8` ids, which leaves room for 256 types; the registry and the block-mesh traits
+/// take wider ids as well, for content that needs more.
+#[derive(Copy, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct Voxel<T = u8>(pub T);
+
+impl Voxel {
+    pub const EMPTY_VOXEL: Voxel = Voxel(0);
+    pub const A1_VOXEL: Voxel = Voxel(1);
+    pub const A2_VOXEL: Voxel = Voxel(2);
+    pub const STONE_VOXEL: Voxel = Voxel(3);
+    pub const DIRT_VOXEL: Voxel = Voxel(4);
+    pub const GRASS_VOXEL: Voxel = Voxel(5);
+    pub const GLOWSTONE_VOXEL: Voxel = Voxel(6);
+    pub const WATER_VOXEL: Voxel = Voxel(7);
+    pub const SAND_VOXEL: Voxel = Voxel(8);
+    pub const SNOW_VOXEL: Voxel = Voxel(9);
+    pub const STONE_SLAB_VOXEL: Voxel = Voxel(10);
+}
+
+impl<T: VoxelId> Default for Voxel<T> {
+    fn default() -> Self {
+        Self(T::EMPTY)
+    }
+}
+
+/// One of the six faces of a voxel, named for the direction it faces with Y up and -Z as north.
+///
+/// The faces are numbered in the order of the faces of block-mesh's [`block_mesh::QuadCoordinateConfig`], which is
+/// the same for every [`crate::meshing::CoordinateSystem`], so the quad groups from
+/// [`crate::meshing::generate_quads`] belong to [`VoxelFace::from_index`] of their position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VoxelFace {
+    West,
+    Down,
+    North,
+    East,
+    Up,
+    South,
+}
+
+impl VoxelFace {
+    /// Every face, in the order of their indices.
+    pub const ALL: [Self; 6] = [
+        Self::West,
+        Self::Down,
+        Self::North,
+        Self::East,
+        Self::Up,
+        Self::South,
+    ];
+
+    /// The face of the quad group at `index`. Panics for indices past 5.
+    pub fn from_index(index: usize) -> Self {
+        Self::ALL[index]
+    }
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// The face pointing along `normal`, which has to be a unit vector along one of the axes.
+    pub fn from_normal(normal: IVec3) -> Option<Self> {
+        Self::ALL.into_iter().find(|face| face.normal() == normal)
+    }
+
+    /// The unit vector pointing out of the face, in grid space.
+    pub fn normal(self) -> IVec3 {
+        match self {
+            Self::West => -IVec3::X,
+            Self::Down => -IVec3::Y,
+            Self::North => -IVec3::Z,
+            Self::East => IVec3::X,
+            Self::Up => IVec3::Y,
+            Self::South => IVec3::Z,
+        }
+    }
+}
+
+/// The atlas tiles used for each side of a voxel. Tiles are given as a column and row, counting from 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FaceTextures {
+    pub top: [f32; 2],
+    pub bottom: [f32; 2],
+    /// Used for the four faces whose normal is horizontal.
+    pub sides: [f32; 2],
+}
+
+impl FaceTextures {
+    /// The same tile on every face.
+    pub const fn uniform(tile_offset: [f32; 2]) -> Self {
+        Self {
+            top: tile_offset,
+            bottom: tile_offset,
+            sides: tile_offset,
+        }
+    }
+
+    /// The tile shown on `face`.
+    pub fn for_face(&self, face: VoxelFace) -> [f32; 2] {
+        match face {
+            VoxelFace::Up => self.top,
+            VoxelFace::Down => self.bottom,
+            _ => self.sides,
+        }
+    }
+}
+
+/// The part of its cell a voxel fills.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VoxelShape {
+    /// The whole cube.
+    #[default]
+    Full,
+    /// The lower half of the cube, like a slab.
+    BottomSlab,
+}
+
+/// Everything the mesher needs to know about one kind of voxel.
+#[derive(Clone, Debug)]
+pub struct VoxelType {
+    pub name: String,
+    pub textures: FaceTextures,
+    /// Drawn instead of the textures in [`crate::meshing::RenderMode::SolidColor`]. The alpha only matters for
+    /// translucent voxels.
+    pub color: [f32; 4],
+    pub visibility: VoxelVisibility,
+    /// How strongly the faces glow, as a multiple of their own color. 0 for blocks that are only lit by the scene.
+    /// Glowing blocks light up the voxels around them too, see [`crate::light::emitted_light`].
+    pub emissive_strength: f32,
+    /// Scrolls the texture across the faces by [`crate::water::WaterSettings::scroll_speed`], like running water.
+    pub flowing: bool,
+    /// Voxels that don't fill their cell are meshed apart from the full cubes, see
+    /// [`crate::meshing::partial_voxel_quads`]. They still block rays, light and AO like a full voxel.
+    pub shape: VoxelShape,
+}
+
+/// What voxels without a registered type are meshed as, see [`VoxelRegistry::get_or_missing`]. It's opaque like
+/// [`VoxelRegistry::visibility`] says, textured like stone and magenta in the solid color mode so it stands out there.
+pub static MISSING_VOXEL_TYPE: VoxelType = VoxelType {
+    name: String::new(),
+    textures: FaceTextures::uniform([4.0, 4.0]),
+    color: [1.0, 0.0, 1.0, 1.0],
+    visibility: VoxelVisibility::Opaque,
+    emissive_strength: 0.0,
+    flowing: false,
+    shape: VoxelShape::Full,
+};
+
+/// Which faces between two touching translucent voxels get drawn, like the faces inside a block of glass. Between a
+/// translucent and an opaque voxel only the opaque side ever gets a face.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TransparencyMerge {
+    /// Every face between translucent voxels is drawn, so each voxel keeps its own outline.
+    Never,
+    /// Voxels of the same id merge into one volume, but different types, like water against glass, draw the faces
+    /// between them.
+    SameId,
+    /// Touching translucent voxels always merge and the faces between them are culled.
+    #[default]
+    Always,
+}
+
+/// Maps voxel ids to their [`VoxelType`], so new blocks can be added without touching the meshing code.
+#[derive(Resource, Debug)]
+pub struct VoxelRegistry {
+    types: Vec<Option<VoxelType>>,
+    pub transparency_merge: TransparencyMerge,
+    /// The missing ids that were already logged, so the mesher warns once for each of them instead of for every face.
+    warned_missing: Mutex<HashSet<usize>>,
+}
+
+impl Clone for VoxelRegistry {
+    fn clone(&self) -> Self {
+        Self {
+            types: self.types.clone(),
+            transparency_merge: self.transparency_merge,
+            warned_missing: Mutex::new(self.warned_missing.lock().unwrap().clone()),
+        }
+    }
+}
+
+impl VoxelRegistry {
+    /// A registry without any types. [`VoxelRegistry::default`] has the demo blocks instead.
+    pub fn new() -> Self {
+        Self {
+            types: Vec::new(),
+            transparency_merge: TransparencyMerge::default(),
+            warned_missing: Mutex::default(),
+        }
+    }
+
+    /// Registers `voxel_type` under `id`, replacing whatever was registered there before.
+    pub fn register<T: VoxelId>(&mut self, id: T, voxel_type: VoxelType) -> &mut Self {
+        debug!("registering voxel type {} as {:?}", voxel_type.name, id);
+        let index = id.index();
+        if index >= self.types.len() {
+            self.types.resize(index + 1, None);
+        }
+        self.types[index] = Some(voxel_type);
+        self
+    }
+
+    /// The type registered under `voxel`'s id, if any.
+    pub fn get<T: VoxelId>(&self, voxel: Voxel<T>) -> Option<&VoxelType> {
+        self.types.get(voxel.0.index())?.as_ref()
+    }
+
+    /// Like [`VoxelRegistry::get`], but ids that were never registered get [`MISSING_VOXEL_TYPE`]. The first time that
+    /// happens for an id a warning is logged.
+    pub fn get_or_missing<T: VoxelId>(&self, voxel: Voxel<T>) -> &VoxelType {
+        self.get(voxel).unwrap_or_else(|| {
+            if self.warned_missing.lock().unwrap().insert(voxel.0.index()) {
+                warn!(
+                    "voxel id {:?} has no registered type, it's drawn with the stone tile",
+                    voxel.0
+                );
+            }
+            &MISSING_VOXEL_TYPE
+        })
+    }
+
+    /// The texture array layers of the top, bottom and side faces of `voxel`, for
+    /// [`TextureLayout::Array`](crate::atlas::TextureLayout::Array). They're worked out from its tiles, so the atlas and
+    /// the array always agree.
+    pub fn texture_layers<T: VoxelId>(&self, voxel: Voxel<T>, atlas: &AtlasConfig) -> [u32; 3] {
+        let textures = self.get_or_missing(voxel).textures;
+        [textures.top, textures.bottom, textures.sides]
+            .map(|[column, row]| atlas.layer(column, row))
+    }
+
+    /// [`Voxel::EMPTY_VOXEL`] is always empty and ids that were never registered are treated as opaque.
+    #[inline]
+    pub fn visibility<T: VoxelId>(&self, voxel: Voxel<T>) -> VoxelVisibility {
+        if voxel.0 == T::EMPTY {
+            return VoxelVisibility::Empty;
+        }
+        self.get(voxel)
+            .map_or(VoxelVisibility::Opaque, |voxel_type| voxel_type.visibility)
+    }
+
+    /// The shape of `voxel`, [`VoxelShape::Full`] for unregistered ids.
+    pub fn shape<T: VoxelId>(&self, voxel: Voxel<T>) -> VoxelShape {
+        self.get(voxel)
+            .map_or(VoxelShape::Full, |voxel_type| voxel_type.shape)
+    }
+
+    /// Whether `voxel` gets a face towards the touching `neighbour`. This is the rule both meshers follow, with the
+    /// faces between translucent voxels picked by [`VoxelRegistry::transparency_merge`].
+    pub fn face_visible<T: VoxelId>(&self, voxel: Voxel<T>, neighbour: Voxel<T>) -> bool {
+        use VoxelVisibility::*;
+        match (self.visibility(voxel), self.visibility(neighbour)) {
+            (Empty, _) | (_, Opaque) => false,
+            (_, Empty) | (Opaque, Translucent) => true,
+            (Translucent, Translucent) => match self.transparency_merge {
+                TransparencyMerge::Never => true,
+                TransparencyMerge::SameId => voxel != neighbour,
+                TransparencyMerge::Always => false,
+            },
+        }
+    }
+}
+
+impl Default for VoxelRegistry {
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry
+            .register(
+                Voxel::A1_VOXEL.0,
+                VoxelType {
+                    name: "A1".to_string(),
+                    textures: FaceTextures::uniform([10.0, 10.0]),
+                    color: [0.6, 0.8, 1.0, 0.5],
+                    visibility: VoxelVisibility::Translucent,
+                    emissive_strength: 0.0,
+                    flowing: false,
+                    shape: VoxelShape::Full,
+                },
+            )
+            .register(
+                Voxel::A2_VOXEL.0,
+                VoxelType {
+                    name: "A2".to_string(),
+                    textures: FaceTextures::uniform([16.0, 16.0]),
+                    color: [0.8, 0.3, 0.3, 1.0],
+                    visibility: VoxelVisibility::Opaque,
+                    emissive_strength: 0.0,
+                    flowing: false,
+                    shape: VoxelShape::Full,
+                },
+            )
+            .register(
+                Voxel::STONE_VOXEL.0,
+                VoxelType {
+                    name: "Stone".to_string(),
+                    textures: FaceTextures::uniform([4.0, 4.0]),
+                    color: [0.5, 0.5, 0.5, 1.0],
+                    visibility: VoxelVisibility::Opaque,
+                    emissive_strength: 0.0,
+                    flowing: false,
+                    shape: VoxelShape::Full,
+                },
+            )
+            .register(
+                Voxel::DIRT_VOXEL.0,
+                VoxelType {
+                    name: "Dirt".to_string(),
+                    textures: FaceTextures::uniform([7.0, 7.0]),
+                    color: [0.45, 0.3, 0.15, 1.0],
+                    visibility: VoxelVisibility::Opaque,
+                    emissive_strength: 0.0,
+                    flowing: false,
+                    shape: VoxelShape::Full,
+                },
+            )
+            .register(
+                Voxel::GRASS_VOXEL.0,
+                VoxelType {
+                    name: "Grass".to_string(),
+                    textures: FaceTextures {
+                        top: [13.0, 13.0],
+                        bottom: [7.0, 7.0],
+                        sides: [13.0, 7.0],
+                    },
+                    color: [0.3, 0.65, 0.2, 1.0],
+                    visibility: VoxelVisibility::Opaque,
+                    emissive_strength: 0.0,
+                    flowing: false,
+                    shape: VoxelShape::Full,
+                },
+            )
+            .register(
+                Voxel::GLOWSTONE_VOXEL.0,
+                VoxelType {
+                    name: "Glowstone".to_string(),
+                    textures: FaceTextures::uniform([2.0, 2.0]),
+                    color: [1.0, 0.85, 0.4, 1.0],
+                    visibility: VoxelVisibility::Opaque,
+                    emissive_strength: 1.0,
+                    flowing: false,
+                    shape: VoxelShape::Full,
+                },
+            )
+            .register(
+                Voxel::WATER_VOXEL.0,
+                VoxelType {
+                    name: "Water".to_string(),
+                    textures: FaceTextures::uniform([10.0, 4.0]),
+                    color: [0.2, 0.4, 0.9, 0.6],
+                    visibility: VoxelVisibility::Translucent,
+                    emissive_strength: 0.0,
+                    flowing: true,
+                    shape: VoxelShape::Full,
+                },
+            )
+            .register(
+                Voxel::SAND_VOXEL.0,
+                VoxelType {
+                    name: "Sand".to_string(),
+                    textures: FaceTextures::uniform([1.0, 4.0]),
+                    color: [0.86, 0.8, 0.55, 1.0],
+                    visibility: VoxelVisibility::Opaque,
+                    emissive_strength: 0.0,
+                    flowing: false,
+                    shape: VoxelShape::Full,
+                },
+            )
+            .register(
+                Voxel::STONE_SLAB_VOXEL.0,
+                VoxelType {
+                    name: "Stone slab".to_string(),
+                    textures: FaceTextures::uniform([4.0, 4.0]),
+                    color: [0.55, 0.55, 0.55, 1.0],
+                    visibility: VoxelVisibility::Opaque,
+                    emissive_strength: 0.0,
+                    flowing: false,
+                    shape: VoxelShape::BottomSlab,
+                },
+            )
+            .register(
+                Voxel::SNOW_VOXEL.0,
+                VoxelType {
+                    name: "Snow".to_string(),
+                    textures: FaceTextures {
+                        top: [16.0, 1.0],
+                        bottom: [7.0, 7.0],
+                        sides: [16.0, 7.0],
+                    },
+                    color: [0.95, 0.95, 1.0, 1.0],
+                    visibility: VoxelVisibility::Opaque,
+                    emissive_strength: 0.0,
+                    flowing: false,
+                    shape: VoxelShape::Full,
+                },
+            );
+        registry
+    }
+}
+
+/// A voxel with what the registry says about it looked up already. This is what actually gets handed to block-mesh,
+/// since the plain [`Voxel`] id doesn't know its own visibility. It doesn't borrow the registry, so buffers of them can
+/// be kept between remeshes.
+#[derive(Clone, Copy)]
+pub struct RegisteredVoxel<T = u8> {
+    pub voxel: Voxel<T>,
+    /// What block-mesh sees. Partial voxels look empty to it, so the cubes around them keep their faces and the
+    /// partial voxels get none of their own from it.
+    pub visibility: VoxelVisibility,
+    pub transparency_merge: TransparencyMerge,
+}
+
+impl<T: VoxelId> RegisteredVoxel<T> {
+    /// Looks `voxel` up in `registry`.
+    pub fn new(voxel: Voxel<T>, registry: &VoxelRegistry) -> Self {
+        let visibility = match registry.shape(voxel) {
+            VoxelShape::Full => registry.visibility(voxel),
+            VoxelShape::BottomSlab => VoxelVisibility::Empty,
+        };
+        Self {
+            voxel,
+            visibility,
+            transparency_merge: registry.transparency_merge,
+        }
+    }
+
+    /// Looks every voxel up in `registry`, in the same order.
+    pub fn wrap_all(voxels: &[Voxel<T>], registry: &VoxelRegistry) -> Vec<Self> {
+        let mut wrapped = Vec::with_capacity(voxels.len());
+        Self::wrap_into(voxels, registry, &mut wrapped);
+        wrapped
+    }
+
+    /// [`RegisteredVoxel::wrap_all`] into `wrapped`, replacing what was in it but keeping its allocation.
+    pub fn wrap_into(voxels: &[Voxel<T>], registry: &VoxelRegistry, wrapped: &mut Vec<Self>) {
+        wrapped.clear();
+        wrapped.extend(voxels.iter().map(|&voxel| Self::new(voxel, registry)));
+    }
+}
+
+impl<T: VoxelId> MeshableVoxel for RegisteredVoxel<T> {
+    #[inline]
+    fn get_visibility(&self) -> VoxelVisibility {
+        self.visibility
+    }
+
+    /// Only asked for two translucent voxels, where it's the same rule as [`VoxelRegistry::face_visible`].
+    #[inline]
+    fn shows_face_to_translucent(&self, neighbour: &Self) -> bool {
+        match self.transparency_merge {
+            TransparencyMerge::Never => true,
+            TransparencyMerge::SameId => self.voxel != neighbour.voxel,
+            TransparencyMerge::Always => false,
+        }
+    }
+}
+
+impl<T: VoxelId> MergeVoxel for RegisteredVoxel<T> {
+    type MergeValue = T;
+    type MergeValueFacingNeighbour = T;
+
+    /// The id alone is enough even for blocks with different tiles per face like grass: `greedy_quads` meshes each of
+    /// the six face directions on its own, so a top face can never join a side face.
+    #[inline]
+    fn merge_value(&self) -> Self::MergeValue {
+        self.voxel.0
+    }
+    /// Only needs to tell neighbours apart, so the id is used as is. Anything that combines it with other data has to fit
+    /// every id of `T` without overflowing.
+    #[inline]
+    fn merge_value_facing_neighbour(&self) -> Self::MergeValueFacingNeighbour {
+        self.voxel.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meshing::{generate_quads, MeshingMode};
+    use crate::volume::VoxelVolume;
+    use block_mesh::RIGHT_HANDED_Y_UP_CONFIG;
+
+    /// An id the default registry leaves free, past [`Voxel::STONE_SLAB_VOXEL`], for a second kind of glass.
+    const GREEN_GLASS_VOXEL: Voxel = Voxel(Voxel::STONE_SLAB_VOXEL.0 + 1);
+
+    #[test]
+    fn voxel_faces_follow_the_quad_groups() {
+        use crate::meshing::CoordinateSystem;
+
+        for coordinates in [
+            CoordinateSystem::RightHandedYUp,
+            CoordinateSystem::LeftHandedYUp,
+            CoordinateSystem::RightHandedZUp,
+        ] {
+            for (index, face) in coordinates.quad_config().faces.iter().enumerate() {
+                let voxel_face = VoxelFace::from_index(index);
+                assert_eq!(voxel_face.index(), index);
+                assert_eq!(
+                    voxel_face.normal(),
+                    IVec3::from(face.signed_normal().to_array()),
+                    "{coordinates:?}"
+                );
+                assert_eq!(
+                    VoxelFace::from_normal(voxel_face.normal()),
+                    Some(voxel_face)
+                );
+            }
+        }
+        assert_eq!(VoxelFace::from_normal(IVec3::ZERO), None);
+
+        let grass = VoxelRegistry::default()
+            .get(Voxel::GRASS_VOXEL)
+            .unwrap()
+            .textures;
+        assert_eq!(grass.for_face(VoxelFace::Up), grass.top);
+        assert_eq!(grass.for_face(VoxelFace::Down), grass.bottom);
+        assert_eq!(grass.for_face(VoxelFace::North), grass.sides);
+    }
+
+    #[test]
+    fn high_voxel_ids_mesh_and_merge() {
+        let mut registry = VoxelRegistry::new();
+        registry.register(
+            200u8,
+            VoxelType {
+                name: "High".to_string(),
+                textures: FaceTextures::uniform([1.0, 1.0]),
+                color: [1.0; 4],
+                visibility: VoxelVisibility::Opaque,
+                emissive_strength: 0.0,
+                flowing: false,
+                shape: VoxelShape::Full,
+            },
+        );
+
+        // A 2x1x1 bar, whose four long sides should each become one quad.
+        let mut voxels = VoxelVolume::default();
+        voxels.set(1, 1, 1, Voxel(200));
+        voxels.set(2, 1, 1, Voxel(200));
+
+        let groups = generate_quads(
+            &voxels,
+            &registry,
+            MeshingMode::Greedy,
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+        );
+        let quads: Vec<_> = groups.iter().flatten().collect();
+        assert_eq!(quads.len(), 6);
+        let merged = quads
+            .iter()
+            .filter(|quad| quad.width * quad.height == 2)
+            .count();
+        assert_eq!(merged, 4);
+    }
+
+    #[test]
+    fn wide_voxel_ids_mesh_and_merge() {
+        use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+        use block_mesh::{greedy_quads, GreedyQuadsBuffer};
+
+        let mut registry = VoxelRegistry::new();
+        registry.register(
+            1000u16,
+            VoxelType {
+                name: "Wide".to_string(),
+                textures: FaceTextures::uniform([1.0, 1.0]),
+                color: [1.0; 4],
+                visibility: VoxelVisibility::Opaque,
+                emissive_strength: 0.0,
+                flowing: false,
+                shape: VoxelShape::Full,
+            },
+        );
+        assert_eq!(registry.get(Voxel(1000u16)).unwrap().name, "Wide");
+        // Ids that only differ above the low byte must not be taken for each other.
+        assert!(registry.get(Voxel(1000u16 & 0xff)).is_none());
+        assert_eq!(registry.visibility(Voxel(0u16)), VoxelVisibility::Empty);
+
+        type Shape = ConstShape3u32<4, 3, 3>;
+        let mut voxels = vec![Voxel(0u16); Shape::USIZE];
+        voxels[Shape::linearize([1, 1, 1]) as usize] = Voxel(1000);
+        voxels[Shape::linearize([2, 1, 1]) as usize] = Voxel(1000);
+        let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+        greedy_quads(
+            &RegisteredVoxel::wrap_all(&voxels, &registry),
+            &Shape {},
+            [0; 3],
+            [3, 2, 2],
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+            &mut buffer,
+        );
+        assert_eq!(buffer.quads.num_quads(), 6);
+    }
+
+    #[test]
+    fn each_missing_id_is_warned_about_once() {
+        let registry = VoxelRegistry::default();
+        for id in [200u8, 200, 201, 200] {
+            assert!(std::ptr::eq(
+                registry.get_or_missing(Voxel(id)),
+                &MISSING_VOXEL_TYPE
+            ));
+        }
+        assert!(std::ptr::eq(
+            registry.get_or_missing(Voxel(200u16)),
+            &MISSING_VOXEL_TYPE
+        ));
+        assert_eq!(
+            *registry.warned_missing.lock().unwrap(),
+            HashSet::from([200, 201])
+        );
+        // Another registry warns about its own missing ids.
+        assert!(VoxelRegistry::default()
+            .warned_missing
+            .lock()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn translucent_faces_follow_the_merge_policy() {
+        let mut registry = VoxelRegistry::default();
+        assert!(registry.get(GREEN_GLASS_VOXEL).is_none());
+        registry.register(
+            GREEN_GLASS_VOXEL.0,
+            VoxelType {
+                name: "Green glass".to_string(),
+                ..registry.get(Voxel::A1_VOXEL).unwrap().clone()
+            },
+        );
+        // Two glass voxels of the same id, with a voxel of the other glass next to them.
+        let mut voxels = VoxelVolume::default();
+        voxels.set(1, 1, 1, Voxel::A1_VOXEL);
+        voxels.set(2, 1, 1, Voxel::A1_VOXEL);
+        voxels.set(3, 1, 1, GREEN_GLASS_VOXEL);
+
+        let mut counts = Vec::new();
+        for policy in [
+            TransparencyMerge::Always,
+            TransparencyMerge::SameId,
+            TransparencyMerge::Never,
+        ] {
+            registry.transparency_merge = policy;
+            for mode in [MeshingMode::Simple, MeshingMode::Greedy] {
+                let groups =
+                    generate_quads(&voxels, &registry, mode, &RIGHT_HANDED_Y_UP_CONFIG.faces);
+                let faces: u32 = groups
+                    .iter()
+                    .flatten()
+                    .map(|quad| quad.width * quad.height)
+                    .sum();
+                counts.push((policy, mode, faces));
+            }
+        }
+        // Always leaves the outside of the 3x1x1 bar. SameId adds a face on each side of the wall between the two
+        // types, and Never another two between the voxels of the same id.
+        for (policy, mode, faces) in counts {
+            let expected = match policy {
+                TransparencyMerge::Always => 14,
+                TransparencyMerge::SameId => 16,
+                TransparencyMerge::Never => 18,
+            };
+            assert_eq!(faces, expected, "{policy:?} {mode:?}");
+        }
+
+        // The same id still merges into long quads when the policy culls the faces between its voxels.
+        registry.transparency_merge = TransparencyMerge::SameId;
+        let groups = generate_quads(
+            &voxels,
+            &registry,
+            MeshingMode::Greedy,
+            &RIGHT_HANDED_Y_UP_CONFIG.faces,
+        );
+        assert!(groups
+            .iter()
+            .flatten()
+            .any(|quad| quad.width * quad.height == 2));
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::voxel::Voxel;
+
+/// What [`voxelize_triangles`] does with the inside of a mesh.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VoxelizeFill {
+    /// Only the voxels the triangles pass through, so the model is hollow.
+    Shell,
+    /// The shell and everything it encloses. A mesh with holes in it can't enclose anything, and stays a shell.
+    #[default]
+    Solid,
+}
+
+/// Reads a Wavefront OBJ file and turns it into voxels `resolution` voxels across at its largest, see
+/// [`voxelize_triangles`]. Every voxel is stone.
+///
+/// The voxels come back as a dense array and its size rather than a [`crate::volume::VoxelVolume`], since a volume is
+/// a single padded chunk of a fixed size and most models don't fit into one. It's the same form
+/// [`crate::vox::load_vox`] loads models in, so both go into the grid through [`crate::vox::VoxModel`]. `fill` picks
+/// whether the model comes out hollow or solid.
+pub fn voxelize_obj(
+    path: &str,
+    resolution: u32,
+    fill: VoxelizeFill,
+) -> Result<(Vec<Voxel>, UVec3), String> {
+    let text =
+        std::fs::read_to_string(path).map_err(|err| format!("failed to load {path}: {err}"))?;
+    let triangles = parse_obj(&text).map_err(|err| format!("failed to load {path}: {err}"))?;
+    if triangles.is_empty() {
+        return Err(format!("failed to load {path}: it has no faces"));
+    }
+    voxelize_triangles(&triangles, resolution, fill, Voxel::STONE_VOXEL)
+        .map_err(|err| format!("failed to load {path}: {err}"))
+}
+
+/// The triangles of the faces of an OBJ file, with faces of more than three corners split into a fan. Only the
+/// positions are read; texture coordinates, normals, groups and materials are skipped.
+pub fn parse_obj(text: &str) -> Result<Vec<[Vec3; 3]>, String> {
+    let mut positions = Vec::new();
+    let mut triangles = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let error = |message: &str| format!("line {}: {message}", number + 1);
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("v") => {
+                let mut coordinate = || -> Result<f32, String> {
+                    words
+                        .next()
+                        .and_then(|word| word.parse().ok())
+                        .ok_or_else(|| error("bad vertex"))
+                };
+                positions.push(Vec3::new(coordinate()?, coordinate()?, coordinate()?));
+            }
+            Some("f") => {
+                // Indices count from 1, or back from the last vertex so far if they're negative.
+                let corners = words
+                    .map(|word| {
+                        let index: i64 = word
+                            .split('/')
+                            .next()
+                            .and_then(|index| index.parse().ok())
+                            .ok_or_else(|| error("bad face"))?;
+                        let index = if index < 0 {
+                            positions.len() as i64 + index
+                        } else {
+                            index - 1
+                        };
+                        usize::try_from(index)
+                            .ok()
+                            .and_then(|index| positions.get(index).copied())
+                            .ok_or_else(|| error("face refers to a missing vertex"))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if corners.len() < 3 {
+                    return Err(error("face with fewer than three corners"));
+                }
+                for i in 1..corners.len() - 1 {
+                    triangles.push([corners[0], corners[i], corners[i + 1]]);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(triangles)
+}
+
+/// Whether `triangle` touches the voxel cell whose minimum corner is `cell`, by the separating axis test of
+/// Akenine-Möller: the two don't touch if there's an axis their projections don't overlap on, and only the axes of the
+/// cell, the normal of the triangle and the crosses of both sets of edges need to be tried.
+fn triangle_touches_cell(triangle: &[Vec3; 3], cell: Vec3) -> bool {
+    let half = Vec3::splat(0.5);
+    let [a, b, c] = triangle.map(|corner| corner - (cell + half));
+    let separated = |axis: Vec3| {
+        let [pa, pb, pc] = [a, b, c].map(|corner| corner.dot(axis));
+        let radius = half.dot(axis.abs());
+        pa.min(pb).min(pc) > radius || pa.max(pb).max(pc) < -radius
+    };
+    let edges = [b - a, c - b, a - c];
+    let axes = [Vec3::X, Vec3::Y, Vec3::Z];
+    !(axes.into_iter().any(separated)
+        || separated(edges[0].cross(edges[1]))
+        || edges
+            .into_iter()
+            .any(|edge| axes.into_iter().any(|axis| separated(axis.cross(edge)))))
+}
+
+/// Rasterizes `triangles` into a dense array of `voxel`, scaled so the longest side of their bounding box is
+/// `resolution` voxels. Every cell a triangle touches is filled, which keeps thin parts of the mesh connected. With
+/// [`VoxelizeFill::Solid`], the empty cells that can't be reached from outside the shell are filled as well.
+///
+/// The voxels are indexed `x + size.x * (y + size.y * z)` like [`crate::vox::load_vox`], so the model can be placed
+/// with [`crate::vox::place_model`]. The mesh keeps its axes, so a Y up model stays upright. A resolution too high for
+/// the voxels to fit into memory is an error.
+pub fn voxelize_triangles(
+    triangles: &[[Vec3; 3]],
+    resolution: u32,
+    fill: VoxelizeFill,
+    voxel: Voxel,
+) -> Result<(Vec<Voxel>, UVec3), String> {
+    let corners = || triangles.iter().flatten().copied();
+    let min = corners().fold(Vec3::splat(f32::MAX), Vec3::min);
+    let max = corners().fold(Vec3::splat(f32::MIN), Vec3::max);
+    let scale = resolution.max(1) as f32 / (max - min).max_element().max(f32::EPSILON);
+    let size = ((max - min) * scale).ceil().as_uvec3().max(UVec3::ONE);
+    let count = (size.x as usize)
+        .checked_mul(size.y as usize)
+        .and_then(|count| count.checked_mul(size.z as usize))
+        .ok_or_else(|| format!("{} by {} by {} voxels are too many", size.x, size.y, size.z))?;
+    let index =
+        |p: UVec3| p.x as usize + size.x as usize * (p.y as usize + size.y as usize * p.z as usize);
+
+    let mut voxels = vec![Voxel::EMPTY_VOXEL; count];
+    for triangle in triangles {
+        let triangle = triangle.map(|corner| (corner - min) * scale);
+        let low = triangle[0].min(triangle[1]).min(triangle[2]).floor();
+        let high = triangle[0].max(triangle[1]).max(triangle[2]).floor();
+        // A triangle on the far side of the bounding box lies on the border between the last cells and the ones past
+        // the end, so it goes into the last ones.
+        let low = low.max(Vec3::ZERO).as_uvec3().min(size - 1);
+        let high = high.as_uvec3().min(size - 1);
+        for z in low.z..=high.z {
+            for y in low.y..=high.y {
+                for x in low.x..=high.x {
+                    let cell = UVec3::new(x, y, z);
+                    if triangle_touches_cell(&triangle, cell.as_vec3()) {
+                        voxels[index(cell)] = voxel;
+                    }
+                }
+            }
+        }
+    }
+
+    if fill == VoxelizeFill::Solid {
+        // Flood the outside from every empty cell on the border of the box; whatever the flood can't reach is inside.
+        let mut outside = vec![false; voxels.len()];
+        let mut queue: VecDeque<UVec3> = VecDeque::new();
+        let mut visit = |cell: UVec3, queue: &mut VecDeque<UVec3>| {
+            let i = index(cell);
+            if !outside[i] && voxels[i] == Voxel::EMPTY_VOXEL {
+                outside[i] = true;
+                queue.push_back(cell);
+            }
+        };
+        for z in 0..size.z {
+            for y in 0..size.y {
+                for x in 0..size.x {
+                    let cell = UVec3::new(x, y, z);
+                    if cell.cmpeq(UVec3::ZERO).any() || cell.cmpeq(size - 1).any() {
+                        visit(cell, &mut queue);
+                    }
+                }
+            }
+        }
+        while let Some(cell) = queue.pop_front() {
+            for offset in [
+                IVec3::X,
+                IVec3::Y,
+                IVec3::Z,
+                -IVec3::X,
+                -IVec3::Y,
+                -IVec3::Z,
+            ] {
+                let next = cell.as_ivec3() + offset;
+                if next.cmpge(IVec3::ZERO).all() && next.cmplt(size.as_ivec3()).all() {
+                    visit(next.as_uvec3(), &mut queue);
+                }
+            }
+        }
+        for (voxel_at, outside) in voxels.iter_mut().zip(outside) {
+            if !outside {
+                *voxel_at = voxel;
+            }
+        }
+    }
+    Ok((voxels, size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A cube from 0 to 2 on every axis, with quads for faces and a few lines the parser should skip.
+    const CUBE: &str = "# a cube
+o cube
+v 0 0 0
+v 2 0 0
+v 2 2 0
+v 0 2 0
+v 0 0 2
+v 2 0 2
+v 2 2 2
+v 0 2 2
+vt 0 0
+vn 0 0 -1
+f 1/1/1 4/1/1 3/1/1 2/1/1
+f 5 6 7 8
+f 1 2 6 5
+f 4 8 7 3
+f 1 5 8 4
+f -7 -6 -2 -3
+";
+
+    #[test]
+    fn cubes_voxelize_into_shells_or_solids() {
+        let triangles = parse_obj(CUBE).unwrap();
+        assert_eq!(triangles.len(), 12);
+
+        let count = |voxels: &[Voxel]| voxels.iter().filter(|&&v| v != Voxel::EMPTY_VOXEL).count();
+        let (shell, size) =
+            voxelize_triangles(&triangles, 6, VoxelizeFill::Shell, Voxel::STONE_VOXEL).unwrap();
+        assert_eq!(size, UVec3::splat(6));
+        // The six faces of a 6³ cube, one voxel thick, with the middle left empty.
+        assert_eq!(count(&shell), 6 * 6 * 6 - 4 * 4 * 4);
+        let (solid, _) =
+            voxelize_triangles(&triangles, 6, VoxelizeFill::Solid, Voxel::STONE_VOXEL).unwrap();
+        assert_eq!(count(&solid), 6 * 6 * 6);
+
+        // A single slanted triangle is a shell either way, since it encloses nothing.
+        let slanted = [[
+            Vec3::ZERO,
+            Vec3::new(4.0, 1.0, 0.0),
+            Vec3::new(0.0, 3.0, 4.0),
+        ]];
+        let (shell, _) =
+            voxelize_triangles(&slanted, 8, VoxelizeFill::Shell, Voxel::STONE_VOXEL).unwrap();
+        let (solid, _) =
+            voxelize_triangles(&slanted, 8, VoxelizeFill::Solid, Voxel::STONE_VOXEL).unwrap();
+        assert_eq!(shell, solid);
+        assert!(count(&shell) > 8);
+
+        // Too many voxels to count, let alone allocate.
+        assert!(voxelize_triangles(
+            &triangles,
+            u32::MAX,
+            VoxelizeFill::Shell,
+            Voxel::STONE_VOXEL
+        )
+        .is_err());
+
+        assert!(parse_obj("f 1 2 3").is_err());
+        assert!(parse_obj("v 0 0 zero").is_err());
+    }
+}
@@ -0,0 +1,40 @@
+use bevy::prelude::*;
+
+/// How the texture of [`crate::voxel::VoxelType::flowing`] voxels moves.
+#[derive(Resource, Clone, Debug)]
+pub struct WaterSettings {
+    /// How fast the texture scrolls along the face's U and V directions, in voxels per second.
+    pub scroll_speed: Vec2,
+}
+
+impl Default for WaterSettings {
+    fn default() -> Self {
+        Self {
+            scroll_speed: Vec2::new(0.0, 0.4),
+        }
+    }
+}
+
+impl WaterSettings {
+    /// The [`crate::material::VoxelMaterial::uv_offset`] after `seconds`. It's wrapped to a single voxel, which looks
+    /// the same and keeps the offset precise however long the demo runs.
+    pub fn uv_offset(&self, seconds: f64) -> Vec2 {
+        let offset = self.scroll_speed.as_dvec2() * seconds;
+        (offset - offset.floor()).as_vec2()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uv_offset_wraps_to_one_voxel() {
+        let settings = WaterSettings {
+            scroll_speed: Vec2::new(0.5, -0.25),
+        };
+        assert_eq!(settings.uv_offset(0.0), Vec2::ZERO);
+        assert_eq!(settings.uv_offset(1.0), Vec2::new(0.5, 0.75));
+        assert_eq!(settings.uv_offset(1e9 + 1.0), Vec2::new(0.5, 0.75));
+    }
+}
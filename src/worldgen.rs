@@ -0,0 +1,345 @@
+use bevy::prelude::*;
+use noise::{NoiseFn, Perlin};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::chunk::{ChunkGrid, CHUNK_SIZE};
+use crate::input::{Action, KeyBindings};
+use crate::voxel::Voxel;
+
+/// How the chunk grid gets filled. Press T to switch and regenerate the world.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WorldGenerator {
+    /// Every voxel gets a random id, which is good for finding meshing edge cases.
+    #[default]
+    Random,
+    /// Rolling hills from a Perlin heightmap, covered in grass, sand or snow by their [`Biome`], see [`TerrainConfig`].
+    Terrain,
+}
+
+/// The seed of [`random_fill`], so a random world that shows a meshing bug can be made again. It's random unless one
+/// is inserted, or given with `--seed`, and logged when the world is built.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RandomSeed(pub u64);
+
+impl Default for RandomSeed {
+    fn default() -> Self {
+        Self(rand::random())
+    }
+}
+
+/// Parameters of the heightmap used by [`generate_terrain`], and of the caves carved into it.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct TerrainConfig {
+    pub seed: u32,
+    /// How quickly the height changes along X and Z, in noise cycles per voxel.
+    pub frequency: f64,
+    /// How far the surface can rise above or sink below `base_height`, in voxels.
+    pub amplitude: f64,
+    pub base_height: f64,
+    /// Air below this height is filled with water, which turns the valleys into lakes.
+    pub water_level: u32,
+    /// How quickly the biomes change, in noise cycles per voxel. Much lower than `frequency`, so a biome spans a few
+    /// hills.
+    pub biome_frequency: f64,
+    /// How ragged the borders between biomes are, see [`BiomeMap`]. 0 gives smooth borders.
+    pub biome_blend: f64,
+    /// Where [`carve_caves`] hollows out the ground. Lower values give more and larger caves, 1 gives none.
+    pub cave_threshold: f64,
+}
+
+impl Default for TerrainConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            frequency: 0.03,
+            amplitude: 8.0,
+            base_height: 16.0,
+            water_level: 14,
+            biome_frequency: 0.02,
+            biome_blend: 0.15,
+            cave_threshold: 0.35,
+        }
+    }
+}
+
+/// The kinds of landscape the terrain is made of. They only differ in the voxels on and right below the surface.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Biome {
+    Plains,
+    Desert,
+    Snow,
+}
+
+impl Biome {
+    /// The voxel on top of the ground.
+    pub fn surface(self) -> Voxel {
+        match self {
+            Biome::Plains => Voxel::GRASS_VOXEL,
+            Biome::Desert => Voxel::SAND_VOXEL,
+            Biome::Snow => Voxel::SNOW_VOXEL,
+        }
+    }
+
+    /// The voxels of the few layers between the surface and the stone.
+    pub fn subsurface(self) -> Voxel {
+        match self {
+            Biome::Plains | Biome::Snow => Voxel::DIRT_VOXEL,
+            Biome::Desert => Voxel::SAND_VOXEL,
+        }
+    }
+}
+
+/// Picks the [`Biome`] of each column from a second, low frequency noise, with deserts at the low end, snow at the
+/// high end and plains in between. A third noise of a higher frequency is added on top, scaled by
+/// [`TerrainConfig::biome_blend`], so the biomes reach into each other in patches instead of meeting along a smooth
+/// line.
+pub struct BiomeMap {
+    noise: Perlin,
+    detail: Perlin,
+    frequency: f64,
+    blend: f64,
+}
+
+impl BiomeMap {
+    /// Noise below minus this gives deserts, and above it snow.
+    const BORDER: f64 = 0.2;
+
+    pub fn new(config: &TerrainConfig) -> Self {
+        Self {
+            noise: Perlin::new(config.seed.wrapping_add(1)),
+            detail: Perlin::new(config.seed.wrapping_add(2)),
+            frequency: config.biome_frequency,
+            blend: config.biome_blend,
+        }
+    }
+
+    /// The biome of the column at world `x` and `z`.
+    pub fn biome_at(&self, x: u32, z: u32) -> Biome {
+        let point = [x as f64 * self.frequency, z as f64 * self.frequency];
+        let detail = self.detail.get(point.map(|p| p * 8.0));
+        let value = self.noise.get(point) + detail * self.blend;
+        if value < -Self::BORDER {
+            Biome::Desert
+        } else if value > Self::BORDER {
+            Biome::Snow
+        } else {
+            Biome::Plains
+        }
+    }
+}
+
+/// Overwrites every voxel of the grid using `generator`.
+pub fn generate_world(
+    grid: &mut ChunkGrid,
+    generator: WorldGenerator,
+    config: &TerrainConfig,
+    seed: RandomSeed,
+) {
+    match generator {
+        WorldGenerator::Random => random_fill(grid, seed),
+        WorldGenerator::Terrain => {
+            let chunks_per_axis = grid.chunks_per_axis();
+            for z in 0..chunks_per_axis {
+                for y in 0..chunks_per_axis {
+                    for x in 0..chunks_per_axis {
+                        generate_terrain(grid, UVec3::new(x, y, z), config);
+                    }
+                }
+            }
+            carve_caves(grid, config.seed.wrapping_add(3), config.cave_threshold);
+        }
+    }
+}
+
+/// Switches the generator when T is pressed and picks a fresh [`RandomSeed`] when R is, regenerating the world in
+/// place. It's also regenerated whenever the config changes. The new seed is logged and shown in the diagnostics
+/// overlay, so a world with an interesting artifact can be brought back with `--seed`.
+pub fn regenerate_world_system(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    config: Res<TerrainConfig>,
+    mut seed: ResMut<RandomSeed>,
+    mut generator: ResMut<WorldGenerator>,
+    mut grid: ResMut<ChunkGrid>,
+) {
+    if bindings.just_pressed(&keys, Action::SwitchGenerator) {
+        *generator = match *generator {
+            WorldGenerator::Random => WorldGenerator::Terrain,
+            WorldGenerator::Terrain => WorldGenerator::Random,
+        };
+        info!("world generator: {:?}", *generator);
+    } else if bindings.just_pressed(&keys, Action::NewSeed) {
+        *seed = RandomSeed(rand::random());
+        info!("random fill seed: {}", seed.0);
+    } else if config.is_added() || !config.is_changed() {
+        return;
+    }
+    generate_world(&mut grid, *generator, &config, *seed);
+}
+
+/// Fills the whole grid with random voxel ids, with the odd glowstone in between. The same seed always gives the same
+/// voxels.
+pub fn random_fill(grid: &mut ChunkGrid, seed: RandomSeed) {
+    let world_size = grid.world_size();
+    let mut rng = StdRng::seed_from_u64(seed.0);
+    for z in 0..world_size.z {
+        for y in 0..world_size.y {
+            for x in 0..world_size.x {
+                let voxel = if rng.gen_ratio(1, 50) {
+                    Voxel::GLOWSTONE_VOXEL
+                } else {
+                    Voxel(rng.gen_range(0..3))
+                };
+                grid.set_voxel(UVec3::new(x, y, z), voxel);
+            }
+        }
+    }
+}
+
+/// Fills the chunk at `coord` with terrain: a layer of the [`Biome::surface`] on top, a few voxels of the
+/// [`Biome::subsurface`] below it and stone underneath. Valleys deeper than [`TerrainConfig::water_level`] are flooded.
+///
+/// The heightmap is sampled in world space from the chunk's offset in the grid, so generating every chunk gives one
+/// continuous landscape.
+pub fn generate_terrain(grid: &mut ChunkGrid, coord: UVec3, config: &TerrainConfig) {
+    let perlin = Perlin::new(config.seed);
+    let biomes = BiomeMap::new(config);
+    let offset = coord * CHUNK_SIZE;
+
+    for z in offset.z..offset.z + CHUNK_SIZE.z {
+        for x in offset.x..offset.x + CHUNK_SIZE.x {
+            let noise = perlin.get([x as f64 * config.frequency, z as f64 * config.frequency]);
+            let height = (config.base_height + noise * config.amplitude).round() as i64;
+            let biome = biomes.biome_at(x, z);
+
+            for y in offset.y..offset.y + CHUNK_SIZE.y {
+                let depth = height - y as i64;
+                let voxel = match depth {
+                    i64::MIN..=0 if y < config.water_level => Voxel::WATER_VOXEL,
+                    i64::MIN..=0 => Voxel::EMPTY_VOXEL,
+                    1 => biome.surface(),
+                    2..=4 => biome.subsurface(),
+                    _ => Voxel::STONE_VOXEL,
+                };
+                grid.set_voxel(UVec3::new(x, y, z), voxel);
+            }
+        }
+    }
+}
+
+/// How quickly the cave noise of [`carve_caves`] changes, in noise cycles per voxel, which sets how wide the caves are.
+const CAVE_FREQUENCY: f64 = 0.08;
+
+/// How many voxels of ground [`carve_caves`] leaves between the surface and the caves, so the surface stays whole.
+const CAVE_CRUST: u32 = 4;
+
+/// Empties the ground wherever a 3D Perlin noise rises above `threshold`, which hollows it out into closed caves. The
+/// noise stays between -1 and 1, so a threshold of 1 carves nothing and lower ones carve more. Only voxels at least
+/// [`CAVE_CRUST`] below the top of their column are carved, and water is left alone.
+///
+/// The caves are all inside the ground, so they're only seen with the cross section or the fly camera, but they give
+/// the mesher faces that point inwards and concave corners for the AO.
+pub fn carve_caves(grid: &mut ChunkGrid, seed: u32, threshold: f64) {
+    let perlin = Perlin::new(seed);
+    let size = grid.world_size();
+    let is_ground = |voxel: Voxel| voxel != Voxel::EMPTY_VOXEL && voxel != Voxel::WATER_VOXEL;
+    for z in 0..size.z {
+        for x in 0..size.x {
+            let Some(top) = (0..size.y)
+                .rev()
+                .find(|&y| is_ground(grid.voxel(UVec3::new(x, y, z))))
+            else {
+                continue;
+            };
+            for y in 0..(top + 1).saturating_sub(CAVE_CRUST) {
+                let position = UVec3::new(x, y, z);
+                let point = position.as_dvec3() * CAVE_FREQUENCY;
+                if is_ground(grid.voxel(position)) && perlin.get(point.to_array()) > threshold {
+                    grid.set_voxel(position, Voxel::EMPTY_VOXEL);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::utils::HashSet;
+
+    fn random_grid(seed: u64) -> ChunkGrid {
+        let mut grid = ChunkGrid::new(1);
+        random_fill(&mut grid, RandomSeed(seed));
+        grid
+    }
+
+    #[test]
+    fn random_fill_repeats_for_a_seed() {
+        let same = |a: &ChunkGrid, b: &ChunkGrid| {
+            a.chunks()
+                .zip(b.chunks())
+                .all(|((_, a), (_, b))| a.voxels == b.voxels)
+        };
+        assert!(same(&random_grid(7), &random_grid(7)));
+        assert!(!same(&random_grid(7), &random_grid(8)));
+    }
+
+    #[test]
+    fn terrain_surface_follows_the_biomes() {
+        let config = TerrainConfig::default();
+        let biomes = BiomeMap::new(&config);
+        let found: HashSet<Biome> = (0..400)
+            .flat_map(|z| (0..400).map(move |x| (x, z)))
+            .map(|(x, z)| biomes.biome_at(x, z))
+            .collect();
+        assert_eq!(found.len(), 3);
+
+        let mut grid = ChunkGrid::new(2);
+        generate_world(&mut grid, WorldGenerator::Terrain, &config, RandomSeed(0));
+        let size = grid.world_size();
+        for z in 0..size.z {
+            for x in 0..size.x {
+                let top = (0..size.y)
+                    .rev()
+                    .map(|y| grid.voxel(UVec3::new(x, y, z)))
+                    .find(|&voxel| voxel != Voxel::EMPTY_VOXEL && voxel != Voxel::WATER_VOXEL);
+                assert_eq!(top, Some(biomes.biome_at(x, z).surface()));
+            }
+        }
+    }
+
+    #[test]
+    fn caves_hollow_out_the_ground_below_the_surface() {
+        let terrain = |cave_threshold| {
+            let mut grid = ChunkGrid::new(2);
+            let config = TerrainConfig {
+                cave_threshold,
+                ..default()
+            };
+            generate_world(&mut grid, WorldGenerator::Terrain, &config, RandomSeed(0));
+            grid
+        };
+        let solid = terrain(1.0);
+        let caves = terrain(0.2);
+        let size = solid.world_size();
+        let mut carved = 0;
+        for z in 0..size.z {
+            for x in 0..size.x {
+                for y in 0..size.y {
+                    let position = UVec3::new(x, y, z);
+                    let (before, after) = (solid.voxel(position), caves.voxel(position));
+                    if before == after {
+                        continue;
+                    }
+                    // Only ground turns into air, and never right below the surface.
+                    assert_eq!(after, Voxel::EMPTY_VOXEL);
+                    assert!((y + 1..y + CAVE_CRUST)
+                        .all(|above| solid.voxel(UVec3::new(x, above, z)) != Voxel::EMPTY_VOXEL));
+                    carved += 1;
+                }
+            }
+        }
+        assert!(carved > 100, "{carved}");
+    }
+}